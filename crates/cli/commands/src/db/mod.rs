@@ -16,6 +16,7 @@ mod copy;
 mod diff;
 mod get;
 mod list;
+mod peers;
 mod prune_checkpoints;
 mod repair_trie;
 mod settings;
@@ -77,6 +78,8 @@ pub enum Subcommands {
     AccountStorage(account_storage::Command),
     /// Gets account state and storage at a specific block
     State(state::Command),
+    /// Inspects and maintains persisted peers files
+    Peers(peers::Command),
 }
 
 impl<C: ChainSpecParser<ChainSpec: EthChainSpec + EthereumHardforks>> Command<C> {
@@ -97,6 +100,11 @@ impl<C: ChainSpecParser<ChainSpec: EthChainSpec + EthereumHardforks>> Command<C>
             };
         }
 
+        // peers files are handled standalone, without a database
+        if let Subcommands::Peers(command) = self.command {
+            return command.execute()
+        }
+
         let data_dir = self.env.datadir.clone().resolve_datadir(self.env.chain.chain());
         let db_path = data_dir.db();
         let static_files_path = data_dir.static_files();
@@ -218,7 +226,7 @@ impl<C: ChainSpecParser<ChainSpec: EthChainSpec + EthereumHardforks>> Command<C>
             }
             Subcommands::StageCheckpoints(command) => {
                 db_exec!(self.env, tool, N, command.access_rights(), {
-                    command.execute(&tool)?;
+                    command.execute(&tool).await?;
                 });
             }
             Subcommands::AccountStorage(command) => {
@@ -231,6 +239,7 @@ impl<C: ChainSpecParser<ChainSpec: EthChainSpec + EthereumHardforks>> Command<C>
                     command.execute(&tool)?;
                 });
             }
+            Subcommands::Peers(_) => unreachable!("handled above"),
         }
 
         Ok(())
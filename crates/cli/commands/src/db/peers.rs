@@ -0,0 +1,176 @@
+//! `reth db peers` command for inspecting and maintaining persisted peers files
+
+use clap::{Parser, Subcommand};
+use reth_network::types::{compact_persisted, LenientPersistedPeers, PeerKind};
+use std::path::PathBuf;
+
+/// `reth db peers` subcommand
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Validates a persisted peers file without loading the node
+    Validate(ValidateArgs),
+    /// Rewrites a persisted peers file without placeholder and duplicate entries
+    Compact(CompactArgs),
+}
+
+#[derive(Debug, Parser)]
+struct ValidateArgs {
+    /// Path to the persisted peers file
+    path: PathBuf,
+
+    /// Succeed even if the file contains corrupt entries
+    #[arg(long)]
+    lenient: bool,
+}
+
+#[derive(Debug, Parser)]
+struct CompactArgs {
+    /// Path to the persisted peers file
+    path: PathBuf,
+
+    /// Also remove entries that can't be decoded, which are kept by default
+    #[arg(long)]
+    drop_corrupt: bool,
+}
+
+impl Command {
+    /// Execute the command
+    pub fn execute(self) -> eyre::Result<()> {
+        match self.command {
+            Subcommands::Validate(args) => args.execute(),
+            Subcommands::Compact(args) => args.execute(),
+        }
+    }
+}
+
+impl ValidateArgs {
+    fn execute(self) -> eyre::Result<()> {
+        let loaded = LenientPersistedPeers::from_file(&self.path)?;
+
+        println!("Valid entries: {}", loaded.peers.len());
+        for kind in [PeerKind::Basic, PeerKind::Static, PeerKind::Trusted, PeerKind::Observer] {
+            let count = loaded.peers.iter().filter(|peer| peer.kind == kind).count();
+            println!("  {}: {count}", kind.as_str());
+        }
+        println!("Invalid entries: {}", loaded.invalid.len());
+        for (index, err) in &loaded.invalid {
+            println!("  entry {index}: {err}");
+        }
+
+        eyre::ensure!(
+            self.lenient || loaded.is_valid(),
+            "{} corrupt entries in {}, pass --lenient to ignore them",
+            loaded.invalid.len(),
+            self.path.display()
+        );
+
+        Ok(())
+    }
+}
+
+impl CompactArgs {
+    fn execute(self) -> eyre::Result<()> {
+        let stats = compact_persisted(&self.path, self.drop_corrupt)?;
+        println!("Wrote {} entries to {}", stats.written, self.path.display());
+        println!("Removed placeholders: {}", stats.tombstones);
+        println!("Removed duplicates: {}", stats.duplicates);
+        if self.drop_corrupt {
+            println!("Removed corrupt entries: {}", stats.dropped_corrupt);
+        } else if stats.kept_corrupt > 0 {
+            println!(
+                "Kept {} corrupt entries, pass --drop-corrupt to remove them",
+                stats.kept_corrupt
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(path: &std::path::Path, lenient: bool) -> eyre::Result<()> {
+        let mut args = vec!["peers".to_string(), "validate".to_string()];
+        args.push(path.display().to_string());
+        if lenient {
+            args.push("--lenient".to_string());
+        }
+        Command::try_parse_from(args)?.execute()
+    }
+
+    #[test]
+    fn validate_mixed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let valid = dir.path().join("valid.json");
+        std::fs::write(
+            &valid,
+            r#"[
+                {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301","kind":"trusted","reputation":0},
+                "enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a1@10.3.58.6:30303"
+            ]"#,
+        )
+        .unwrap();
+        validate(&valid, false).unwrap();
+
+        let mixed = dir.path().join("mixed.json");
+        std::fs::write(
+            &mixed,
+            r#"[
+                {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301","kind":"static","reputation":0},
+                {"record":"not an enode","kind":"basic","reputation":0}
+            ]"#,
+        )
+        .unwrap();
+        assert!(validate(&mixed, false).is_err());
+        validate(&mixed, true).unwrap();
+
+        // not a peers file at all, fails regardless of leniency
+        let corrupt = dir.path().join("corrupt.json");
+        std::fs::write(&corrupt, "{").unwrap();
+        assert!(validate(&corrupt, true).is_err());
+    }
+
+    #[test]
+    fn compact_removes_duplicates_and_tombstones() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("peers.json");
+        std::fs::write(
+            &path,
+            r#"[
+                {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303","kind":"basic","reputation":-100},
+                null,
+                {"record":"not an enode","kind":"basic","reputation":0},
+                {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a1@10.3.58.6:30303","kind":"static","reputation":0},
+                {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303","kind":"basic","reputation":-200}
+            ]"#,
+        )
+        .unwrap();
+
+        let path_arg = path.display().to_string();
+        Command::try_parse_from(["peers", "compact", &path_arg]).unwrap().execute().unwrap();
+
+        // the corrupt entry is kept by default
+        let compacted = LenientPersistedPeers::from_file(&path).unwrap();
+        assert_eq!(compacted.invalid.len(), 1);
+        assert_eq!(compacted.invalid[0].0, 0);
+        assert_eq!(compacted.peers.len(), 2);
+        assert_eq!(compacted.peers[0].kind, PeerKind::Static);
+        assert_eq!(compacted.peers[1].reputation, -200);
+        assert!(validate(&path, false).is_err());
+
+        let args = ["peers", "compact", &path_arg, "--drop-corrupt"];
+        Command::try_parse_from(args).unwrap().execute().unwrap();
+        let compacted = LenientPersistedPeers::from_file(&path).unwrap();
+        assert!(compacted.is_valid());
+        assert_eq!(compacted.peers.len(), 2);
+        validate(&path, false).unwrap();
+    }
+}
@@ -1,12 +1,29 @@
 //! `reth db stage-checkpoints` command for viewing and setting stage checkpoint values.
 
+use alloy_consensus::BlockHeader;
+use alloy_primitives::BlockNumber;
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use reth_db_common::DbTool;
+use reth_db_common::{
+    check_checkpoint_consistency, missing_checkpoints, CheckpointInconsistency, DbTool,
+    STAGE_PREREQUISITES,
+};
+use reth_era::{
+    e2s::{file::E2StoreReader, types::IndexEntry},
+    era1::types::group::{BlockIndex, BLOCK_INDEX},
+};
 use reth_provider::{
-    providers::ProviderNodeTypes, DBProvider, DatabaseProviderFactory, StageCheckpointReader,
-    StageCheckpointWriter,
+    providers::ProviderNodeTypes, BlockNumReader, DBProvider, DatabaseProviderFactory,
+    HeaderProvider, StageCheckpointReader, StageCheckpointWriter,
+};
+use reth_stages::{StageCheckpoint, StageId};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{File, OpenOptions},
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use reth_stages::StageId;
 
 use crate::common::AccessRights;
 
@@ -21,45 +38,214 @@ impl Command {
     /// Returns database access rights required for the command.
     pub fn access_rights(&self) -> AccessRights {
         match &self.command {
-            Subcommands::Get { .. } => AccessRights::RO,
-            Subcommands::Set(_) => AccessRights::RW,
+            Subcommands::Get { .. } |
+            Subcommands::Verify { .. } |
+            Subcommands::Record { .. } |
+            Subcommands::PruneHorizon |
+            Subcommands::Assert { .. } => AccessRights::RO,
+            Subcommands::Set(_) | Subcommands::FromManifest { .. } => AccessRights::RW,
         }
     }
 
     /// Execute the command
-    pub fn execute<N: ProviderNodeTypes>(self, tool: &DbTool<N>) -> eyre::Result<()> {
+    pub async fn execute<N: ProviderNodeTypes>(self, tool: &DbTool<N>) -> eyre::Result<()> {
+        match self.command {
+            // records until interrupted
+            Subcommands::Record { out, interval } => Self::record(tool, &out, interval).await,
+            command => Self { command }.execute_blocking(tool),
+        }
+    }
+
+    /// Executes the subcommands that complete on their own.
+    fn execute_blocking<N: ProviderNodeTypes>(self, tool: &DbTool<N>) -> eyre::Result<()> {
         match self.command {
-            Subcommands::Get { stage } => Self::get(tool, stage),
-            Subcommands::Set(args) => Self::set(tool, args),
+            Subcommands::Get { stage, remaining, tip, missing_only, block_time } => {
+                if missing_only {
+                    Self::get_missing(tool)
+                } else {
+                    Self::get(tool, stage, remaining, tip, block_time)
+                }
+            }
+            Subcommands::Set(args) => Self::set(tool, args).map(drop),
+            Subcommands::FromManifest { path } => Self::from_manifest(tool, &path),
+            Subcommands::Verify { era_dir } => Self::verify(tool, &era_dir),
+            Subcommands::Record { .. } => unreachable!("handled by execute"),
+            Subcommands::PruneHorizon => Self::prune_horizon(tool),
+            Subcommands::Assert { expected } => Self::assert(tool, &expected),
         }
     }
 
-    fn get<N: ProviderNodeTypes>(tool: &DbTool<N>, stage: Option<StageArg>) -> eyre::Result<()> {
+    fn get<N: ProviderNodeTypes>(
+        tool: &DbTool<N>,
+        stage: Option<StageArg>,
+        remaining: bool,
+        tip: Option<u64>,
+        block_time: bool,
+    ) -> eyre::Result<()> {
         let provider = tool.provider_factory.provider()?;
 
+        // An explicit tip implies `--remaining`; otherwise fall back to the latest header.
+        let tip = match tip {
+            Some(tip) => Some(tip),
+            None if remaining => Some(provider.last_block_number()?),
+            None => None,
+        };
+
+        let now = SystemTime::now();
+        for (stage, checkpoint) in Self::checkpoints(&provider, stage)? {
+            let mut line = format!("{stage}: {checkpoint:?}");
+            if let Some(tip) = tip {
+                let remaining = blocks_remaining(tip, checkpoint.as_ref());
+                line.push_str(&format!(", remaining = {remaining}"));
+            }
+            if block_time {
+                let timestamp = checkpoint_timestamp(&provider, checkpoint.as_ref())?;
+                line.push_str(&format!(", {}", format_block_time(timestamp, now)));
+            }
+            println!("{line}");
+        }
+
+        Ok(())
+    }
+
+    fn get_missing<N: ProviderNodeTypes>(tool: &DbTool<N>) -> eyre::Result<()> {
+        let provider = tool.provider_factory.provider()?;
+        let missing = missing_checkpoints(&provider)?;
+
+        if missing.is_empty() {
+            println!("All stages have a checkpoint");
+        }
+        for stage_id in missing {
+            println!("{stage_id}");
+        }
+
+        Ok(())
+    }
+
+    /// Returns the checkpoint of the given stage, or of all stages in pipeline order if omitted,
+    /// see [`pipeline_position`].
+    fn checkpoints<P: StageCheckpointReader>(
+        provider: &P,
+        stage: Option<StageArg>,
+    ) -> eyre::Result<Vec<(String, Option<StageCheckpoint>)>> {
         match stage {
             Some(stage) => {
-                let stage_id = stage.into();
-                let checkpoint = provider.get_stage_checkpoint(stage_id)?;
-                println!("{stage_id}: {checkpoint:?}");
+                let stage_id: StageId = stage.into();
+                Ok(vec![(stage_id.to_string(), provider.get_stage_checkpoint(stage_id)?)])
             }
             None => {
                 let mut checkpoints = provider.get_all_checkpoints()?;
-                checkpoints.sort_by(|a, b| a.0.cmp(&b.0));
-                for (stage, checkpoint) in checkpoints {
-                    println!("{stage}: {checkpoint:?}");
-                }
+                checkpoints.sort_by(|a, b| {
+                    (pipeline_position(&a.0), &a.0).cmp(&(pipeline_position(&b.0), &b.0))
+                });
+                Ok(checkpoints.into_iter().map(|(stage, cp)| (stage, Some(cp))).collect())
             }
         }
+    }
+
+    fn from_manifest<N: ProviderNodeTypes>(tool: &DbTool<N>, path: &Path) -> eyre::Result<()> {
+        let checkpoints = CheckpointManifest::read(path)?.checkpoints()?;
+
+        let provider_rw = tool.provider_factory.database_provider_rw()?;
+        for (stage_id, block_number) in &checkpoints {
+            provider_rw.save_stage_checkpoint(*stage_id, StageCheckpoint::new(*block_number))?;
+        }
+        provider_rw.commit()?;
+
+        for (stage_id, block_number) in checkpoints {
+            println!("Updated checkpoint for {stage_id}: {block_number}");
+        }
 
         Ok(())
     }
 
-    fn set<N: ProviderNodeTypes>(tool: &DbTool<N>, args: SetArgs) -> eyre::Result<()> {
+    fn verify<N: ProviderNodeTypes>(tool: &DbTool<N>, era_dir: &Path) -> eyre::Result<()> {
+        let highest = highest_era1_block(era_dir)?;
+        let provider = tool.provider_factory.provider()?;
+        let exceeding = checkpoints_exceeding(&provider, highest)?;
+
+        if exceeding.is_empty() {
+            println!("All ERA stage checkpoints are within the ERA files (up to block {highest})");
+            return Ok(())
+        }
+        for (stage_id, block_number) in &exceeding {
+            println!("{stage_id}: checkpoint {block_number} exceeds the ERA files (up to block {highest})");
+        }
+        eyre::bail!(
+            "{} stage checkpoints exceed the ERA files in {}",
+            exceeding.len(),
+            era_dir.display()
+        )
+    }
+
+    fn prune_horizon<N: ProviderNodeTypes>(tool: &DbTool<N>) -> eyre::Result<()> {
+        let provider = tool.provider_factory.provider()?;
+        let (stage_id, horizon) = prune_horizon(&provider)?;
+        println!("Blocks up to {horizon} are safe to prune, held back by {stage_id}");
+        Ok(())
+    }
+
+    fn assert<N: ProviderNodeTypes>(
+        tool: &DbTool<N>,
+        expected: &[(StageArg, BlockNumber)],
+    ) -> eyre::Result<()> {
+        let expected =
+            expected.iter().map(|(stage, block_number)| ((*stage).into(), *block_number));
+        let mismatches = checkpoint_mismatches(&tool.provider_factory.provider()?, expected)?;
+
+        if mismatches.is_empty() {
+            println!("All stage checkpoints match the expected values");
+            return Ok(())
+        }
+        for (stage_id, expected, actual) in &mismatches {
+            match actual {
+                Some(actual) => println!("{stage_id}: expected {expected}, got {actual}"),
+                None => println!("{stage_id}: expected {expected}, got no checkpoint"),
+            }
+        }
+        eyre::bail!("{} stage checkpoints don't match the expected values", mismatches.len())
+    }
+
+    async fn record<N: ProviderNodeTypes>(
+        tool: &DbTool<N>,
+        out: &Path,
+        interval: Duration,
+    ) -> eyre::Result<()> {
+        let mut recorder = CheckpointRecorder::from_log(out)?;
+        let mut file = recorder.open_log(out)?;
+
+        // a single listener, so that a ctrl-c during a poll is not missed
+        let mut ctrl_c = std::pin::pin!(tokio::signal::ctrl_c());
+
+        println!(
+            "Recording stage checkpoints to {} every {}, press Ctrl+C to stop",
+            out.display(),
+            humantime::format_duration(interval)
+        );
+        loop {
+            recorder.poll(&tool.provider_factory.provider()?, &mut file)?;
+
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = &mut ctrl_c => break,
+            }
+        }
+        println!("Stopped recording stage checkpoints");
+
+        Ok(())
+    }
+
+    /// Sets the checkpoint and, with `--check`, returns the checkpoint inconsistencies found
+    /// afterwards.
+    fn set<N: ProviderNodeTypes>(
+        tool: &DbTool<N>,
+        args: SetArgs,
+    ) -> eyre::Result<Vec<CheckpointInconsistency>> {
         let stage_id: StageId = args.stage.into();
         let provider_rw = tool.provider_factory.database_provider_rw()?;
 
         let previous = provider_rw.get_stage_checkpoint(stage_id)?;
+        let raised = previous.is_none_or(|previous| previous.block_number < args.block_number);
         let mut checkpoint = previous.unwrap_or_default();
         checkpoint.block_number = args.block_number;
 
@@ -69,11 +255,42 @@ impl Command {
 
         provider_rw.save_stage_checkpoint(stage_id, checkpoint)?;
 
+        let mut cascaded = Vec::new();
+        if args.cascade && raised {
+            for downstream in dependent_stages(stage_id) {
+                let mut downstream_checkpoint =
+                    provider_rw.get_stage_checkpoint(downstream)?.unwrap_or_default();
+                if downstream_checkpoint.block_number >= args.block_number {
+                    continue
+                }
+                downstream_checkpoint.block_number = args.block_number;
+                provider_rw.save_stage_checkpoint(downstream, downstream_checkpoint)?;
+                cascaded.push(downstream);
+            }
+        }
         provider_rw.commit()?;
 
         println!("Updated checkpoint for {stage_id}: {checkpoint:?}");
+        for downstream in cascaded {
+            println!("Raised checkpoint for {downstream} to block {}", args.block_number);
+        }
 
-        Ok(())
+        if !args.check {
+            return Ok(Vec::new())
+        }
+
+        // the operator chose the value, so inconsistencies are only reported
+        let inconsistencies = check_checkpoint_consistency(&tool.provider_factory.provider()?)?
+            .err()
+            .unwrap_or_default();
+        if inconsistencies.is_empty() {
+            println!("Stage checkpoints are consistent");
+        }
+        for inconsistency in &inconsistencies {
+            println!("Warning: {inconsistency}");
+        }
+
+        Ok(inconsistencies)
     }
 }
 
@@ -84,9 +301,87 @@ enum Subcommands {
         /// Specific stage to query. If omitted, shows all stages.
         #[arg(long, value_enum)]
         stage: Option<StageArg>,
+
+        /// Print the number of blocks each stage is behind the tip.
+        ///
+        /// Uses the latest header as the tip unless `--tip` is given.
+        #[arg(long)]
+        remaining: bool,
+
+        /// Block number to compute the remaining blocks against. Implies `--remaining`.
+        #[arg(long, value_name = "BLOCK")]
+        tip: Option<u64>,
+
+        /// Only list the stages that have no checkpoint.
+        #[arg(long, conflicts_with_all = ["stage", "remaining", "tip", "block_time"])]
+        missing_only: bool,
+
+        /// Print the timestamp of each checkpoint block and how far it is behind the current
+        /// time.
+        ///
+        /// Shows `n/a` for stages whose checkpoint block header is not available.
+        #[arg(long)]
+        block_time: bool,
     },
     /// Set a stage checkpoint.
     Set(SetArgs),
+    /// Set the checkpoints of all stages from a snapshot manifest in one transaction.
+    ///
+    /// The manifest is a JSON file with the snapshot `block_number` and optional per-stage
+    /// overrides, e.g. `{"block_number": 100, "stages": {"Finish": 90}}`. Stage-specific unit
+    /// checkpoints are cleared.
+    FromManifest {
+        /// Path to the manifest file.
+        #[arg(long, value_name = "FILE")]
+        path: PathBuf,
+    },
+    /// Verify that the Era, Headers and Bodies checkpoints don't exceed the blocks covered by a
+    /// directory of ERA1 files.
+    Verify {
+        /// Directory containing the ERA1 files.
+        ///
+        /// The files must cover a contiguous range of eras starting at era 0.
+        #[arg(long, value_name = "DIR")]
+        era_dir: PathBuf,
+    },
+    /// Append a timestamped JSON line to a log whenever a stage checkpoint changes.
+    ///
+    /// Runs until interrupted with Ctrl+C.
+    Record {
+        /// File to append the checkpoint changes to.
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+
+        /// How often to poll the stage checkpoints.
+        #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
+        interval: Duration,
+    },
+    /// Print the highest block that is safe to prune, i.e. that all stages consuming raw block
+    /// or changeset data have processed.
+    PruneHorizon,
+    /// Check that stage checkpoints have the expected block numbers, e.g. in CI.
+    ///
+    /// Prints every mismatch and exits with an error if any checkpoint doesn't match.
+    Assert {
+        /// Expected checkpoint of a stage, can be repeated.
+        #[arg(
+            long = "expect",
+            value_name = "STAGE=BLOCK",
+            value_parser = parse_expected_checkpoint,
+            required = true
+        )]
+        expected: Vec<(StageArg, BlockNumber)>,
+    },
+}
+
+/// Parses a `STAGE=BLOCK` expectation of the `assert` subcommand.
+fn parse_expected_checkpoint(s: &str) -> Result<(StageArg, BlockNumber), String> {
+    let (stage, block_number) =
+        s.split_once('=').ok_or_else(|| format!("expected STAGE=BLOCK, got {s}"))?;
+    let stage = StageArg::from_str(stage, true)?;
+    let block_number =
+        block_number.parse().map_err(|err| format!("invalid block number: {err}"))?;
+    Ok((stage, block_number))
 }
 
 /// Arguments for the `set` subcommand.
@@ -103,6 +398,332 @@ pub struct SetArgs {
     /// Clear stage-specific unit checkpoint payload.
     #[arg(long)]
     clear_stage_unit: bool,
+
+    /// Check the consistency of all stage checkpoints after the update and print any
+    /// inconsistencies.
+    ///
+    /// The checkpoint is written regardless of the result.
+    #[arg(long)]
+    check: bool,
+
+    /// When raising the checkpoint, also raise all stages that depend on the output of this
+    /// stage to the same block in the same transaction.
+    ///
+    /// Stages that are already at or beyond the block, and stages that don't depend on this
+    /// stage, e.g. the prune stages, are left untouched.
+    #[arg(long)]
+    cascade: bool,
+}
+
+/// Manifest of a snapshot, see [`Subcommands::FromManifest`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckpointManifest {
+    /// Block of the snapshot, used as checkpoint for all stages without an override.
+    block_number: BlockNumber,
+    /// Checkpoints of stages that differ from the snapshot block, keyed by stage name.
+    #[serde(default)]
+    stages: BTreeMap<String, BlockNumber>,
+}
+
+impl CheckpointManifest {
+    /// Reads and parses the manifest at `path`.
+    fn read(path: &Path) -> eyre::Result<Self> {
+        serde_json::from_str(&reth_fs_util::read_to_string(path)?)
+            .map_err(|err| eyre::eyre!("Invalid manifest {}: {err}", path.display()))
+    }
+
+    /// Returns the checkpoint of every stage in [`StageId::ALL`].
+    ///
+    /// Fails if an override names a stage that is not part of the pipeline.
+    fn checkpoints(&self) -> eyre::Result<Vec<(StageId, BlockNumber)>> {
+        if let Some(unknown) = self
+            .stages
+            .keys()
+            .find(|stage| !StageId::ALL.iter().any(|id| id.as_str() == stage.as_str()))
+        {
+            eyre::bail!("Unknown stage in manifest: {unknown}")
+        }
+        Ok(StageId::ALL
+            .into_iter()
+            .map(|id| (id, self.stages.get(id.as_str()).copied().unwrap_or(self.block_number)))
+            .collect())
+    }
+}
+
+/// Returns the position of the stage in the pipeline, i.e. its index in [`StageId::ALL`].
+///
+/// Stages that are not part of the pipeline, such as deprecated stages, are placed after all
+/// pipeline stages.
+fn pipeline_position(stage: &str) -> usize {
+    StageId::ALL.iter().position(|id| id.as_str() == stage).unwrap_or(StageId::ALL.len())
+}
+
+/// Returns the stages that directly or transitively consume the output of the given stage, in
+/// pipeline order, see [`STAGE_PREREQUISITES`].
+fn dependent_stages(stage: StageId) -> Vec<StageId> {
+    let mut dependents = Vec::new();
+    let mut pending = vec![stage];
+    while let Some(prerequisite) = pending.pop() {
+        for &(dependent, required) in STAGE_PREREQUISITES {
+            if required == prerequisite && !dependents.contains(&dependent) {
+                dependents.push(dependent);
+                pending.push(dependent);
+            }
+        }
+    }
+    dependents.sort_by_key(|stage| pipeline_position(stage.as_str()));
+    dependents
+}
+
+/// Returns how many blocks the stage is behind `tip`, clamped at zero.
+///
+/// A stage without a checkpoint is treated as being at block 0.
+fn blocks_remaining(tip: u64, checkpoint: Option<&StageCheckpoint>) -> u64 {
+    tip.saturating_sub(checkpoint.map_or(0, |checkpoint| checkpoint.block_number))
+}
+
+/// Returns the timestamp of the checkpoint block, if there is a checkpoint and its header is
+/// available.
+fn checkpoint_timestamp<P: HeaderProvider<Header: BlockHeader>>(
+    provider: &P,
+    checkpoint: Option<&StageCheckpoint>,
+) -> eyre::Result<Option<u64>> {
+    let Some(checkpoint) = checkpoint else { return Ok(None) };
+    Ok(provider.header_by_number(checkpoint.block_number)?.map(|header| header.timestamp()))
+}
+
+/// Returns how far the block timestamp is behind `now`, clamped at zero.
+fn block_age(timestamp: u64, now: SystemTime) -> Duration {
+    now.duration_since(UNIX_EPOCH + Duration::from_secs(timestamp)).unwrap_or_default()
+}
+
+/// Formats the block timestamp and its age, or `n/a` if the timestamp is unknown.
+fn format_block_time(timestamp: Option<u64>, now: SystemTime) -> String {
+    let Some(timestamp) = timestamp else { return "block time = n/a".to_string() };
+    format!(
+        "block time = {}, age = {}",
+        humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(timestamp)),
+        humantime::format_duration(block_age(timestamp, now))
+    )
+}
+
+/// Stages whose checkpoints are bounded by the imported ERA1 files.
+const ERA_STAGES: [StageId; 3] = [StageId::Era, StageId::Headers, StageId::Bodies];
+
+/// Returns the highest block covered by the ERA1 files in `dir`.
+///
+/// The era numbers in the file names, see
+/// [`EraFileType::format_filename`](reth_era::common::file_ops::EraFileType::format_filename),
+/// must cover a contiguous range of eras starting at era 0. The highest block is read from the
+/// block index of the last file, because the last pre-merge file doesn't contain a full era.
+fn highest_era1_block(dir: &Path) -> eyre::Result<BlockNumber> {
+    let mut files = BTreeMap::new();
+    for entry in reth_fs_util::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension() != Some("era1".as_ref()) {
+            continue
+        }
+        let Some(name) = path.file_stem().map(|name| name.to_string_lossy()) else { continue };
+        let parts = name.split('-').collect::<Vec<_>>();
+        let (number, count) = match parts.as_slice() {
+            [_, number, _] => (number.parse::<u64>()?, 1),
+            [_, number, count, _] => (number.parse::<u64>()?, count.parse::<u64>()?),
+            _ => eyre::bail!("Unexpected ERA1 file name: {}", path.display()),
+        };
+        if files.insert(number, (count, path)).is_some() {
+            eyre::bail!("Multiple ERA1 files for era {number} in {}", dir.display())
+        }
+    }
+
+    let mut next_era = 0;
+    let mut last = None;
+    for (number, (count, path)) in files {
+        if number > next_era {
+            eyre::bail!(
+                "Missing ERA1 files for eras {next_era} to {} in {}",
+                number - 1,
+                dir.display()
+            )
+        }
+        if number < next_era {
+            eyre::bail!("ERA1 file {} overlaps with the previous file", path.display())
+        }
+        next_era = number + count;
+        last = Some(path);
+    }
+
+    let last = last.ok_or_else(|| eyre::eyre!("No ERA1 files in {}", dir.display()))?;
+    last_indexed_block(&last)
+}
+
+/// Returns the last block of the block index of the ERA1 file, i.e. its starting block plus the
+/// number of blocks minus one.
+fn last_indexed_block(path: &Path) -> eyre::Result<BlockNumber> {
+    let mut reader = E2StoreReader::new(BufReader::new(reth_fs_util::open(path)?));
+    let mut block_index = None;
+    while let Some(entry) = reader.read_next_entry()? {
+        if entry.entry_type == BLOCK_INDEX {
+            block_index = Some(BlockIndex::from_entry(&entry)?);
+        }
+    }
+
+    let block_index = block_index
+        .ok_or_else(|| eyre::eyre!("ERA1 file {} has no block index", path.display()))?;
+    let count = block_index.offsets().len() as u64;
+    if count == 0 {
+        eyre::bail!("ERA1 file {} has an empty block index", path.display())
+    }
+    Ok(block_index.starting_number() + count - 1)
+}
+
+/// Stages that read block or changeset data which the pruner may remove.
+const DATA_CONSUMING_STAGES: [StageId; 8] = [
+    StageId::SenderRecovery,
+    StageId::Execution,
+    StageId::AccountHashing,
+    StageId::StorageHashing,
+    StageId::MerkleExecute,
+    StageId::TransactionLookup,
+    StageId::IndexStorageHistory,
+    StageId::IndexAccountHistory,
+];
+
+/// Returns the lowest checkpoint of the given stages, i.e. the highest block all of them reached,
+/// together with the stage at that checkpoint.
+///
+/// Stages without a checkpoint are treated as being at block 0. Ties are resolved in favor of the
+/// stage that comes first in `stages`.
+fn highest_common_checkpoint<P: StageCheckpointReader>(
+    provider: &P,
+    stages: &[StageId],
+) -> eyre::Result<Option<(StageId, BlockNumber)>> {
+    let mut lowest: Option<(StageId, BlockNumber)> = None;
+    for stage_id in stages {
+        let block_number = provider
+            .get_stage_checkpoint(*stage_id)?
+            .map_or(0, |checkpoint| checkpoint.block_number);
+        if lowest.is_none_or(|(_, lowest)| block_number < lowest) {
+            lowest = Some((*stage_id, block_number));
+        }
+    }
+    Ok(lowest)
+}
+
+/// Returns the highest block that is safe to prune, i.e. the highest common checkpoint of the
+/// [`DATA_CONSUMING_STAGES`], together with the stage holding it back.
+fn prune_horizon<P: StageCheckpointReader>(provider: &P) -> eyre::Result<(StageId, BlockNumber)> {
+    highest_common_checkpoint(provider, &DATA_CONSUMING_STAGES)?
+        .ok_or_else(|| eyre::eyre!("No data consuming stages"))
+}
+
+/// Returns the stages whose checkpoint doesn't match the expected block number, with the expected
+/// and the actual block number, or `None` if the stage has no checkpoint.
+fn checkpoint_mismatches<P: StageCheckpointReader>(
+    provider: &P,
+    expected: impl IntoIterator<Item = (StageId, BlockNumber)>,
+) -> eyre::Result<Vec<(StageId, BlockNumber, Option<BlockNumber>)>> {
+    let mut mismatches = Vec::new();
+    for (stage_id, expected) in expected {
+        let actual =
+            provider.get_stage_checkpoint(stage_id)?.map(|checkpoint| checkpoint.block_number);
+        if actual != Some(expected) {
+            mismatches.push((stage_id, expected, actual));
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Returns the [`ERA_STAGES`] whose checkpoint is above `highest`, with their checkpoint.
+fn checkpoints_exceeding<P: StageCheckpointReader>(
+    provider: &P,
+    highest: BlockNumber,
+) -> eyre::Result<Vec<(StageId, BlockNumber)>> {
+    let mut exceeding = Vec::new();
+    for stage_id in ERA_STAGES {
+        if let Some(checkpoint) = provider.get_stage_checkpoint(stage_id)? &&
+            checkpoint.block_number > highest
+        {
+            exceeding.push((stage_id, checkpoint.block_number));
+        }
+    }
+    Ok(exceeding)
+}
+
+/// Records changes of the stage checkpoints as JSON lines.
+#[derive(Debug, Default)]
+struct CheckpointRecorder {
+    /// The last recorded block number of each stage.
+    last: HashMap<String, BlockNumber>,
+    /// Length of the existing log up to its last complete line.
+    complete_len: u64,
+}
+
+impl CheckpointRecorder {
+    /// Returns a recorder that continues the log at `path`, if it exists, so that checkpoints that
+    /// didn't change since the last recorded line are not recorded again.
+    ///
+    /// An incomplete last line, e.g. from an interrupted write, is ignored and removed by
+    /// [`Self::open_log`].
+    fn from_log(path: &Path) -> eyre::Result<Self> {
+        let mut recorder = Self::default();
+        if !path.exists() {
+            return Ok(recorder)
+        }
+        let log = reth_fs_util::read_to_string(path)?;
+        let complete_len = log.rfind('\n').map_or(0, |idx| idx + 1);
+        if complete_len < log.len() {
+            println!("Ignoring incomplete last line of {}", path.display());
+        }
+        for line in log[..complete_len].lines() {
+            let line: serde_json::Value = serde_json::from_str(line)?;
+            if let (Some(stage), Some(block_number)) =
+                (line["stage"].as_str(), line["block_number"].as_u64())
+            {
+                recorder.last.insert(stage.to_string(), block_number);
+            }
+        }
+        recorder.complete_len = complete_len as u64;
+        Ok(recorder)
+    }
+
+    /// Opens the log at `path` for appending, removing an incomplete last line.
+    fn open_log(&self, path: &Path) -> eyre::Result<File> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() > self.complete_len {
+            file.set_len(self.complete_len)?;
+        }
+        Ok(file)
+    }
+
+    /// Writes a line for every stage whose block number changed since the last poll and returns
+    /// the number of written lines.
+    fn poll<P: StageCheckpointReader>(
+        &mut self,
+        provider: &P,
+        out: &mut impl Write,
+    ) -> eyre::Result<usize> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut checkpoints = provider.get_all_checkpoints()?;
+        checkpoints.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut written = 0;
+        for (stage, checkpoint) in checkpoints {
+            if self.last.get(&stage) == Some(&checkpoint.block_number) {
+                continue
+            }
+            let line = serde_json::json!({
+                "timestamp": timestamp,
+                "stage": stage,
+                "block_number": checkpoint.block_number,
+            });
+            writeln!(out, "{line}")?;
+            self.last.insert(stage, checkpoint.block_number);
+            written += 1;
+        }
+        out.flush()?;
+        Ok(written)
+    }
 }
 
 /// CLI-friendly stage names.
@@ -151,12 +772,38 @@ impl From<StageArg> for StageId {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy_consensus::Header;
+    use alloy_primitives::B256;
     use clap::Parser;
+    use reth_era::{
+        common::file_ops::StreamWriter,
+        era1::{
+            file::Era1Writer,
+            types::execution::{Accumulator, MAX_BLOCKS_PER_ERA1},
+        },
+    };
     use reth_provider::{
         test_utils::create_test_provider_factory, DBProvider, DatabaseProviderFactory,
-        StageCheckpointReader, StageCheckpointWriter,
+        StageCheckpointReader, StageCheckpointWriter, StaticFileProviderFactory, StaticFileWriter,
     };
-    use reth_stages::StageCheckpoint;
+    use reth_static_file_types::StaticFileSegment;
+
+    #[test]
+    fn parse_get_args() {
+        let command =
+            Command::parse_from(["stage-checkpoints", "get", "--stage", "bodies", "--tip", "100"]);
+
+        assert!(matches!(
+            command.command,
+            Subcommands::Get {
+                stage: Some(StageArg::Bodies),
+                remaining: false,
+                tip: Some(100),
+                missing_only: false,
+                block_time: false,
+            }
+        ));
+    }
 
     #[test]
     fn parse_set_args() {
@@ -175,6 +822,8 @@ mod tests {
                 stage: StageArg::Headers,
                 block_number: 123,
                 clear_stage_unit: false,
+                check: false,
+                cascade: false,
             })
         ));
     }
@@ -197,10 +846,12 @@ mod tests {
                 stage: StageArg::Headers,
                 block_number: 42,
                 clear_stage_unit: false,
+                check: false,
+                cascade: false,
             }),
         };
 
-        command.execute(&tool).expect("execute command");
+        command.execute_blocking(&tool).expect("execute command");
 
         let provider = provider_factory.provider().expect("provider");
         let checkpoint = provider
@@ -230,9 +881,11 @@ mod tests {
                 stage: StageArg::Execution,
                 block_number: 11,
                 clear_stage_unit: false,
+                check: false,
+                cascade: false,
             }),
         }
-        .execute(&tool)
+        .execute_blocking(&tool)
         .expect("execute command");
 
         let provider = provider_factory.provider().expect("provider");
@@ -247,9 +900,11 @@ mod tests {
                 stage: StageArg::Execution,
                 block_number: 12,
                 clear_stage_unit: true,
+                check: false,
+                cascade: false,
             }),
         }
-        .execute(&tool)
+        .execute_blocking(&tool)
         .expect("execute command");
 
         let checkpoint = provider_factory
@@ -282,9 +937,11 @@ mod tests {
                 stage: StageArg::MerkleExecute,
                 block_number: 20,
                 clear_stage_unit: false,
+                check: false,
+                cascade: false,
             }),
         }
-        .execute(&tool)
+        .execute_blocking(&tool)
         .expect("execute command");
 
         let provider = provider_factory.provider().expect("provider");
@@ -294,4 +951,521 @@ mod tests {
 
         assert_eq!(progress, Some(vec![1, 2, 3]));
     }
+
+    #[test]
+    fn set_cascade_raises_dependent_stages() {
+        let provider_factory = create_test_provider_factory();
+        let tool = DbTool::new(provider_factory.clone()).expect("db tool");
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for (stage_id, block_number) in
+                [(StageId::Headers, 10), (StageId::Bodies, 5), (StageId::Execution, 50)]
+            {
+                provider_rw
+                    .save_stage_checkpoint(stage_id, StageCheckpoint::new(block_number))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit initial checkpoints");
+        }
+
+        let command = Command::parse_from([
+            "stage-checkpoints",
+            "set",
+            "--stage",
+            "headers",
+            "--block-number",
+            "20",
+            "--cascade",
+        ]);
+        assert!(matches!(command.command, Subcommands::Set(SetArgs { cascade: true, .. })));
+        command.execute_blocking(&tool).expect("execute command");
+
+        let provider = provider_factory.provider().expect("provider");
+        let block_number = |stage_id| {
+            provider
+                .get_stage_checkpoint(stage_id)
+                .expect("get stage checkpoint")
+                .map(|checkpoint| checkpoint.block_number)
+        };
+        assert_eq!(block_number(StageId::Era), None);
+        assert_eq!(block_number(StageId::Headers), Some(20));
+        assert_eq!(block_number(StageId::Bodies), Some(20));
+        assert_eq!(block_number(StageId::SenderRecovery), Some(20));
+        // stages ahead of the block are never lowered
+        assert_eq!(block_number(StageId::Execution), Some(50));
+        assert_eq!(block_number(StageId::Finish), Some(20));
+        // stages that don't consume the headers are left untouched
+        assert_eq!(block_number(StageId::PruneSenderRecovery), None);
+        assert_eq!(block_number(StageId::MerkleUnwind), None);
+        assert_eq!(block_number(StageId::Prune), None);
+
+        // lowering a stage doesn't cascade
+        Command::parse_from([
+            "stage-checkpoints",
+            "set",
+            "--stage",
+            "headers",
+            "--block-number",
+            "15",
+            "--cascade",
+        ])
+        .execute_blocking(&tool)
+        .expect("execute command");
+
+        let provider = provider_factory.provider().expect("provider");
+        let block_number = |stage_id| {
+            provider
+                .get_stage_checkpoint(stage_id)
+                .expect("get stage checkpoint")
+                .map(|checkpoint| checkpoint.block_number)
+        };
+        assert_eq!(block_number(StageId::Headers), Some(15));
+        assert_eq!(block_number(StageId::Bodies), Some(20));
+    }
+
+    #[test]
+    fn remaining_blocks_against_tip() {
+        let provider_factory = create_test_provider_factory();
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            provider_rw
+                .save_stage_checkpoint(StageId::Headers, StageCheckpoint::new(100))
+                .expect("save checkpoint");
+            provider_rw
+                .save_stage_checkpoint(StageId::Bodies, StageCheckpoint::new(60))
+                .expect("save checkpoint");
+            provider_rw
+                .save_stage_checkpoint(StageId::Execution, StageCheckpoint::new(120))
+                .expect("save checkpoint");
+            provider_rw.commit().expect("commit initial checkpoints");
+        }
+
+        let provider = provider_factory.provider().expect("provider");
+        let remaining = Command::checkpoints(&provider, None)
+            .expect("get checkpoints")
+            .into_iter()
+            .map(|(stage, checkpoint)| (stage, blocks_remaining(100, checkpoint.as_ref())))
+            .collect::<Vec<_>>();
+
+        assert!(remaining.contains(&(StageId::Headers.to_string(), 0)));
+        assert!(remaining.contains(&(StageId::Bodies.to_string(), 40)));
+        // Stages ahead of the tip are clamped at zero.
+        assert!(remaining.contains(&(StageId::Execution.to_string(), 0)));
+
+        let missing =
+            Command::checkpoints(&provider, Some(StageArg::Finish)).expect("get checkpoints");
+        assert_eq!(missing, vec![(StageId::Finish.to_string(), None)]);
+        assert_eq!(blocks_remaining(100, missing[0].1.as_ref()), 100);
+    }
+
+    #[test]
+    fn checkpoints_in_pipeline_order() {
+        let provider_factory = create_test_provider_factory();
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for stage_id in StageId::ALL {
+                provider_rw
+                    .save_stage_checkpoint(stage_id, StageCheckpoint::new(10))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit initial checkpoints");
+        }
+
+        let provider = provider_factory.provider().expect("provider");
+        let stages = Command::checkpoints(&provider, None)
+            .expect("get checkpoints")
+            .into_iter()
+            .map(|(stage, _)| stage)
+            .collect::<Vec<_>>();
+
+        let pipeline = StageId::ALL.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(stages, pipeline);
+
+        let mut alphabetical = pipeline.clone();
+        alphabetical.sort();
+        assert_ne!(stages, alphabetical);
+    }
+
+    #[test]
+    fn prune_horizon_held_back_by_lagging_stage() {
+        let provider_factory = create_test_provider_factory();
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for stage_id in StageId::ALL {
+                provider_rw
+                    .save_stage_checkpoint(stage_id, StageCheckpoint::new(1000))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit initial checkpoints");
+        }
+
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(prune_horizon(&provider).unwrap(), (StageId::SenderRecovery, 1000));
+        drop(provider);
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            // stages that don't consume block data don't hold back the horizon
+            provider_rw
+                .save_stage_checkpoint(StageId::Finish, StageCheckpoint::new(10))
+                .expect("save checkpoint");
+            provider_rw
+                .save_stage_checkpoint(StageId::TransactionLookup, StageCheckpoint::new(400))
+                .expect("save checkpoint");
+            provider_rw.commit().expect("commit lagging checkpoints");
+        }
+
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(prune_horizon(&provider).unwrap(), (StageId::TransactionLookup, 400));
+    }
+
+    /// Writes an ERA1 file that only consists of the accumulator and the block index.
+    fn write_era1_index(dir: &Path, name: &str, start_block: BlockNumber, block_count: usize) {
+        let file = File::create(dir.join(name)).expect("create era1 file");
+        let mut writer = Era1Writer::new(file);
+        writer.write_accumulator(&Accumulator::new(B256::ZERO)).expect("write accumulator");
+        writer
+            .write_block_index(&BlockIndex::new(start_block, vec![0; block_count]))
+            .expect("write block index");
+        writer.flush().expect("flush era1 file");
+    }
+
+    #[test]
+    fn highest_era1_block_from_block_index() {
+        let era_dir = tempfile::tempdir().expect("tempdir");
+        let full = MAX_BLOCKS_PER_ERA1;
+        write_era1_index(era_dir.path(), "mainnet-00000-5ec1ffb8.era1", 0, full);
+        // the last pre-merge file is partial
+        write_era1_index(era_dir.path(), "mainnet-00001-a5364e9a.era1", full as u64, 100);
+        std::fs::write(era_dir.path().join("checksums.txt"), []).expect("write checksums");
+        assert_eq!(highest_era1_block(era_dir.path()).expect("highest block"), full as u64 + 99);
+
+        // a missing file in the middle is rejected
+        write_era1_index(era_dir.path(), "mainnet-00003-1a2b3c4d.era1", 3 * full as u64, full);
+        assert!(highest_era1_block(era_dir.path()).is_err());
+
+        // as is a missing first file
+        let gap = tempfile::tempdir().expect("tempdir");
+        write_era1_index(gap.path(), "mainnet-00001-a5364e9a.era1", full as u64, full);
+        assert!(highest_era1_block(gap.path()).is_err());
+
+        let empty = tempfile::tempdir().expect("tempdir");
+        assert!(highest_era1_block(empty.path()).is_err());
+    }
+
+    #[test]
+    fn verify_against_era_files() {
+        let era_dir = tempfile::tempdir().expect("tempdir");
+        write_era1_index(era_dir.path(), "mainnet-00000-5ec1ffb8.era1", 0, MAX_BLOCKS_PER_ERA1);
+        write_era1_index(
+            era_dir.path(),
+            "mainnet-00001-a5364e9a.era1",
+            MAX_BLOCKS_PER_ERA1 as u64,
+            MAX_BLOCKS_PER_ERA1,
+        );
+        let highest = highest_era1_block(era_dir.path()).expect("highest block");
+        assert_eq!(highest, 2 * MAX_BLOCKS_PER_ERA1 as u64 - 1);
+
+        let provider_factory = create_test_provider_factory();
+        let tool = DbTool::new(provider_factory.clone()).expect("db tool");
+        let save = |checkpoints: &[(StageId, u64)]| {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for (stage_id, block_number) in checkpoints {
+                provider_rw
+                    .save_stage_checkpoint(*stage_id, StageCheckpoint::new(*block_number))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit checkpoints");
+        };
+        let verify = || {
+            Command { command: Subcommands::Verify { era_dir: era_dir.path().to_path_buf() } }
+                .execute_blocking(&tool)
+        };
+
+        // stages outside the ERA range are not checked
+        save(&[
+            (StageId::Era, highest),
+            (StageId::Headers, 100),
+            (StageId::Execution, highest + 1),
+        ]);
+        verify().expect("checkpoints within the ERA files");
+
+        save(&[(StageId::Bodies, highest + 1)]);
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(
+            checkpoints_exceeding(&provider, highest).expect("checkpoints"),
+            vec![(StageId::Bodies, highest + 1)]
+        );
+        drop(provider);
+        assert!(verify().is_err());
+    }
+
+    #[test]
+    fn record_checkpoint_changes() {
+        let provider_factory = create_test_provider_factory();
+        let save = |checkpoints: &[(StageId, u64)]| {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for (stage_id, block_number) in checkpoints {
+                provider_rw
+                    .save_stage_checkpoint(*stage_id, StageCheckpoint::new(*block_number))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit checkpoints");
+        };
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("checkpoints.jsonl");
+        let poll = |recorder: &mut CheckpointRecorder| {
+            let mut file =
+                OpenOptions::new().create(true).append(true).open(&out).expect("open log");
+            let provider = provider_factory.provider().expect("provider");
+            recorder.poll(&provider, &mut file).expect("poll checkpoints")
+        };
+        let logged = || {
+            std::fs::read_to_string(&out)
+                .expect("read log")
+                .lines()
+                .map(|line| {
+                    let line: serde_json::Value = serde_json::from_str(line).expect("json line");
+                    assert!(line["timestamp"].as_u64().is_some());
+                    (line["stage"].as_str().unwrap().to_string(), line["block_number"].as_u64())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        save(&[(StageId::Headers, 10), (StageId::Bodies, 5)]);
+        let mut recorder = CheckpointRecorder::from_log(&out).expect("recorder");
+        assert_eq!(poll(&mut recorder), 2);
+        // unchanged checkpoints are not recorded again
+        assert_eq!(poll(&mut recorder), 0);
+
+        save(&[(StageId::Headers, 20)]);
+        assert_eq!(poll(&mut recorder), 1);
+        assert_eq!(
+            logged(),
+            vec![
+                ("Bodies".to_string(), Some(5)),
+                ("Headers".to_string(), Some(10)),
+                ("Headers".to_string(), Some(20)),
+            ]
+        );
+
+        // a new recorder continues the existing log
+        let mut recorder = CheckpointRecorder::from_log(&out).expect("recorder");
+        assert_eq!(poll(&mut recorder), 0);
+        save(&[(StageId::Bodies, 20)]);
+        assert_eq!(poll(&mut recorder), 1);
+        assert_eq!(logged().last(), Some(&("Bodies".to_string(), Some(20))));
+    }
+
+    #[test]
+    fn record_resumes_after_truncated_line() {
+        let provider_factory = create_test_provider_factory();
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            provider_rw
+                .save_stage_checkpoint(StageId::Bodies, StageCheckpoint::new(5))
+                .expect("save checkpoint");
+            provider_rw.commit().expect("commit checkpoints");
+        }
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("checkpoints.jsonl");
+
+        // the last write was interrupted
+        let valid = r#"{"timestamp":1,"stage":"Headers","block_number":10}"#;
+        std::fs::write(&out, format!("{valid}\n{{\"timestamp\":2,\"sta")).expect("write log");
+        let mut recorder = CheckpointRecorder::from_log(&out).expect("recorder");
+        assert_eq!(recorder.last.get("Headers"), Some(&10));
+
+        let mut file = recorder.open_log(&out).expect("open log");
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(recorder.poll(&provider, &mut file).expect("poll checkpoints"), 1);
+
+        // the incomplete line was replaced, so the log can be resumed again
+        let log = std::fs::read_to_string(&out).expect("read log");
+        let lines = log.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], valid);
+        let line: serde_json::Value = serde_json::from_str(lines[1]).expect("json line");
+        assert_eq!(line["stage"], "Bodies");
+        let recorder = CheckpointRecorder::from_log(&out).expect("recorder");
+        assert_eq!(recorder.last.get("Bodies"), Some(&5));
+
+        // corrupt complete lines are still rejected
+        std::fs::write(&out, format!("not json\n{valid}\n")).expect("write log");
+        assert!(CheckpointRecorder::from_log(&out).is_err());
+    }
+
+    #[test]
+    fn set_with_check_reports_inconsistencies() {
+        let provider_factory = create_test_provider_factory();
+        let tool = DbTool::new(provider_factory.clone()).expect("db tool");
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for stage_id in [StageId::Headers, StageId::Bodies, StageId::SenderRecovery] {
+                provider_rw
+                    .save_stage_checkpoint(stage_id, StageCheckpoint::new(10))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit initial checkpoints");
+        }
+
+        let set = |block_number| {
+            Command::set(
+                &tool,
+                SetArgs {
+                    stage: StageArg::Execution,
+                    block_number,
+                    clear_stage_unit: false,
+                    check: true,
+                    cascade: false,
+                },
+            )
+            .expect("set checkpoint")
+        };
+
+        assert_eq!(set(10), vec![]);
+
+        // the inconsistency is reported, but the checkpoint is still written
+        assert_eq!(
+            set(20),
+            vec![CheckpointInconsistency {
+                stage: StageId::Execution,
+                checkpoint: 20,
+                prerequisite: StageId::SenderRecovery,
+                prerequisite_checkpoint: 10,
+            }]
+        );
+        let checkpoint = provider_factory
+            .provider()
+            .expect("provider")
+            .get_stage_checkpoint(StageId::Execution)
+            .expect("get stage checkpoint")
+            .expect("missing stage checkpoint");
+        assert_eq!(checkpoint.block_number, 20);
+    }
+
+    #[test]
+    fn checkpoint_block_time() {
+        let provider_factory = create_test_provider_factory();
+        let timestamp = 1_700_000_000;
+        {
+            let static_file_provider = provider_factory.static_file_provider();
+            let mut writer = static_file_provider
+                .latest_writer(StaticFileSegment::Headers)
+                .expect("header writer");
+            writer
+                .append_header(&Header { number: 0, timestamp, ..Default::default() }, &B256::ZERO)
+                .expect("append header");
+            writer.commit().expect("commit header");
+        }
+
+        let provider = provider_factory.provider().expect("provider");
+        let checkpoint = StageCheckpoint::new(0);
+        assert_eq!(checkpoint_timestamp(&provider, Some(&checkpoint)).unwrap(), Some(timestamp));
+        // missing checkpoints and headers can't be resolved
+        assert_eq!(checkpoint_timestamp(&provider, None).unwrap(), None);
+        assert_eq!(checkpoint_timestamp(&provider, Some(&StageCheckpoint::new(1))).unwrap(), None);
+
+        let now = UNIX_EPOCH + Duration::from_secs(timestamp + 90);
+        assert_eq!(block_age(timestamp, now), Duration::from_secs(90));
+        // blocks from the future are not behind
+        assert_eq!(block_age(timestamp + 10, now - Duration::from_secs(90)), Duration::ZERO);
+        assert_eq!(
+            format_block_time(Some(timestamp), now),
+            "block time = 2023-11-14T22:13:20Z, age = 1m 30s"
+        );
+        assert_eq!(format_block_time(None, now), "block time = n/a");
+    }
+
+    #[test]
+    fn set_from_manifest() {
+        let provider_factory = create_test_provider_factory();
+        let tool = DbTool::new(provider_factory.clone()).expect("db tool");
+        let dir = tempfile::tempdir().expect("tempdir");
+        let from_manifest = |manifest: &str| {
+            let path = dir.path().join("manifest.json");
+            std::fs::write(&path, manifest).expect("write manifest");
+            Command { command: Subcommands::FromManifest { path } }.execute_blocking(&tool)
+        };
+        let checkpoint = |stage_id| {
+            provider_factory
+                .provider()
+                .expect("provider")
+                .get_stage_checkpoint(stage_id)
+                .expect("get stage checkpoint")
+        };
+
+        // invalid manifests don't change any checkpoint
+        assert!(from_manifest(r#"{"stages": {}}"#).is_err());
+        assert!(from_manifest(r#"{"block_number": 10, "unknown": 1}"#).is_err());
+        assert!(from_manifest(r#"{"block_number": 10, "stages": {"Unknown": 1}}"#).is_err());
+        assert!(StageId::ALL.into_iter().all(|stage_id| checkpoint(stage_id).is_none()));
+
+        from_manifest(r#"{"block_number": 1000}"#).expect("apply manifest");
+        for stage_id in StageId::ALL {
+            assert_eq!(checkpoint(stage_id), Some(StageCheckpoint::new(1000)));
+        }
+
+        from_manifest(r#"{"block_number": 2000, "stages": {"Finish": 1500}}"#)
+            .expect("apply manifest");
+        for stage_id in StageId::ALL {
+            let expected = if stage_id == StageId::Finish { 1500 } else { 2000 };
+            assert_eq!(checkpoint(stage_id).map(|cp| cp.block_number), Some(expected));
+        }
+    }
+
+    #[test]
+    fn assert_expected_checkpoints() {
+        let command = Command::parse_from([
+            "stage-checkpoints",
+            "assert",
+            "--expect",
+            "headers=100",
+            "--expect",
+            "sender-recovery=90",
+        ]);
+        let Subcommands::Assert { expected } = &command.command else { panic!("assert command") };
+        assert!(matches!(
+            expected.as_slice(),
+            [(StageArg::Headers, 100), (StageArg::SenderRecovery, 90)]
+        ));
+        assert!(Command::try_parse_from(["stage-checkpoints", "assert"]).is_err());
+        assert!(parse_expected_checkpoint("headers").is_err());
+        assert!(parse_expected_checkpoint("unknown=1").is_err());
+
+        let provider_factory = create_test_provider_factory();
+        let tool = DbTool::new(provider_factory.clone()).expect("db tool");
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for (stage_id, block_number) in [(StageId::Headers, 100), (StageId::Bodies, 80)] {
+                provider_rw
+                    .save_stage_checkpoint(stage_id, StageCheckpoint::new(block_number))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit checkpoints");
+        }
+        let check = |expected: &[(StageArg, BlockNumber)]| {
+            Command { command: Subcommands::Assert { expected: expected.to_vec() } }
+                .execute_blocking(&tool)
+        };
+
+        check(&[(StageArg::Headers, 100), (StageArg::Bodies, 80)]).expect("matching checkpoints");
+        assert!(check(&[(StageArg::Headers, 100), (StageArg::Bodies, 100)]).is_err());
+        assert!(check(&[(StageArg::Execution, 0)]).is_err());
+
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(
+            checkpoint_mismatches(
+                &provider,
+                [(StageId::Headers, 100), (StageId::Bodies, 100), (StageId::Execution, 0)]
+            )
+            .expect("mismatches"),
+            vec![(StageId::Bodies, 100, Some(80)), (StageId::Execution, 0, None)]
+        );
+    }
 }
@@ -19,17 +19,21 @@ pub mod session;
 /// [`BackoffKind`] definition.
 mod backoff;
 
-pub use peers::reputation::{Reputation, ReputationChangeKind, ReputationChangeWeights};
+pub use peers::reputation::{
+    Reputation, ReputationChangeKind, ReputationChangeWeights, ReputationTier,
+};
 
 pub use backoff::BackoffKind;
+#[cfg(feature = "serde")]
+pub use peers::persist::{compact_persisted, CompactionStats, LenientPersistedPeers};
 pub use peers::{
-    addr::PeerAddr,
+    addr::{IpSubnet, PeerAddr},
     kind::PeerKind,
     reputation::{
         is_banned_reputation, is_connection_failed_reputation, ReputationChangeOutcome,
         DEFAULT_REPUTATION,
     },
     state::PeerConnectionState,
-    ConnectionsConfig, Peer, PeersConfig, PersistedPeerInfo,
+    ConnectionsConfig, DialOutcome, Peer, PeersConfig, PersistedPeerInfo, RequestKinds,
 };
 pub use session::{SessionLimits, SessionsConfig};
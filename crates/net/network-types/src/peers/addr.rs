@@ -1,6 +1,11 @@
 //! `RLPx` (TCP) and `Discovery` (UDP) sockets of a peer.
 
-use std::net::{IpAddr, SocketAddr};
+use reth_network_peers::PeerId;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
 
 /// Represents a peer's address information.
 ///
@@ -9,10 +14,13 @@ use std::net::{IpAddr, SocketAddr};
 /// - `tcp`: A `SocketAddr` representing the peer's data transfer address.
 /// - `udp`: An optional `SocketAddr` representing the peer's discover address. `None` if the peer
 ///   is directly connecting to us or the port is the same to `tcp`'s
+/// - `quic_port`: An optional QUIC port on the `tcp` IP. `None` if the peer isn't known to support
+///   QUIC.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct PeerAddr {
     tcp: SocketAddr,
     udp: Option<SocketAddr>,
+    quic_port: Option<u16>,
 }
 
 impl PeerAddr {
@@ -21,19 +29,50 @@ impl PeerAddr {
         self.tcp
     }
 
+    /// Returns the peer's TCP address if it can be dialed.
+    ///
+    /// Returns `None` if the peer has no usable TCP endpoint, i.e. the TCP port is zero, for
+    /// example for discovery records that only announce a UDP port.
+    pub const fn tcp_addr(&self) -> Option<SocketAddr> {
+        if self.tcp.port() == 0 {
+            return None
+        }
+        Some(self.tcp)
+    }
+
     /// Returns the peer's UDP address.
     pub const fn udp(&self) -> Option<SocketAddr> {
         self.udp
     }
 
+    /// Returns the peer's QUIC port, if known.
+    pub const fn quic_port(&self) -> Option<u16> {
+        self.quic_port
+    }
+
+    /// Returns the peer's QUIC address, which shares the IP of the TCP address, if the QUIC port
+    /// is known.
+    pub const fn quic_addr(&self) -> Option<SocketAddr> {
+        match self.quic_port {
+            Some(port) => Some(SocketAddr::new(self.tcp.ip(), port)),
+            None => None,
+        }
+    }
+
     /// Returns a new `PeerAddr` with the given `tcp` and `udp` addresses.
     pub const fn new(tcp: SocketAddr, udp: Option<SocketAddr>) -> Self {
-        Self { tcp, udp }
+        Self { tcp, udp, quic_port: None }
     }
 
     /// Returns a new `PeerAddr` with a `tcp` address only.
     pub const fn from_tcp(tcp: SocketAddr) -> Self {
-        Self { tcp, udp: None }
+        Self { tcp, udp: None, quic_port: None }
+    }
+
+    /// Sets the peer's QUIC port.
+    pub const fn with_quic_port(mut self, quic_port: Option<u16>) -> Self {
+        self.quic_port = quic_port;
+        self
     }
 
     /// Returns a new `PeerAddr` with the given `tcp` and `udp` ports.
@@ -42,4 +81,76 @@ impl PeerAddr {
         let udp = udp_port.map(|port| SocketAddr::new(ip, port));
         Self::new(tcp, udp)
     }
+
+    /// Returns the subnet of the peer's TCP address, keeping the first `v4_bits` of IPv4 and the
+    /// first `v6_bits` of IPv6 addresses, see [`IpSubnet::new`].
+    pub fn subnet_group(&self, v4_bits: u8, v6_bits: u8) -> IpSubnet {
+        IpSubnet::new(self.tcp.ip(), v4_bits, v6_bits)
+    }
+}
+
+/// Returns the peer ids that appear with more than one distinct address in the given entries,
+/// together with their distinct addresses in order of appearance.
+///
+/// The result is sorted by peer id.
+pub fn find_id_conflicts(
+    entries: impl IntoIterator<Item = (PeerId, PeerAddr)>,
+) -> Vec<(PeerId, Vec<PeerAddr>)> {
+    let mut addrs = HashMap::<PeerId, Vec<PeerAddr>>::new();
+    for (peer_id, addr) in entries {
+        let known = addrs.entry(peer_id).or_default();
+        if !known.contains(&addr) {
+            known.push(addr);
+        }
+    }
+    let mut conflicts = addrs.into_iter().filter(|(_, addrs)| addrs.len() > 1).collect::<Vec<_>>();
+    conflicts.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+    conflicts
+}
+
+/// A group of IP addresses sharing the same network prefix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IpSubnet {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpSubnet {
+    /// Returns the subnet containing `ip`, with a prefix of `v4_bits` for IPv4 and `v6_bits` for
+    /// IPv6 addresses.
+    ///
+    /// IPv4-mapped IPv6 addresses are treated as IPv4 addresses. Prefix lengths are capped at the
+    /// length of the address.
+    pub fn new(ip: IpAddr, v4_bits: u8, v6_bits: u8) -> Self {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => {
+                let prefix_len = v4_bits.min(32);
+                let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+                let network = Ipv4Addr::from(u32::from(ip) & mask);
+                Self { network: network.into(), prefix_len }
+            }
+            IpAddr::V6(ip) => {
+                let prefix_len = v6_bits.min(128);
+                let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+                let network = Ipv6Addr::from(u128::from(ip) & mask);
+                Self { network: network.into(), prefix_len }
+            }
+        }
+    }
+
+    /// Returns the network address of the subnet.
+    pub const fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    /// Returns the prefix length of the subnet.
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+}
+
+impl fmt::Display for IpSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
 }
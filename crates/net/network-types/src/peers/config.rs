@@ -2,16 +2,28 @@
 
 use std::{
     collections::HashSet,
+    fmt::Display,
     io::{self, ErrorKind},
     path::Path,
+    str::FromStr,
     time::Duration,
 };
 
 use reth_net_banlist::{BanList, IpFilter};
 use reth_network_peers::{NodeRecord, TrustedPeer};
-use tracing::info;
-
-use crate::{peers::PersistedPeerInfo, BackoffKind, ReputationChangeWeights};
+use tracing::{info, warn};
+
+use crate::{
+    peers::{
+        reputation::{
+            BANNED_REPUTATION, DEFAULT_DISCOVERED_REPUTATION, DEFAULT_GOSSIP_REPUTATION,
+            DEFAULT_PROBATION_PENALTY_FACTOR, DEFAULT_REPUTATION_DECAY_MIN_AGE,
+            DEFAULT_REPUTATION_DECAY_PER_SECOND, QUARANTINE_REPUTATION, UNBAN_REPUTATION_MARGIN,
+        },
+        MergePolicy, PersistedPeerInfo, DEFAULT_DIAL_HISTORY_CAPACITY,
+    },
+    BackoffKind, Reputation, ReputationChangeWeights,
+};
 
 /// Maximum number of available slots for outbound sessions.
 pub const DEFAULT_MAX_COUNT_PEERS_OUTBOUND: u32 = 100;
@@ -24,9 +36,40 @@ pub const DEFAULT_MAX_COUNT_PEERS_INBOUND: u32 = 30;
 /// This restricts how many outbound dials can be performed concurrently.
 pub const DEFAULT_MAX_COUNT_CONCURRENT_OUTBOUND_DIALS: usize = 30;
 
+/// Maximum number of concurrent outgoing dials to observer peers.
+///
+/// Observers don't count toward the outbound limits, so their dials are restricted separately.
+pub const DEFAULT_MAX_COUNT_CONCURRENT_OBSERVER_DIALS: usize = 5;
+
+/// Environment variable overriding the total number of peers, split like `--max-peers` into one
+/// third outbound and two thirds inbound, see [`PeersConfig::apply_env_overrides`].
+pub const ENV_MAX_PEERS: &str = "RETH_MAX_PEERS";
+
+/// Environment variable overriding [`ConnectionsConfig::max_outbound`].
+pub const ENV_MAX_OUTBOUND_PEERS: &str = "RETH_MAX_OUTBOUND_PEERS";
+
+/// Environment variable overriding [`ConnectionsConfig::max_inbound`].
+pub const ENV_MAX_INBOUND_PEERS: &str = "RETH_MAX_INBOUND_PEERS";
+
+/// Environment variable overriding [`ConnectionsConfig::max_concurrent_outbound_dials`].
+pub const ENV_MAX_CONCURRENT_OUTBOUND_DIALS: &str = "RETH_MAX_CONCURRENT_OUTBOUND_DIALS";
+
+/// Environment variable overriding [`PeersConfig::ban_threshold`].
+pub const ENV_BAN_THRESHOLD: &str = "RETH_BAN_THRESHOLD";
+
+/// Environment variable overriding [`PeersConfig::quarantine_threshold`].
+pub const ENV_QUARANTINE_THRESHOLD: &str = "RETH_QUARANTINE_THRESHOLD";
+
+/// Environment variable overriding [`PeersConfig::trusted_nodes_only`].
+pub const ENV_TRUSTED_NODES_ONLY: &str = "RETH_TRUSTED_NODES_ONLY";
+
 /// A temporary timeout for ips on incoming connection attempts.
 pub const INBOUND_IP_THROTTLE_DURATION: Duration = Duration::from_secs(30);
 
+/// The default quiet period after which a peer's severe backoff counter is decremented, see
+/// [`PeersConfig::backoff_decay_period`].
+pub const DEFAULT_BACKOFF_DECAY_PERIOD: Duration = Duration::from_secs(60 * 60);
+
 /// The durations to use when a backoff should be applied to a peer.
 ///
 /// See also [`BackoffKind`].
@@ -106,6 +149,21 @@ pub struct ConnectionsConfig {
     /// Maximum allowed concurrent outbound dials.
     #[cfg_attr(feature = "serde", serde(default))]
     pub max_concurrent_outbound_dials: usize,
+    /// Maximum allowed concurrent outbound dials to observer peers.
+    ///
+    /// These are not counted toward [`Self::max_concurrent_outbound_dials`].
+    pub max_concurrent_observer_dials: usize,
+    /// Number of outbound slots reserved for trusted peers.
+    ///
+    /// Other peers are only dialed if this many outbound slots remain for trusted peers, and
+    /// outbound peers are evicted to make room for trusted peers waiting to be dialed.
+    pub reserved_trusted_slots: usize,
+    /// Minimum reputation of known peers to accept their inbound sessions.
+    ///
+    /// Inbound sessions of already known, non-trusted peers below this reputation are refused.
+    /// Unknown peers are always accepted since there's no history for them.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub min_inbound_reputation: Option<i32>,
 }
 
 impl Default for ConnectionsConfig {
@@ -114,6 +172,9 @@ impl Default for ConnectionsConfig {
             max_outbound: DEFAULT_MAX_COUNT_PEERS_OUTBOUND as usize,
             max_inbound: DEFAULT_MAX_COUNT_PEERS_INBOUND as usize,
             max_concurrent_outbound_dials: DEFAULT_MAX_COUNT_CONCURRENT_OUTBOUND_DIALS,
+            max_concurrent_observer_dials: DEFAULT_MAX_COUNT_CONCURRENT_OBSERVER_DIALS,
+            reserved_trusted_slots: 0,
+            min_inbound_reputation: None,
         }
     }
 }
@@ -144,6 +205,24 @@ pub struct PeersConfig {
     ///
     /// Note: this does not apply to trusted peers.
     pub max_backoff_count: u8,
+    /// How long a peer must go without a severe backoff before its severe backoff counter is
+    /// decremented by one, see `PeersManager::decay_backoff_counters`.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub backoff_decay_period: Duration,
+    /// Maximum number of severe backoffs after which a peer is considered unreachable and no
+    /// longer dialed until its backoff expires.
+    ///
+    /// Unreachable peers are still retried, so they are removed once they exceed
+    /// [`Self::max_backoff_count`].
+    ///
+    /// Note: this does not apply to trusted and static peers.
+    pub max_dial_attempts: u8,
+    /// Maximum number of idle basic peers to remember.
+    ///
+    /// Discovery can add many peers we never connect to. If this is exceeded, the least recently
+    /// seen basic peers we never connected to are evicted, in batches down to 90% of this limit.
+    /// Peers we have connected to, trusted and static peers are never evicted.
+    pub max_discovered_peers: usize,
     /// Basic nodes to connect to.
     #[cfg_attr(feature = "serde", serde(skip))]
     pub basic_nodes: HashSet<NodeRecord>,
@@ -160,6 +239,54 @@ pub struct PeersConfig {
     pub connection_info: ConnectionsConfig,
     /// How to weigh reputation changes.
     pub reputation_weights: ReputationChangeWeights,
+    /// The reputation below which peers are banned.
+    pub ban_threshold: Reputation,
+    /// How far above the ban threshold a banned peer's reputation must climb before it is
+    /// unbanned.
+    ///
+    /// This prevents peers hovering around the ban threshold from flapping between banned and
+    /// unbanned.
+    pub unban_margin: Reputation,
+    /// The reputation below which peers are quarantined: they stay connected but are excluded
+    /// from request routing.
+    pub quarantine_threshold: Reputation,
+    /// The reputation below which connected peers are skipped for transaction and block
+    /// announcements. Trusted peers are always gossiped to.
+    pub gossip_reputation_threshold: Reputation,
+    /// The initial reputation of peers learned via discovery.
+    ///
+    /// Inbound peers and peers added with an explicit [`PeerKind`](crate::PeerKind) start with the
+    /// default reputation instead.
+    pub discovered_peer_reputation: Reputation,
+    /// The reputation a peer recovers per second, moving towards the default reputation.
+    pub reputation_decay_per_second: Reputation,
+    /// How long a peer must be known before its reputation decays, so that peers penalized right
+    /// after they were discovered aren't forgiven too quickly.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub reputation_decay_min_age: Duration,
+    /// The reputation below which penalties can't push trusted peers, see
+    /// [`PeerKind::reputation_floor`](crate::PeerKind::reputation_floor).
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub trusted_reputation_floor: Option<Reputation>,
+    /// The reputation below which penalties can't push static peers, see
+    /// [`PeerKind::reputation_floor`](crate::PeerKind::reputation_floor).
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub static_reputation_floor: Option<Reputation>,
+    /// How the reputation of a persisted peer is combined with the reputation of an already
+    /// known peer with the same id on startup, see
+    /// [`merge_reputation`](crate::peers::merge_reputation).
+    pub reputation_merge_policy: MergePolicy,
+    /// Initial reputation bonus for new peers in the same subnet as a trusted peer.
+    ///
+    /// Disabled by default.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub trusted_subnet_bonus: Option<Reputation>,
+    /// How long a peer is on probation after it was unbanned.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub probation_duration: Duration,
+    /// The factor by which reputation penalties of peers on probation are amplified, so that
+    /// repeat offenders are banned again quickly.
+    pub probation_penalty_factor: u32,
     /// How long to backoff peers that we are failed to connect to for non-fatal reasons.
     ///
     /// The backoff duration increases with number of backoff attempts.
@@ -180,6 +307,47 @@ pub struct PeersConfig {
     ///
     /// This filters out peers from other networks that pollute the discovery table.
     pub enforce_enr_fork_id: bool,
+    /// If true, static peers get the same protections as trusted peers.
+    ///
+    /// Reputation penalties of static and trusted peers are always capped. This additionally
+    /// keeps static peers in the peer set after fatal protocol errors instead of removing and
+    /// discovery banning them.
+    pub protect_static: bool,
+    /// Number of changed peers after which the peer set should be persisted, see
+    /// [`PersistScheduler`](crate::peers::persist::PersistScheduler).
+    pub persist_dirty_threshold: usize,
+    /// Time after which the peer set should be persisted if any peer changed, see
+    /// [`PersistScheduler`](crate::peers::persist::PersistScheduler).
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub persist_interval: Duration,
+    /// How long a peer with a normal reputation should stay connected before it is considered for
+    /// rotation, see [`Peer::min_connection_duration`](crate::Peer::min_connection_duration).
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub min_connection_duration: Duration,
+    /// Number of recent dial attempts kept per peer, see
+    /// [`Peer::dial_history`](crate::Peer::dial_history).
+    pub dial_history_capacity: usize,
+    /// How long a peer must be known before it is considered long-term, see
+    /// [`Peer::is_long_term`](crate::Peer::is_long_term).
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub long_term_peer_age: Duration,
+    /// How often keepalives are sent to peers with a low reputation, see
+    /// [`Peer::keepalive_interval`](crate::Peer::keepalive_interval).
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub keepalive_interval: Duration,
+    /// The dial and handshake timeout for trusted and well-reputed peers, see
+    /// [`Peer::connect_timeout`](crate::Peer::connect_timeout).
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub connect_timeout: Duration,
+    /// How long a trusted peer may fail to connect before it is reported as unreachable.
+    ///
+    /// Trusted peers are retried regardless, this only surfaces a warning so that misconfigured
+    /// trusted peers are noticed. Disabled by default.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "humantime_serde", skip_serializing_if = "Option::is_none")
+    )]
+    pub trusted_unreachable_warn_after: Option<Duration>,
 }
 
 impl Default for PeersConfig {
@@ -188,6 +356,19 @@ impl Default for PeersConfig {
             refill_slots_interval: Duration::from_millis(5_000),
             connection_info: Default::default(),
             reputation_weights: Default::default(),
+            ban_threshold: BANNED_REPUTATION,
+            unban_margin: UNBAN_REPUTATION_MARGIN,
+            quarantine_threshold: QUARANTINE_REPUTATION,
+            gossip_reputation_threshold: DEFAULT_GOSSIP_REPUTATION,
+            discovered_peer_reputation: DEFAULT_DISCOVERED_REPUTATION,
+            reputation_decay_per_second: DEFAULT_REPUTATION_DECAY_PER_SECOND,
+            reputation_decay_min_age: DEFAULT_REPUTATION_DECAY_MIN_AGE,
+            trusted_reputation_floor: None,
+            static_reputation_floor: None,
+            reputation_merge_policy: MergePolicy::TakeLive,
+            trusted_subnet_bonus: None,
+            probation_duration: Duration::from_secs(60 * 60),
+            probation_penalty_factor: DEFAULT_PROBATION_PENALTY_FACTOR,
             ban_list: Default::default(),
             // Ban peers for 12h
             ban_duration: Duration::from_secs(60 * 60 * 12),
@@ -198,9 +379,21 @@ impl Default for PeersConfig {
             basic_nodes: Default::default(),
             persisted_peers: Default::default(),
             max_backoff_count: 5,
+            backoff_decay_period: DEFAULT_BACKOFF_DECAY_PERIOD,
+            max_dial_attempts: 3,
+            max_discovered_peers: 10_000,
             incoming_ip_throttle_duration: INBOUND_IP_THROTTLE_DURATION,
             ip_filter: IpFilter::default(),
             enforce_enr_fork_id: false,
+            protect_static: false,
+            persist_dirty_threshold: 100,
+            persist_interval: Duration::from_secs(5 * 60),
+            min_connection_duration: Duration::from_secs(10 * 60),
+            dial_history_capacity: DEFAULT_DIAL_HISTORY_CAPACITY,
+            long_term_peer_age: Duration::from_secs(60 * 60),
+            keepalive_interval: Duration::from_secs(15),
+            connect_timeout: Duration::from_secs(20),
+            trusted_unreachable_warn_after: None,
         }
     }
 }
@@ -230,6 +423,19 @@ impl PeersConfig {
         self
     }
 
+    /// Number of outbound slots reserved for trusted peers.
+    pub const fn with_reserved_trusted_slots(mut self, reserved_trusted_slots: usize) -> Self {
+        self.connection_info.reserved_trusted_slots = reserved_trusted_slots;
+        self
+    }
+
+    /// Minimum reputation of known peers to accept their inbound sessions, see
+    /// [`ConnectionsConfig::min_inbound_reputation`].
+    pub const fn with_min_inbound_reputation(mut self, min_inbound_reputation: i32) -> Self {
+        self.connection_info.min_inbound_reputation = Some(min_inbound_reputation);
+        self
+    }
+
     /// Maximum allowed inbound connections with optional update.
     pub const fn with_max_inbound_opt(mut self, max_inbound: Option<usize>) -> Self {
         if let Some(max_inbound) = max_inbound {
@@ -258,6 +464,15 @@ impl PeersConfig {
         self
     }
 
+    /// Maximum allowed concurrent outbound dials to observer peers.
+    pub const fn with_max_concurrent_observer_dials(
+        mut self,
+        max_concurrent_observer_dials: usize,
+    ) -> Self {
+        self.connection_info.max_concurrent_observer_dials = max_concurrent_observer_dials;
+        self
+    }
+
     /// Nodes to always connect to.
     pub fn with_trusted_nodes(mut self, nodes: Vec<TrustedPeer>) -> Self {
         self.trusted_nodes = nodes;
@@ -282,6 +497,24 @@ impl PeersConfig {
         self
     }
 
+    /// Configures the quiet period after which a peer's severe backoff counter is decremented.
+    pub const fn with_backoff_decay_period(mut self, period: Duration) -> Self {
+        self.backoff_decay_period = period;
+        self
+    }
+
+    /// Configures the number of severe backoffs after which a peer is considered unreachable.
+    pub const fn with_max_dial_attempts(mut self, max_dial_attempts: u8) -> Self {
+        self.max_dial_attempts = max_dial_attempts;
+        self
+    }
+
+    /// Configures the maximum number of idle basic peers to remember.
+    pub const fn with_max_discovered_peers(mut self, max_discovered_peers: usize) -> Self {
+        self.max_discovered_peers = max_discovered_peers;
+        self
+    }
+
     /// Configures how to weigh reputation changes.
     pub const fn with_reputation_weights(
         mut self,
@@ -291,6 +524,74 @@ impl PeersConfig {
         self
     }
 
+    /// Configures the reputation below which peers are banned.
+    pub const fn with_ban_threshold(mut self, ban_threshold: Reputation) -> Self {
+        self.ban_threshold = ban_threshold;
+        self
+    }
+
+    /// Configures how far above the ban threshold a banned peer's reputation must climb before it
+    /// is unbanned.
+    pub const fn with_unban_margin(mut self, unban_margin: Reputation) -> Self {
+        self.unban_margin = unban_margin;
+        self
+    }
+
+    /// Configures the reputation below which peers are quarantined.
+    pub const fn with_quarantine_threshold(mut self, quarantine_threshold: Reputation) -> Self {
+        self.quarantine_threshold = quarantine_threshold;
+        self
+    }
+
+    /// Configures the reputation below which peers don't receive announcements.
+    pub const fn with_gossip_reputation_threshold(mut self, threshold: Reputation) -> Self {
+        self.gossip_reputation_threshold = threshold;
+        self
+    }
+
+    /// Configures the initial reputation of peers learned via discovery.
+    pub const fn with_discovered_peer_reputation(mut self, reputation: Reputation) -> Self {
+        self.discovered_peer_reputation = reputation;
+        self
+    }
+
+    /// Configures the reputation a peer recovers per second.
+    pub const fn with_reputation_decay_per_second(mut self, rate: Reputation) -> Self {
+        self.reputation_decay_per_second = rate;
+        self
+    }
+
+    /// Configures how long a peer must be known before its reputation decays.
+    pub const fn with_reputation_decay_min_age(mut self, min_age: Duration) -> Self {
+        self.reputation_decay_min_age = min_age;
+        self
+    }
+
+    /// Configures the reputation below which penalties can't push trusted and static peers.
+    ///
+    /// The trusted floor is expected to be the higher one.
+    pub const fn with_reputation_floors(
+        mut self,
+        trusted_floor: Reputation,
+        static_floor: Reputation,
+    ) -> Self {
+        self.trusted_reputation_floor = Some(trusted_floor);
+        self.static_reputation_floor = Some(static_floor);
+        self
+    }
+
+    /// Configures how persisted reputations are merged with live reputations on startup.
+    pub const fn with_reputation_merge_policy(mut self, policy: MergePolicy) -> Self {
+        self.reputation_merge_policy = policy;
+        self
+    }
+
+    /// Configures the initial reputation bonus for new peers in the same subnet as a trusted peer.
+    pub const fn with_trusted_subnet_bonus(mut self, bonus: Reputation) -> Self {
+        self.trusted_subnet_bonus = Some(bonus);
+        self
+    }
+
     /// Configures how long to backoff peers that are we failed to connect to for non-fatal reasons
     pub const fn with_backoff_durations(mut self, backoff_durations: PeerBackoffDurations) -> Self {
         self.backoff_durations = backoff_durations;
@@ -350,6 +651,127 @@ impl PeersConfig {
         self
     }
 
+    /// Configures how long a peer with a normal reputation should stay connected before it is
+    /// considered for rotation.
+    pub const fn with_min_connection_duration(mut self, duration: Duration) -> Self {
+        self.min_connection_duration = duration;
+        self
+    }
+
+    /// Configures how long a peer must be known before it is considered long-term.
+    pub const fn with_long_term_peer_age(mut self, age: Duration) -> Self {
+        self.long_term_peer_age = age;
+        self
+    }
+
+    /// Configures how often keepalives are sent to peers with a low reputation.
+    pub const fn with_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// Configures the dial and handshake timeout for trusted and well-reputed peers.
+    pub const fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Configures how long a trusted peer may fail to connect before it is reported as
+    /// unreachable.
+    pub const fn with_trusted_unreachable_warn_after(mut self, duration: Duration) -> Self {
+        self.trusted_unreachable_warn_after = Some(duration);
+        self
+    }
+
+    /// Configures how many recent dial attempts are kept per peer.
+    pub const fn with_dial_history_capacity(mut self, capacity: usize) -> Self {
+        self.dial_history_capacity = capacity;
+        self
+    }
+
+    /// Configures how long unbanned peers are on probation and by which factor their penalties
+    /// are amplified in the meantime.
+    pub const fn with_probation(mut self, duration: Duration, penalty_factor: u32) -> Self {
+        self.probation_duration = duration;
+        self.probation_penalty_factor = penalty_factor;
+        self
+    }
+
+    /// If set, static peers get the same protections as trusted peers, see
+    /// [`Self::protect_static`].
+    pub const fn with_protect_static(mut self, protect_static: bool) -> Self {
+        self.protect_static = protect_static;
+        self
+    }
+
+    /// Applies overrides from environment variables, for example in container deployments without
+    /// a config file.
+    ///
+    /// The following variables are read, unset variables are ignored:
+    ///  - [`ENV_MAX_PEERS`]: total number of peers, split like `--max-peers`
+    ///  - [`ENV_MAX_OUTBOUND_PEERS`], [`ENV_MAX_INBOUND_PEERS`]: applied after [`ENV_MAX_PEERS`]
+    ///  - [`ENV_MAX_CONCURRENT_OUTBOUND_DIALS`]
+    ///  - [`ENV_BAN_THRESHOLD`], [`ENV_QUARANTINE_THRESHOLD`]
+    ///  - [`ENV_TRUSTED_NODES_ONLY`]: `true` or `false`
+    ///
+    /// Values that can't be parsed are logged and ignored.
+    pub fn apply_env_overrides(&mut self) {
+        self.apply_overrides(|key| std::env::var(key).ok())
+    }
+
+    /// Same as [`Self::apply_env_overrides`], but reads the variables with the given function.
+    pub fn apply_overrides(&mut self, mut var: impl FnMut(&str) -> Option<String>) {
+        fn parse<T>(var: &mut impl FnMut(&str) -> Option<String>, key: &str) -> Option<T>
+        where
+            T: FromStr,
+            T::Err: Display,
+        {
+            let raw = var(key)?;
+            match raw.trim().parse() {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    warn!(target: "net::peers", key, value = %raw, %err, "Ignoring invalid peers config override");
+                    None
+                }
+            }
+        }
+
+        if let Some(max_peers) = parse::<usize>(&mut var, ENV_MAX_PEERS) {
+            let outbound = if max_peers == 0 { 0 } else { (max_peers / 3).max(1) };
+            self.connection_info.max_outbound = outbound;
+            self.connection_info.max_inbound = max_peers - outbound;
+        }
+        if let Some(max_outbound) = parse(&mut var, ENV_MAX_OUTBOUND_PEERS) {
+            self.connection_info.max_outbound = max_outbound;
+        }
+        if let Some(max_inbound) = parse(&mut var, ENV_MAX_INBOUND_PEERS) {
+            self.connection_info.max_inbound = max_inbound;
+        }
+        if let Some(max_dials) = parse(&mut var, ENV_MAX_CONCURRENT_OUTBOUND_DIALS) {
+            self.connection_info.max_concurrent_outbound_dials = max_dials;
+        }
+        if let Some(ban_threshold) = parse(&mut var, ENV_BAN_THRESHOLD) {
+            self.ban_threshold = ban_threshold;
+        }
+        if let Some(quarantine_threshold) = parse(&mut var, ENV_QUARANTINE_THRESHOLD) {
+            self.quarantine_threshold = quarantine_threshold;
+        }
+        if let Some(trusted_nodes_only) = parse(&mut var, ENV_TRUSTED_NODES_ONLY) {
+            self.trusted_nodes_only = trusted_nodes_only;
+        }
+    }
+
+    /// Configures after how many changed peers and how much time the peer set should be persisted.
+    pub const fn with_persist_thresholds(
+        mut self,
+        dirty_threshold: usize,
+        interval: Duration,
+    ) -> Self {
+        self.persist_dirty_threshold = dirty_threshold;
+        self.persist_interval = interval;
+        self
+    }
+
     /// Returns settings for testing
     #[cfg(any(test, feature = "test-utils"))]
     pub fn test() -> Self {
@@ -361,3 +783,52 @@ impl PeersConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn overrides<'a>(vars: &'a HashMap<&str, &str>) -> impl FnMut(&str) -> Option<String> + 'a {
+        |key| vars.get(key).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let vars = HashMap::from([
+            (ENV_MAX_PEERS, "90"),
+            (ENV_MAX_INBOUND_PEERS, "50"),
+            (ENV_MAX_CONCURRENT_OUTBOUND_DIALS, "7"),
+            (ENV_BAN_THRESHOLD, "-1000"),
+            (ENV_TRUSTED_NODES_ONLY, "true"),
+        ]);
+        let mut config = PeersConfig::default();
+        config.apply_overrides(overrides(&vars));
+
+        assert_eq!(config.connection_info.max_outbound, 30);
+        assert_eq!(config.connection_info.max_inbound, 50);
+        assert_eq!(config.connection_info.max_concurrent_outbound_dials, 7);
+        assert_eq!(config.ban_threshold, -1000);
+        assert!(config.trusted_nodes_only);
+
+        // unset variables are ignored
+        let defaults = PeersConfig::default();
+        assert_eq!(config.quarantine_threshold, defaults.quarantine_threshold);
+    }
+
+    #[test]
+    fn test_apply_invalid_overrides() {
+        let vars = HashMap::from([
+            (ENV_MAX_OUTBOUND_PEERS, "many"),
+            (ENV_QUARANTINE_THRESHOLD, "-500"),
+            (ENV_TRUSTED_NODES_ONLY, "yes"),
+        ]);
+        let mut config = PeersConfig::default();
+        config.apply_overrides(overrides(&vars));
+
+        let defaults = PeersConfig::default();
+        assert_eq!(config.connection_info.max_outbound, defaults.connection_info.max_outbound);
+        assert_eq!(config.trusted_nodes_only, defaults.trusted_nodes_only);
+        assert_eq!(config.quarantine_threshold, -500);
+    }
+}
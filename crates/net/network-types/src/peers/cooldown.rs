@@ -0,0 +1,84 @@
+//! Reconnect scheduling for backed off peers.
+
+use std::{cmp::Reverse, collections::BinaryHeap, time::Instant};
+
+use reth_network_peers::PeerId;
+
+/// A queue of peers ordered by the instant their cooldown, e.g. a backoff, expires.
+///
+/// Peers are popped once their cooldown elapsed, earliest first, see [`CooldownQueue::pop_ready`].
+/// A peer pushed multiple times is returned once per push.
+#[derive(Debug, Clone, Default)]
+pub struct CooldownQueue {
+    heap: BinaryHeap<Reverse<(Instant, PeerId)>>,
+}
+
+impl CooldownQueue {
+    /// Returns an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the peer, which becomes ready at `ready_at`.
+    pub fn push(&mut self, peer_id: PeerId, ready_at: Instant) {
+        self.heap.push(Reverse((ready_at, peer_id)));
+    }
+
+    /// Removes and returns all peers whose cooldown elapsed at `now`, earliest first.
+    pub fn pop_ready(&mut self, now: Instant) -> Vec<PeerId> {
+        let mut ready = Vec::new();
+        while let Some(Reverse((ready_at, peer_id))) = self.heap.peek() &&
+            *ready_at <= now
+        {
+            ready.push(*peer_id);
+            self.heap.pop();
+        }
+        ready
+    }
+
+    /// Returns the instant the next peer becomes ready, if any.
+    pub fn next_ready_at(&self) -> Option<Instant> {
+        self.heap.peek().map(|Reverse((ready_at, _))| *ready_at)
+    }
+
+    /// Returns the number of queued peers.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Returns `true` if no peers are queued.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pop_ready() {
+        let now = Instant::now();
+        let secs = Duration::from_secs;
+        let mut queue = CooldownQueue::new();
+        queue.push(PeerId::repeat_byte(3), now + secs(30));
+        queue.push(PeerId::repeat_byte(1), now + secs(10));
+        queue.push(PeerId::repeat_byte(2), now + secs(20));
+        queue.push(PeerId::repeat_byte(4), now);
+
+        assert_eq!(queue.pop_ready(now), vec![PeerId::repeat_byte(4)]);
+        assert!(queue.pop_ready(now + secs(5)).is_empty());
+        assert_eq!(queue.next_ready_at(), Some(now + secs(10)));
+
+        assert_eq!(
+            queue.pop_ready(now + secs(25)),
+            vec![PeerId::repeat_byte(1), PeerId::repeat_byte(2)]
+        );
+        assert_eq!(queue.len(), 1);
+
+        assert_eq!(queue.pop_ready(now + secs(60)), vec![PeerId::repeat_byte(3)]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.next_ready_at(), None);
+    }
+}
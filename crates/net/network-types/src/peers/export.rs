@@ -0,0 +1,120 @@
+//! Helpers for exporting the peer set in operator friendly formats.
+
+use std::io::{self, Write};
+
+use reth_network_peers::{NodeRecord, PeerId};
+
+use crate::Peer;
+
+/// Header row written by [`write_reputation_csv`].
+pub const REPUTATION_CSV_HEADER: &str = "id,ip,kind,reputation,backed_off,severe_backoff_counter";
+
+/// Writes a CSV report of the given peers' reputation to the writer.
+///
+/// The report starts with [`REPUTATION_CSV_HEADER`] followed by one row per peer.
+pub fn write_reputation_csv<'a, W: Write>(
+    peers: impl IntoIterator<Item = (&'a PeerId, &'a Peer)>,
+    mut writer: W,
+) -> io::Result<()> {
+    writeln!(writer, "{REPUTATION_CSV_HEADER}")?;
+    for (peer_id, peer) in peers {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            csv_field(&format!("{peer_id:?}")),
+            csv_field(&peer.addr.tcp().ip().to_string()),
+            peer.kind.as_str(),
+            peer.reputation,
+            peer.backed_off,
+            peer.severe_backoff_counter,
+        )?;
+    }
+    writer.flush()
+}
+
+/// Returns the trusted and static peers as [`NodeRecord`]s, e.g. to migrate a curated peer set to
+/// another node.
+///
+/// Basic peers, which are mostly learned via discovery, are excluded.
+pub fn export_curated_peers<'a>(
+    peers: impl IntoIterator<Item = (&'a PeerId, &'a Peer)>,
+) -> Vec<NodeRecord> {
+    peers
+        .into_iter()
+        .filter(|(_, peer)| peer.is_trusted() || peer.is_static())
+        .map(|(peer_id, peer)| {
+            NodeRecord::new_with_ports(
+                peer.addr.tcp().ip(),
+                peer.addr.tcp().port(),
+                peer.addr.udp().map(|addr| addr.port()),
+                *peer_id,
+            )
+        })
+        .collect()
+}
+
+/// Quotes the field if it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PeerAddr, PeerKind};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    #[test]
+    fn test_write_reputation_csv() {
+        let peer_id = PeerId::repeat_byte(0xab);
+        let mut peer = Peer::with_kind(
+            PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 30303)),
+            PeerKind::Trusted,
+        );
+        peer.reputation = -1024;
+        peer.backed_off = true;
+        peer.severe_backoff_counter = 3;
+
+        let mut out = Vec::new();
+        write_reputation_csv([(&peer_id, &peer)], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some(REPUTATION_CSV_HEADER));
+        let row = lines.next().unwrap().split(',').collect::<Vec<_>>();
+        assert_eq!(row, [&format!("{peer_id:?}"), "1.2.3.4", "trusted", "-1024", "true", "3"]);
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_export_curated_peers() {
+        let addr =
+            |port| PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port));
+        let trusted = (PeerId::repeat_byte(1), Peer::with_kind(addr(1), PeerKind::Trusted));
+        let static_peer = (PeerId::repeat_byte(2), Peer::with_kind(addr(2), PeerKind::Static));
+        let basic = (PeerId::repeat_byte(3), Peer::with_kind(addr(3), PeerKind::Basic));
+        let discovered = (PeerId::repeat_byte(4), Peer::new_discovered(addr(4)));
+
+        let records = export_curated_peers(
+            [&trusted, &basic, &static_peer, &discovered].map(|(peer_id, peer)| (peer_id, peer)),
+        );
+        assert_eq!(
+            records,
+            vec![
+                NodeRecord::new(addr(1).tcp(), trusted.0),
+                NodeRecord::new(addr(2).tcp(), static_peer.0),
+            ]
+        );
+    }
+}
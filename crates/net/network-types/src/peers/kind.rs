@@ -1,5 +1,7 @@
 //! Classification of a peer based on trust.
 
+use crate::{PeersConfig, Reputation};
+
 /// Represents the kind of peer
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -12,6 +14,11 @@ pub enum PeerKind {
     Static,
     /// Trusted peer.
     Trusted,
+    /// Peer that is connected to purely to observe it.
+    ///
+    /// Observers are not routed requests to, are not counted toward the connection limits and
+    /// are never disconnected or banned because of their reputation.
+    Observer,
 }
 
 impl PeerKind {
@@ -29,4 +36,63 @@ impl PeerKind {
     pub const fn is_basic(&self) -> bool {
         matches!(self, Self::Basic)
     }
+
+    /// Returns `true` if the peer is an observer.
+    pub const fn is_observer(&self) -> bool {
+        matches!(self, Self::Observer)
+    }
+
+    /// Returns the reputation below which penalties can't push peers of this kind, as configured
+    /// by [`PeersConfig::trusted_reputation_floor`] and [`PeersConfig::static_reputation_floor`].
+    ///
+    /// Basic peers and observers have no floor, which is [`Reputation::MIN`].
+    pub const fn reputation_floor(&self, config: &PeersConfig) -> Reputation {
+        self.reputation_floor_with(config.trusted_reputation_floor, config.static_reputation_floor)
+    }
+
+    /// Same as [`Self::reputation_floor`], but with the given floors for trusted and static peers.
+    pub const fn reputation_floor_with(
+        &self,
+        trusted_floor: Option<Reputation>,
+        static_floor: Option<Reputation>,
+    ) -> Reputation {
+        let floor = match self {
+            Self::Trusted => trusted_floor,
+            Self::Static => static_floor,
+            Self::Basic | Self::Observer => None,
+        };
+        match floor {
+            Some(floor) => floor,
+            None => Reputation::MIN,
+        }
+    }
+
+    /// Returns the lowercase name of the kind.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Basic => "basic",
+            Self::Static => "static",
+            Self::Trusted => "trusted",
+            Self::Observer => "observer",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reputation_floor() {
+        let config = PeersConfig::default();
+        for kind in [PeerKind::Basic, PeerKind::Static, PeerKind::Trusted, PeerKind::Observer] {
+            assert_eq!(kind.reputation_floor(&config), Reputation::MIN);
+        }
+
+        let config = config.with_reputation_floors(-1000, -5000);
+        assert_eq!(PeerKind::Trusted.reputation_floor(&config), -1000);
+        assert_eq!(PeerKind::Static.reputation_floor(&config), -5000);
+        assert_eq!(PeerKind::Basic.reputation_floor(&config), Reputation::MIN);
+        assert_eq!(PeerKind::Observer.reputation_floor(&config), Reputation::MIN);
+    }
 }
@@ -1,21 +1,71 @@
 pub mod addr;
 pub mod config;
+pub mod cooldown;
+pub mod export;
 pub mod kind;
+pub mod persist;
 pub mod reputation;
+pub mod snapshot;
 pub mod state;
 
 pub use config::{ConnectionsConfig, PeersConfig};
-pub use reputation::{Reputation, ReputationChange, ReputationChangeKind, ReputationChangeWeights};
+pub use reputation::{
+    merge_reputation, MergePolicy, ProtocolId, Reputation, ReputationChange, ReputationChangeKind,
+    ReputationChangeWeights, ReputationTier,
+};
 
 use alloy_eip2124::ForkId;
 use reth_network_peers::{NodeRecord, PeerId};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
 use tracing::trace;
 
 use crate::{
-    is_banned_reputation, PeerAddr, PeerConnectionState, PeerKind, ReputationChangeOutcome,
+    is_banned_reputation,
+    peers::reputation::{
+        BANNED_REPUTATION, DEFAULT_DISCOVERED_REPUTATION, DEFAULT_REPUTATION_DECAY_MIN_AGE,
+        DEFAULT_REPUTATION_DECAY_PER_SECOND, GOOD_RESPONSE_REPUTATION_CHANGE, MAX_REPUTATION,
+        QUARANTINE_REPUTATION,
+    },
+    BackoffKind, PeerAddr, PeerConnectionState, PeerKind, ReputationChangeOutcome,
     DEFAULT_REPUTATION,
 };
 
+/// Number of severe backoffs after which a peer is always backed off with [`BackoffKind::High`].
+const HIGH_BACKOFF_SEVERE_COUNT: u8 = 3;
+
+/// The default number of dial attempts kept in [`Peer::dial_history`].
+pub const DEFAULT_DIAL_HISTORY_CAPACITY: usize = 8;
+
+/// The outcome of an attempt to dial a peer, see [`Peer::record_dial`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialOutcome {
+    /// A session was established.
+    Connected,
+    /// The TCP connection could not be established.
+    ConnectionFailed,
+    /// The connection was established, but the session was dropped during authentication or the
+    /// handshake.
+    HandshakeFailed,
+}
+
+bitflags::bitflags! {
+    /// Kinds of requests a peer can be preferred for, see [`Peer::set_preferred`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct RequestKinds: u8 {
+        /// Block header requests.
+        const HEADERS = 1 << 0;
+        /// Block body requests.
+        const BODIES = 1 << 1;
+        /// State requests.
+        const STATE = 1 << 2;
+        /// Receipt requests.
+        const RECEIPTS = 1 << 3;
+    }
+}
+
 /// Tracks info about a single peer.
 #[derive(Debug, Clone)]
 pub struct Peer {
@@ -23,12 +73,17 @@ pub struct Peer {
     pub addr: PeerAddr,
     /// Reputation of the peer.
     pub reputation: i32,
+    /// The highest reputation the peer reached, see [`Peer::record_peak_reputation`].
+    pub peak_reputation: i32,
     /// The state of the connection, if any.
     pub state: PeerConnectionState,
     /// The [`ForkId`] that the peer announced via discovery.
     pub fork_id: Option<Box<ForkId>>,
     /// Whether the entry should be removed after an existing session was terminated.
     pub remove_after_disconnect: bool,
+    /// Whether the session should be closed at the next refill of the outbound slots, e.g.
+    /// because it exceeds lowered connection limits.
+    pub pending_eviction: bool,
     /// The kind of peer
     pub kind: PeerKind,
     /// Whether the peer is currently backed off.
@@ -36,6 +91,48 @@ pub struct Peer {
     /// Counts number of times the peer was backed off due to a severe
     /// [`BackoffKind`](crate::BackoffKind).
     pub severe_backoff_counter: u8,
+    /// When the peer was last severely backed off, or when its severe backoff counter was last
+    /// decayed.
+    pub last_severe_backoff: Option<Instant>,
+    /// The reputation change that caused the most recent ban of the peer, if any.
+    pub last_ban_reason: Option<ReputationChangeKind>,
+    /// The kind of the most recent penalty applied to the peer, if any.
+    pub last_penalty: Option<ReputationChangeKind>,
+    /// Whether all recent dial attempts to the peer failed.
+    ///
+    /// Unreachable peers are kept in the peer set but are not dialed while they're backed off.
+    /// Once the backoff expires the flag is cleared so the peer is retried, and a successful
+    /// session clears it for good.
+    pub unreachable: bool,
+    /// Whether a session with the peer was established since it was added to the peer set.
+    pub ever_connected: bool,
+    /// When the peer was last added or rediscovered.
+    pub last_seen: Instant,
+    /// When the peer was first added to the peer set.
+    pub first_seen: Instant,
+    /// Reputation per protocol, tracked in addition to the aggregate `reputation`.
+    ///
+    /// Protocols without an entry have a score of [`DEFAULT_REPUTATION`].
+    pub protocol_scores: HashMap<ProtocolId, Reputation>,
+    /// When the reputation of the peer was last decayed, see [`Peer::decay_reputation`].
+    pub last_decay: Instant,
+    /// Whether the peer was banned and its reputation hasn't cleared the unban margin yet, see
+    /// [`Peer::apply_reputation_with_unban_margin`].
+    pub pending_unban: bool,
+    /// Until when the peer is on probation after it was unbanned, see
+    /// [`Peer::probation_adjusted_change`].
+    pub probation_until: Option<Instant>,
+    /// Number of penalties per kind within the escalation window and when the last one was
+    /// applied, see [`Peer::record_penalty`].
+    pub recent_penalties: HashMap<ReputationChangeKind, (u32, Instant)>,
+    /// The most recent dial attempts and their outcome, oldest first, see [`Peer::record_dial`].
+    pub dial_history: VecDeque<(Instant, DialOutcome)>,
+    /// The `eth` protocol version negotiated in the most recent handshake, if known.
+    pub protocol_version: Option<u8>,
+    /// The kinds of requests that should be routed to this peer first.
+    ///
+    /// This is independent of the reputation of the peer.
+    pub preferred_for: RequestKinds,
 }
 
 // === impl Peer ===
@@ -46,6 +143,17 @@ impl Peer {
         Self::with_state(addr, Default::default())
     }
 
+    /// Returns a new peer learned via discovery for the given [`PeerAddr`].
+    ///
+    /// Unlike [`Self::new`], the peer starts with [`DEFAULT_DISCOVERED_REPUTATION`].
+    pub fn new_discovered(addr: PeerAddr) -> Self {
+        Self {
+            reputation: DEFAULT_DISCOVERED_REPUTATION,
+            peak_reputation: DEFAULT_DISCOVERED_REPUTATION,
+            ..Self::new(addr)
+        }
+    }
+
     /// Returns a new trusted peer for given [`PeerAddr`].
     pub fn trusted(addr: PeerAddr) -> Self {
         Self { kind: PeerKind::Trusted, ..Self::new(addr) }
@@ -56,17 +164,131 @@ impl Peer {
         self.reputation
     }
 
+    /// Returns a single line summary of the peer with the given id, suitable for logs.
+    ///
+    /// The format is stable, e.g.
+    /// `id=0x.. ip=1.2.3.4 kind=trusted rep=50 state=out backed_off=false`.
+    pub fn summary(&self, id: &PeerId) -> String {
+        format!(
+            "id={id:#x} ip={} kind={} rep={} state={} backed_off={}",
+            self.addr.tcp().ip(),
+            self.kind.as_str(),
+            self.reputation,
+            self.state.as_str(),
+            self.backed_off
+        )
+    }
+
+    /// Returns the [`ReputationTier`] of the peer for the thresholds of the given config.
+    ///
+    /// Use this instead of comparing [`Self::reputation`] against constants, so that all
+    /// consumers honor the configured thresholds.
+    pub const fn reputation_tier(&self, config: &PeersConfig) -> ReputationTier {
+        ReputationTier::new(self.reputation, config.ban_threshold, config.quarantine_threshold)
+    }
+
+    /// Returns the suggested [`Level`](tracing::Level) for logging events of this peer, based on
+    /// its [`ReputationTier`].
+    ///
+    /// Events of banned and quarantined peers are logged at `TRACE`, so that noisy peers we barely
+    /// trust don't flood the logs. All other peers are logged at `DEBUG`.
+    pub const fn log_level(&self, config: &PeersConfig) -> tracing::Level {
+        match self.reputation_tier(config) {
+            ReputationTier::Banned | ReputationTier::Quarantined => tracing::Level::TRACE,
+            ReputationTier::Normal | ReputationTier::Good | ReputationTier::Excellent => {
+                tracing::Level::DEBUG
+            }
+        }
+    }
+
+    /// Returns how long the peer should stay connected before it is considered for rotation.
+    ///
+    /// This scales [`PeersConfig::min_connection_duration`] with the peer's
+    /// [`ReputationTier`], so that good peers are not churned. Static peers are kept twice as
+    /// long, trusted peers are never rotated and yield [`Duration::MAX`].
+    pub const fn min_connection_duration(&self, config: &PeersConfig) -> Duration {
+        if self.is_trusted() {
+            return Duration::MAX
+        }
+        let factor = match self.reputation_tier(config) {
+            ReputationTier::Banned | ReputationTier::Quarantined => 0,
+            ReputationTier::Normal => 1,
+            ReputationTier::Good => 2,
+            ReputationTier::Excellent => 4,
+        };
+        let factor = if self.is_static() { factor * 2 } else { factor };
+        config.min_connection_duration.saturating_mul(factor)
+    }
+
+    /// Returns how often keepalives should be sent to the peer.
+    ///
+    /// Stable peers are probed less often, while peers we barely trust are probed more often to
+    /// detect dropped connections fast. This scales [`PeersConfig::keepalive_interval`], which
+    /// applies to banned and quarantined peers, with the peer's [`ReputationTier`]: twice as long
+    /// for normal, four times for good and eight times for excellent peers. Trusted peers always
+    /// use the longest interval.
+    pub const fn keepalive_interval(&self, config: &PeersConfig) -> Duration {
+        let factor = if self.is_trusted() {
+            8
+        } else {
+            match self.reputation_tier(config) {
+                ReputationTier::Banned | ReputationTier::Quarantined => 1,
+                ReputationTier::Normal => 2,
+                ReputationTier::Good => 4,
+                ReputationTier::Excellent => 8,
+            }
+        };
+        config.keepalive_interval.saturating_mul(factor)
+    }
+
+    /// Returns how long dialing the peer and the handshake may take before the attempt is
+    /// considered failed.
+    ///
+    /// This scales [`PeersConfig::connect_timeout`] down with the peer's [`ReputationTier`], so
+    /// that attempts to dubious peers fail fast: a quarter for banned and quarantined peers and
+    /// half for normal peers. Static peers get at least half of the timeout, trusted peers always
+    /// get the full timeout.
+    pub fn connect_timeout(&self, config: &PeersConfig) -> Duration {
+        if self.is_trusted() {
+            return config.connect_timeout
+        }
+        let divisor = match self.reputation_tier(config) {
+            ReputationTier::Banned | ReputationTier::Quarantined => 4,
+            ReputationTier::Normal => 2,
+            ReputationTier::Good | ReputationTier::Excellent => 1,
+        };
+        let divisor = if self.is_static() && divisor > 2 { 2 } else { divisor };
+        config.connect_timeout / divisor
+    }
+
     /// Returns a new peer for given [`PeerAddr`] and [`PeerConnectionState`].
     pub fn with_state(addr: PeerAddr, state: PeerConnectionState) -> Self {
         Self {
             addr,
             state,
             reputation: DEFAULT_REPUTATION,
+            peak_reputation: DEFAULT_REPUTATION,
             fork_id: None,
             remove_after_disconnect: false,
+            pending_eviction: false,
             kind: Default::default(),
             backed_off: false,
             severe_backoff_counter: 0,
+            last_severe_backoff: None,
+            last_ban_reason: None,
+            last_penalty: None,
+            unreachable: false,
+            ever_connected: false,
+            last_seen: Instant::now(),
+            first_seen: Instant::now(),
+            protocol_scores: HashMap::new(),
+            last_decay: Instant::now(),
+            pending_unban: false,
+            probation_until: None,
+            recent_penalties: HashMap::new(),
+            dial_history: VecDeque::new(),
+            protocol_version: None,
+            preferred_for: RequestKinds::empty(),
         }
     }
 
@@ -84,49 +306,441 @@ impl Peer {
     }
 
     /// Applies a reputation change to the peer and returns what action should be taken.
+    ///
+    /// The resulting reputation is capped at [`MAX_REPUTATION`].
     pub fn apply_reputation(
         &mut self,
         reputation: i32,
         kind: ReputationChangeKind,
     ) -> ReputationChangeOutcome {
+        self.apply_reputation_detailed(reputation, kind).0
+    }
+
+    /// Applies a reputation change to the peer and returns what action should be taken together
+    /// with the delta that was actually applied.
+    ///
+    /// The applied delta differs from the requested change if the resulting reputation saturated
+    /// or was capped at [`MAX_REPUTATION`].
+    pub fn apply_reputation_detailed(
+        &mut self,
+        reputation: i32,
+        kind: ReputationChangeKind,
+    ) -> (ReputationChangeOutcome, i32) {
+        self.apply_reputation_detailed_with_threshold(reputation, kind, BANNED_REPUTATION)
+    }
+
+    /// Applies a reputation change to the peer and returns what action should be taken, using the
+    /// given ban threshold instead of [`BANNED_REPUTATION`].
+    ///
+    /// The resulting reputation is capped at [`MAX_REPUTATION`].
+    pub fn apply_reputation_with_threshold(
+        &mut self,
+        reputation: i32,
+        kind: ReputationChangeKind,
+        ban_threshold: Reputation,
+    ) -> ReputationChangeOutcome {
+        self.apply_reputation_detailed_with_threshold(reputation, kind, ban_threshold).0
+    }
+
+    /// Same as [`Self::apply_reputation_detailed`], but using the given ban threshold instead of
+    /// [`BANNED_REPUTATION`].
+    pub fn apply_reputation_detailed_with_threshold(
+        &mut self,
+        reputation: i32,
+        kind: ReputationChangeKind,
+        ban_threshold: Reputation,
+    ) -> (ReputationChangeOutcome, i32) {
+        self.apply_reputation_detailed_with_unban_margin(reputation, kind, ban_threshold, 0)
+    }
+
+    /// Same as [`Self::apply_reputation_with_threshold`], but a banned peer is only unbanned once
+    /// its reputation reached `unban_margin` above the ban threshold.
+    ///
+    /// This prevents a peer that hovers around the ban threshold from being banned and unbanned
+    /// repeatedly.
+    pub fn apply_reputation_with_unban_margin(
+        &mut self,
+        reputation: i32,
+        kind: ReputationChangeKind,
+        ban_threshold: Reputation,
+        unban_margin: Reputation,
+    ) -> ReputationChangeOutcome {
+        self.apply_reputation_detailed_with_unban_margin(
+            reputation,
+            kind,
+            ban_threshold,
+            unban_margin,
+        )
+        .0
+    }
+
+    /// Same as [`Self::apply_reputation_detailed_with_threshold`], but using the given unban
+    /// margin, see [`Self::apply_reputation_with_unban_margin`].
+    ///
+    /// Trusted peers are not exempt from the ban checks: callers soften their penalties, but a
+    /// trusted peer that still drops below the ban threshold is banned like any other peer.
+    pub fn apply_reputation_detailed_with_unban_margin(
+        &mut self,
+        reputation: i32,
+        kind: ReputationChangeKind,
+        ban_threshold: Reputation,
+        unban_margin: Reputation,
+    ) -> (ReputationChangeOutcome, i32) {
         let previous = self.reputation;
+        if reputation < 0 {
+            self.last_penalty = Some(kind);
+        }
         // we add reputation since negative reputation change decrease total reputation
-        self.reputation = previous.saturating_add(reputation);
+        self.reputation = previous.saturating_add(reputation).min(MAX_REPUTATION);
+        let applied = self.reputation - previous;
+        let outcome = self.on_reputation_updated(previous, kind, ban_threshold, unban_margin);
+        (outcome, applied)
+    }
+
+    /// Sets the reputation of the peer to the given value and returns what action should be taken,
+    /// using the given ban threshold and unban margin, see
+    /// [`Self::apply_reputation_with_unban_margin`].
+    ///
+    /// The reputation is capped at [`MAX_REPUTATION`].
+    pub fn set_reputation_with_unban_margin(
+        &mut self,
+        reputation: Reputation,
+        kind: ReputationChangeKind,
+        ban_threshold: Reputation,
+        unban_margin: Reputation,
+    ) -> ReputationChangeOutcome {
+        let previous = self.reputation;
+        self.reputation = reputation.min(MAX_REPUTATION);
+        self.on_reputation_updated(previous, kind, ban_threshold, unban_margin)
+    }
+
+    /// Determines the ban transition after the reputation changed from `previous` to the current
+    /// value.
+    fn on_reputation_updated(
+        &mut self,
+        previous: Reputation,
+        kind: ReputationChangeKind,
+        ban_threshold: Reputation,
+        unban_margin: Reputation,
+    ) -> ReputationChangeOutcome {
+        self.record_peak_reputation();
+        let is_banned = self.is_banned_with_threshold(ban_threshold);
+        trace!(target: "net::peers", reputation=%self.reputation, banned=%is_banned, ?kind, "applied reputation change");
 
-        trace!(target: "net::peers", reputation=%self.reputation, banned=%self.is_banned(), ?kind, "applied reputation change");
+        if self.is_observer() {
+            // observers are only scored, they're never disconnected or banned
+            return ReputationChangeOutcome::None
+        }
 
-        if self.state.is_connected() && self.is_banned() {
+        if self.state.is_connected() && is_banned {
             self.state.disconnect();
+            self.last_ban_reason = Some(kind);
+            self.pending_unban = true;
             return ReputationChangeOutcome::DisconnectAndBan
         }
 
-        if self.is_banned() && !is_banned_reputation(previous) {
-            return ReputationChangeOutcome::Ban
+        let unban_threshold = ban_threshold.saturating_add(unban_margin);
+        let was_banned =
+            previous < ban_threshold || (self.pending_unban && previous < unban_threshold);
+        if is_banned {
+            self.pending_unban = true;
+            if !was_banned {
+                self.last_ban_reason = Some(kind);
+                return ReputationChangeOutcome::Ban
+            }
+        } else if was_banned {
+            if self.reputation < unban_threshold {
+                // still within the margin, stay banned
+                self.pending_unban = true;
+            } else {
+                self.pending_unban = false;
+                return ReputationChangeOutcome::Unban
+            }
         }
 
-        if !self.is_banned() && is_banned_reputation(previous) {
-            return ReputationChangeOutcome::Unban
+        ReputationChangeOutcome::None
+    }
+
+    /// Applies a reputation change attributed to the given protocol.
+    ///
+    /// The change is applied to both the score of the protocol and the aggregate reputation, see
+    /// [`Self::apply_reputation`], which also determines the returned outcome. The protocol score
+    /// is capped at [`MAX_REPUTATION`] as well.
+    pub fn apply_protocol_reputation(
+        &mut self,
+        protocol: ProtocolId,
+        reputation: i32,
+        kind: ReputationChangeKind,
+    ) -> ReputationChangeOutcome {
+        let score = self.protocol_scores.entry(protocol).or_insert(DEFAULT_REPUTATION);
+        *score = score.saturating_add(reputation).min(MAX_REPUTATION);
+        self.apply_reputation(reputation, kind)
+    }
+
+    /// Returns the reputation of the peer for the given protocol.
+    pub fn protocol_score(&self, protocol: ProtocolId) -> Reputation {
+        self.protocol_scores.get(&protocol).copied().unwrap_or(DEFAULT_REPUTATION)
+    }
+
+    /// Credits the peer for a successful response using the default
+    /// [`ReputationChangeKind::GoodResponse`] weight.
+    ///
+    /// This allows peers to recover from transient penalties over time.
+    pub fn reward_good_response(&mut self) -> ReputationChangeOutcome {
+        self.apply_reputation(GOOD_RESPONSE_REPUTATION_CHANGE, ReputationChangeKind::GoodResponse)
+    }
+
+    /// Decays the reputation of the peer towards [`DEFAULT_REPUTATION`] for the time elapsed since
+    /// the last decay, at [`DEFAULT_REPUTATION_DECAY_PER_SECOND`], once the peer is older than
+    /// [`DEFAULT_REPUTATION_DECAY_MIN_AGE`].
+    ///
+    /// Returns [`ReputationChangeOutcome::Unban`] if the peer is no longer banned as a result.
+    pub fn decay_reputation(&mut self, now: Instant) -> ReputationChangeOutcome {
+        self.decay_reputation_with_min_age(
+            now,
+            DEFAULT_REPUTATION_DECAY_PER_SECOND,
+            BANNED_REPUTATION,
+            DEFAULT_REPUTATION_DECAY_MIN_AGE,
+        )
+    }
+
+    /// Same as [`Self::decay_reputation_with_rate`], but only decays the reputation if the peer has
+    /// been known for at least `min_age`, see [`Self::first_seen`].
+    ///
+    /// Younger peers keep their reputation, and the time before they matured is never credited
+    /// towards the decay.
+    pub fn decay_reputation_with_min_age(
+        &mut self,
+        now: Instant,
+        rate_per_second: Reputation,
+        ban_threshold: Reputation,
+        min_age: Duration,
+    ) -> ReputationChangeOutcome {
+        let Some(matured) = self.first_seen.checked_add(min_age) else {
+            return ReputationChangeOutcome::None
+        };
+        if now < matured {
+            return ReputationChangeOutcome::None
+        }
+        self.last_decay = self.last_decay.max(matured);
+        self.decay_reputation_with_rate(now, rate_per_second, ban_threshold)
+    }
+
+    /// Same as [`Self::decay_reputation`], but using the given rate and ban threshold.
+    ///
+    /// Elapsed time that is too short to change the reputation is not consumed, so calling this
+    /// frequently does not prevent the decay.
+    pub fn decay_reputation_with_rate(
+        &mut self,
+        now: Instant,
+        rate_per_second: Reputation,
+        ban_threshold: Reputation,
+    ) -> ReputationChangeOutcome {
+        if self.reputation == DEFAULT_REPUTATION {
+            self.last_decay = now;
+            return ReputationChangeOutcome::None
         }
 
+        let elapsed = now.saturating_duration_since(self.last_decay);
+        let decay = elapsed.as_millis().saturating_mul(rate_per_second.max(0) as u128) / 1000;
+        let decay = decay.min(i64::MAX as u128) as i64;
+        if decay == 0 {
+            return ReputationChangeOutcome::None
+        }
+        self.last_decay = now;
+
+        let was_banned = self.is_banned_with_threshold(ban_threshold) || self.pending_unban;
+        let change = (DEFAULT_REPUTATION as i64 - self.reputation as i64).clamp(-decay, decay);
+        self.reputation = (self.reputation as i64 + change) as Reputation;
+        self.record_peak_reputation();
+        trace!(target: "net::peers", reputation=%self.reputation, "decayed reputation");
+
+        if was_banned && !self.is_banned_with_threshold(ban_threshold) {
+            self.pending_unban = false;
+            return ReputationChangeOutcome::Unban
+        }
         ReputationChangeOutcome::None
     }
 
+    /// Records a penalty of the given kind at `now` and returns how many penalties of the same kind
+    /// preceded it, each within `window` of the next.
+    ///
+    /// This drives the escalation of repeated penalties, see
+    /// [`ReputationChangeWeights::escalated_change`].
+    pub fn record_penalty(
+        &mut self,
+        kind: ReputationChangeKind,
+        now: Instant,
+        window: Duration,
+    ) -> u32 {
+        let (count, last) = self.recent_penalties.entry(kind).or_insert((0, now));
+        let repeats =
+            if *count > 0 && now.saturating_duration_since(*last) <= window { *count } else { 0 };
+        *count = repeats.saturating_add(1);
+        *last = now;
+        repeats
+    }
+
+    /// Puts the peer on probation for the given duration, starting at `now`.
+    pub fn start_probation(&mut self, now: Instant, duration: Duration) {
+        self.probation_until = now.checked_add(duration);
+    }
+
+    /// Returns true if the peer is on probation at the given instant.
+    pub fn is_on_probation(&self, now: Instant) -> bool {
+        self.probation_until.is_some_and(|until| now < until)
+    }
+
+    /// Returns the reputation change to apply to the peer at the given instant.
+    ///
+    /// Penalties of a peer on probation are amplified by `penalty_factor`, other changes are
+    /// returned unchanged.
+    pub fn probation_adjusted_change(
+        &self,
+        reputation: i32,
+        now: Instant,
+        penalty_factor: u32,
+    ) -> i32 {
+        if reputation < 0 && self.is_on_probation(now) {
+            reputation.saturating_mul(i32::try_from(penalty_factor).unwrap_or(i32::MAX))
+        } else {
+            reputation
+        }
+    }
+
     /// Returns true if the peer's reputation is below the banned threshold.
     #[inline]
     pub const fn is_banned(&self) -> bool {
         is_banned_reputation(self.reputation)
     }
 
+    /// Returns true if the peer's reputation is below the given ban threshold.
+    #[inline]
+    pub const fn is_banned_with_threshold(&self, ban_threshold: Reputation) -> bool {
+        self.reputation < ban_threshold
+    }
+
+    /// Returns true if the peer's reputation is in the quarantine band, below
+    /// [`QUARANTINE_REPUTATION`] but not yet banned.
+    ///
+    /// A quarantined peer stays connected but should not be used for requests.
+    #[inline]
+    pub const fn is_quarantined(&self) -> bool {
+        self.is_quarantined_with_thresholds(QUARANTINE_REPUTATION, BANNED_REPUTATION)
+    }
+
+    /// Returns true if the peer's reputation is below the given quarantine threshold but not below
+    /// the given ban threshold.
+    #[inline]
+    pub const fn is_quarantined_with_thresholds(
+        &self,
+        quarantine_threshold: Reputation,
+        ban_threshold: Reputation,
+    ) -> bool {
+        self.reputation < quarantine_threshold && !self.is_banned_with_threshold(ban_threshold)
+    }
+
     /// Returns `true` if peer is banned.
     #[inline]
     pub const fn is_backed_off(&self) -> bool {
         self.backed_off
     }
 
+    /// Returns `true` if all recent dial attempts to the peer failed.
+    #[inline]
+    pub const fn is_unreachable(&self) -> bool {
+        self.unreachable
+    }
+
+    /// Returns `true` if a session with the peer was established since it was added.
+    #[inline]
+    pub const fn was_ever_connected(&self) -> bool {
+        self.ever_connected
+    }
+
+    /// Returns the [`BackoffKind`] that should be applied to the peer based on its reputation and
+    /// how often it was severely backed off before.
+    ///
+    /// - [`BackoffKind::High`] if the reputation dropped to half of [`BANNED_REPUTATION`] or the
+    ///   peer was severely backed off at least three times.
+    /// - [`BackoffKind::Medium`] if the reputation dropped to a quarter of [`BANNED_REPUTATION`] or
+    ///   the peer was severely backed off before.
+    /// - [`BackoffKind::Low`] otherwise.
+    pub const fn backoff_kind(&self) -> BackoffKind {
+        if self.reputation <= BANNED_REPUTATION / 2 ||
+            self.severe_backoff_counter >= HIGH_BACKOFF_SEVERE_COUNT
+        {
+            BackoffKind::High
+        } else if self.reputation <= BANNED_REPUTATION / 4 || self.severe_backoff_counter > 0 {
+            BackoffKind::Medium
+        } else {
+            BackoffKind::Low
+        }
+    }
+
+    /// Records that the peer was backed off with the given [`BackoffKind`].
+    ///
+    /// Only severe backoffs, see [`BackoffKind::is_severe`], increment the
+    /// `severe_backoff_counter`.
+    pub const fn record_backoff(&mut self, kind: &BackoffKind) {
+        if kind.is_severe() {
+            self.severe_backoff_counter = self.severe_backoff_counter.saturating_add(1);
+        }
+    }
+
+    /// Records that the peer was backed off with the given [`BackoffKind`] at `now`, see
+    /// [`Self::record_backoff`].
+    ///
+    /// Severe backoffs also restart the quiet period of [`Self::decay_backoff_counter`].
+    pub const fn record_backoff_at(&mut self, kind: &BackoffKind, now: Instant) {
+        self.record_backoff(kind);
+        if kind.is_severe() {
+            self.last_severe_backoff = Some(now);
+        }
+    }
+
+    /// Decrements the `severe_backoff_counter` by one if the peer wasn't severely backed off for
+    /// at least `quiet_period`.
+    ///
+    /// Each decrement restarts the quiet period, so the counter decays by at most one per period.
+    /// If the time of the last severe backoff is unknown, the quiet period starts at `now`.
+    /// Returns `true` if the counter was decremented.
+    pub fn decay_backoff_counter(&mut self, now: Instant, quiet_period: Duration) -> bool {
+        if self.severe_backoff_counter == 0 {
+            return false
+        }
+        let since = *self.last_severe_backoff.get_or_insert(now);
+        if now.saturating_duration_since(since) < quiet_period {
+            return false
+        }
+        self.severe_backoff_counter -= 1;
+        self.last_severe_backoff = Some(now);
+        true
+    }
+
+    /// Forgives past connection failures once the peer held a connection for longer than
+    /// `threshold`.
+    ///
+    /// This clears the `backed_off` flag and resets the `severe_backoff_counter`, so that past
+    /// flakiness doesn't penalize a now stable peer forever. Returns `true` if the peer was reset.
+    pub const fn on_stable_connection(
+        &mut self,
+        connected_for: Duration,
+        threshold: Duration,
+    ) -> bool {
+        if connected_for.as_nanos() <= threshold.as_nanos() {
+            return false
+        }
+        self.backed_off = false;
+        self.severe_backoff_counter = 0;
+        true
+    }
+
     /// Unbans the peer by resetting its reputation
     #[inline]
     pub const fn unban(&mut self) {
-        self.reputation = DEFAULT_REPUTATION
+        self.reputation = DEFAULT_REPUTATION;
+        self.pending_unban = false;
     }
 
     /// Returns whether this peer is trusted
@@ -140,6 +754,122 @@ impl Peer {
     pub const fn is_static(&self) -> bool {
         matches!(self.kind, PeerKind::Static)
     }
+
+    /// Records the outcome of a dial attempt, keeping the last [`DEFAULT_DIAL_HISTORY_CAPACITY`]
+    /// attempts.
+    pub fn record_dial(&mut self, outcome: DialOutcome) {
+        self.record_dial_with_capacity(Instant::now(), outcome, DEFAULT_DIAL_HISTORY_CAPACITY)
+    }
+
+    /// Same as [`Self::record_dial`], but with the given time and capacity.
+    ///
+    /// The oldest attempts are evicted once more than `capacity` attempts are recorded.
+    pub fn record_dial_with_capacity(
+        &mut self,
+        now: Instant,
+        outcome: DialOutcome,
+        capacity: usize,
+    ) {
+        self.dial_history.push_back((now, outcome));
+        while self.dial_history.len() > capacity {
+            self.dial_history.pop_front();
+        }
+    }
+
+    /// Returns the most recent dial attempts with their outcome, oldest first.
+    ///
+    /// This helps to tell apart peers that always fail from intermittently failing ones.
+    pub const fn dial_history(&self) -> &VecDeque<(Instant, DialOutcome)> {
+        &self.dial_history
+    }
+
+    /// Returns an estimate of the heap memory in bytes allocated by the history buffers of the
+    /// peer, i.e. [`Self::dial_history`] and [`Self::recent_penalties`].
+    pub fn history_memory_estimate(&self) -> usize {
+        self.dial_history.capacity() * size_of::<(Instant, DialOutcome)>() +
+            self.recent_penalties.capacity() * size_of::<(ReputationChangeKind, (u32, Instant))>()
+    }
+
+    /// Releases the unused capacity of the history buffers.
+    pub fn shrink_history(&mut self) {
+        self.dial_history.shrink_to_fit();
+        self.recent_penalties.shrink_to_fit();
+    }
+
+    /// Drops the history buffers of the peer.
+    ///
+    /// This forgets the recent dial attempts and resets the penalty escalation of the peer.
+    pub fn clear_history(&mut self) {
+        self.dial_history = VecDeque::new();
+        self.recent_penalties = HashMap::new();
+    }
+
+    /// Returns the `eth` protocol version negotiated with the peer, if known.
+    pub const fn protocol_version(&self) -> Option<u8> {
+        self.protocol_version
+    }
+
+    /// Sets the `eth` protocol version negotiated in the handshake with the peer.
+    pub const fn set_protocol_version(&mut self, version: u8) {
+        self.protocol_version = Some(version);
+    }
+
+    /// Marks the peer as preferred source for the given kinds of requests, or removes the
+    /// preference if `preferred` is false.
+    ///
+    /// Other kinds of requests are not affected.
+    pub fn set_preferred(&mut self, kinds: RequestKinds, preferred: bool) {
+        self.preferred_for.set(kinds, preferred);
+    }
+
+    /// Returns true if the peer is a preferred source for all of the given kinds of requests.
+    pub const fn is_preferred_for(&self, kinds: RequestKinds) -> bool {
+        !kinds.is_empty() && self.preferred_for.contains(kinds)
+    }
+
+    /// Updates [`Self::peak_reputation`] if the current reputation exceeds it.
+    ///
+    /// This is done by all reputation changes of the peer, callers that set
+    /// [`Self::reputation`] directly should call this afterwards.
+    pub const fn record_peak_reputation(&mut self) {
+        if self.reputation > self.peak_reputation {
+            self.peak_reputation = self.reputation;
+        }
+    }
+
+    /// Returns `true` if the peer has been reliably present for a long time, so it should be
+    /// preferred over transient peers.
+    ///
+    /// A peer is long-term if all of the following hold:
+    /// - it is not an observer and not currently banned,
+    /// - it has been known for at least [`PeersConfig::long_term_peer_age`],
+    /// - it is trusted or static, or its peak reputation reached [`ReputationTier::Good`].
+    pub fn is_long_term(&self, config: &PeersConfig) -> bool {
+        self.is_long_term_at(config, Instant::now())
+    }
+
+    /// Same as [`Self::is_long_term`], but at the given time.
+    pub fn is_long_term_at(&self, config: &PeersConfig, now: Instant) -> bool {
+        if self.is_observer() || self.is_banned_with_threshold(config.ban_threshold) {
+            return false
+        }
+        if now.saturating_duration_since(self.first_seen) < config.long_term_peer_age {
+            return false
+        }
+        self.is_trusted() ||
+            self.is_static() ||
+            ReputationTier::new(
+                self.peak_reputation,
+                config.ban_threshold,
+                config.quarantine_threshold,
+            ) >= ReputationTier::Good
+    }
+
+    /// Returns whether this peer is an observer, see [`PeerKind::Observer`].
+    #[inline]
+    pub const fn is_observer(&self) -> bool {
+        matches!(self.kind, PeerKind::Observer)
+    }
 }
 
 /// Peer info persisted to disk.
@@ -158,6 +888,15 @@ pub struct PersistedPeerInfo {
     pub fork_id: Option<ForkId>,
     /// The peer's reputation at the time of persisting.
     pub reputation: i32,
+    /// The reputation change that caused the most recent ban of the peer, if any.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub last_ban_reason: Option<ReputationChangeKind>,
+    /// The last known `eth` protocol version of the peer, if any.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub protocol_version: Option<u8>,
+    /// The peer's QUIC port, if known, see [`PeerAddr::quic_port`].
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub quic_port: Option<u16>,
 }
 
 impl PersistedPeerInfo {
@@ -168,6 +907,782 @@ impl PersistedPeerInfo {
 
     /// Converts a legacy [`NodeRecord`] into a [`PersistedPeerInfo`] with default metadata.
     pub const fn from_node_record(record: NodeRecord) -> Self {
-        Self { record, kind: PeerKind::Basic, fork_id: None, reputation: DEFAULT_REPUTATION }
+        Self {
+            record,
+            kind: PeerKind::Basic,
+            fork_id: None,
+            reputation: DEFAULT_REPUTATION,
+            last_ban_reason: None,
+            protocol_version: None,
+            quic_port: None,
+        }
+    }
+}
+
+/// Number of entries above which [`estimate_persisted_size`] extrapolates from a sample instead of
+/// measuring every entry.
+#[cfg(feature = "serde")]
+const PERSISTED_SIZE_SAMPLE_LEN: usize = 256;
+
+/// Returns the approximate size in bytes of the peers file written for the given peers.
+///
+/// The peers file is a pretty printed JSON array. Entries are measured without buffering the
+/// serialized output, and for large sets only an evenly spaced sample is measured and
+/// extrapolated, so this is cheap even for very large peer sets.
+#[cfg(feature = "serde")]
+pub fn estimate_persisted_size(peers: &[PersistedPeerInfo]) -> usize {
+    /// Counts the bytes written to it.
+    #[derive(Default)]
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    if peers.is_empty() {
+        // `[]`
+        return 2
+    }
+
+    let step = peers.len().div_ceil(PERSISTED_SIZE_SAMPLE_LEN);
+    let mut sampled = 0;
+    let mut sampled_bytes = 0;
+    for peer in peers.iter().step_by(step) {
+        let mut counter = Counter::default();
+        // serialize as a single element array to account for the indentation within the array
+        if serde_json::to_writer_pretty(&mut counter, std::slice::from_ref(peer)).is_err() {
+            continue
+        }
+        // strip the `[\n` and `\n]` of the array but account for the `,\n` separator
+        sampled_bytes += counter.0 - 2;
+        sampled += 1;
+    }
+    if sampled == 0 {
+        return 2
+    }
+
+    // `[\n` and `\n]` minus the separator that is not written after the last entry
+    sampled_bytes * peers.len() / sampled + 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    fn peer() -> Peer {
+        Peer::new(PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303)))
+    }
+
+    #[test]
+    fn test_good_responses_recover_banned_peer() {
+        let mut peer = peer();
+        peer.apply_reputation(BANNED_REPUTATION - 1, ReputationChangeKind::BadMessage);
+        assert!(peer.is_banned());
+
+        let mut outcomes = Vec::new();
+        while peer.is_banned() {
+            outcomes.push(peer.reward_good_response());
+        }
+
+        assert!(matches!(outcomes.pop(), Some(ReputationChangeOutcome::Unban)));
+        assert!(outcomes.iter().all(|outcome| matches!(outcome, ReputationChangeOutcome::None)));
+        assert!(peer.reputation() >= BANNED_REPUTATION);
+    }
+
+    #[test]
+    fn test_ban_records_reason() {
+        let mut peer = peer();
+        peer.apply_reputation(BANNED_REPUTATION / 2, ReputationChangeKind::Timeout);
+        assert_eq!(peer.last_ban_reason, None);
+
+        peer.apply_reputation(BANNED_REPUTATION, ReputationChangeKind::BadProtocol);
+        assert_eq!(peer.last_ban_reason, Some(ReputationChangeKind::BadProtocol));
+
+        // the reason is kept after the peer is unbanned
+        peer.unban();
+        assert_eq!(peer.last_ban_reason, Some(ReputationChangeKind::BadProtocol));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_persisted_peer_info_ban_reason_roundtrip() {
+        let record: NodeRecord = "enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301".parse().unwrap();
+        let info = PersistedPeerInfo {
+            last_ban_reason: Some(ReputationChangeKind::BadMessage),
+            ..PersistedPeerInfo::from_node_record(record)
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let decoded: PersistedPeerInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, info);
+
+        // entries persisted before the reason was tracked default to none
+        let legacy = json.replace(r#","last_ban_reason":"BadMessage""#, "");
+        assert!(!legacy.contains("last_ban_reason"));
+        let decoded: PersistedPeerInfo = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(decoded.last_ban_reason, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_persisted_peer_info_protocol_version_roundtrip() {
+        let record: NodeRecord = "enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301".parse().unwrap();
+        let info = PersistedPeerInfo {
+            protocol_version: Some(68),
+            ..PersistedPeerInfo::from_node_record(record)
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let decoded: PersistedPeerInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, info);
+
+        // entries persisted before the version was tracked default to none
+        let legacy = json.replace(r#","protocol_version":68"#, "");
+        assert!(!legacy.contains("protocol_version"));
+        let decoded: PersistedPeerInfo = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(decoded.protocol_version, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_persisted_peer_info_quic_port_roundtrip() {
+        let record: NodeRecord = "enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301".parse().unwrap();
+        let info = PersistedPeerInfo {
+            quic_port: Some(30305),
+            ..PersistedPeerInfo::from_node_record(record)
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let decoded: PersistedPeerInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, info);
+
+        // entries persisted before the QUIC port was tracked default to none
+        let legacy = json.replace(r#","quic_port":30305"#, "");
+        assert!(!legacy.contains("quic_port"));
+        let decoded: PersistedPeerInfo = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(decoded.quic_port, None);
+    }
+
+    #[test]
+    fn test_quic_addr() {
+        let tcp = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 3, 58, 6)), 30303);
+        let addr = PeerAddr::from_tcp(tcp);
+        assert_eq!(addr.quic_port(), None);
+        assert_eq!(addr.quic_addr(), None);
+
+        let addr = addr.with_quic_port(Some(30305));
+        assert_eq!(addr.quic_port(), Some(30305));
+        assert_eq!(addr.quic_addr(), Some(SocketAddr::new(tcp.ip(), 30305)));
+        assert_eq!(addr.tcp(), tcp);
+        assert_eq!(addr.with_quic_port(None), PeerAddr::from_tcp(tcp));
+    }
+
+    #[test]
+    fn test_preferred_for() {
+        let mut peer = peer();
+        assert!(!peer.is_preferred_for(RequestKinds::HEADERS));
+
+        peer.set_preferred(RequestKinds::HEADERS, true);
+        assert!(peer.is_preferred_for(RequestKinds::HEADERS));
+        assert!(!peer.is_preferred_for(RequestKinds::BODIES));
+        assert!(!peer.is_preferred_for(RequestKinds::STATE));
+        assert!(!peer.is_preferred_for(RequestKinds::HEADERS | RequestKinds::BODIES));
+
+        // the preference doesn't depend on the reputation
+        peer.reputation = BANNED_REPUTATION;
+        assert!(peer.is_preferred_for(RequestKinds::HEADERS));
+
+        peer.set_preferred(RequestKinds::BODIES | RequestKinds::STATE, true);
+        peer.set_preferred(RequestKinds::HEADERS, false);
+        assert!(!peer.is_preferred_for(RequestKinds::HEADERS));
+        assert!(peer.is_preferred_for(RequestKinds::BODIES | RequestKinds::STATE));
+    }
+
+    #[test]
+    fn test_protocol_version() {
+        let mut peer = peer();
+        assert_eq!(peer.protocol_version(), None);
+        peer.set_protocol_version(67);
+        assert_eq!(peer.protocol_version(), Some(67));
+        peer.set_protocol_version(68);
+        assert_eq!(peer.protocol_version(), Some(68));
+    }
+
+    #[test]
+    fn test_backoff_kind() {
+        let mut peer = peer();
+        assert_eq!(peer.backoff_kind(), BackoffKind::Low);
+
+        peer.reputation = BANNED_REPUTATION / 4;
+        assert_eq!(peer.backoff_kind(), BackoffKind::Medium);
+
+        peer.reputation = BANNED_REPUTATION / 2;
+        assert_eq!(peer.backoff_kind(), BackoffKind::High);
+
+        let mut peer = self::peer();
+        peer.record_backoff(&BackoffKind::Low);
+        assert_eq!(peer.severe_backoff_counter, 0);
+        assert_eq!(peer.backoff_kind(), BackoffKind::Low);
+
+        peer.record_backoff(&BackoffKind::Medium);
+        assert_eq!(peer.severe_backoff_counter, 1);
+        assert_eq!(peer.backoff_kind(), BackoffKind::Medium);
+
+        peer.record_backoff(&BackoffKind::Medium);
+        peer.record_backoff(&BackoffKind::High);
+        assert_eq!(peer.severe_backoff_counter, 3);
+        assert_eq!(peer.backoff_kind(), BackoffKind::High);
+    }
+
+    #[test]
+    fn test_decay_backoff_counter() {
+        let period = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut peer = peer();
+        assert!(!peer.decay_backoff_counter(now + period, period));
+
+        peer.record_backoff_at(&BackoffKind::Medium, now);
+        peer.record_backoff_at(&BackoffKind::High, now);
+        peer.record_backoff_at(&BackoffKind::Low, now + period);
+        assert_eq!(peer.last_severe_backoff, Some(now));
+
+        // low backoffs don't restart the quiet period
+        assert!(!peer.decay_backoff_counter(now + period / 2, period));
+        assert!(peer.decay_backoff_counter(now + period, period));
+        assert_eq!(peer.severe_backoff_counter, 1);
+
+        // the quiet period restarts after each decrement
+        assert!(!peer.decay_backoff_counter(now + period * 3 / 2, period));
+        assert!(peer.decay_backoff_counter(now + period * 2, period));
+        assert_eq!(peer.severe_backoff_counter, 0);
+        assert!(!peer.decay_backoff_counter(now + period * 10, period));
+    }
+
+    #[test]
+    fn test_good_responses_capped() {
+        let mut peer = peer();
+        peer.reputation = MAX_REPUTATION - 1;
+        peer.reward_good_response();
+        assert_eq!(peer.reputation(), MAX_REPUTATION);
+        peer.reward_good_response();
+        assert_eq!(peer.reputation(), MAX_REPUTATION);
+    }
+
+    #[test]
+    fn test_quarantine_band() {
+        let mut peer = peer();
+        assert!(!peer.is_quarantined());
+
+        peer.reputation = QUARANTINE_REPUTATION;
+        assert!(!peer.is_quarantined());
+
+        peer.reputation = QUARANTINE_REPUTATION - 1;
+        assert!(peer.is_quarantined());
+
+        peer.reputation = BANNED_REPUTATION;
+        assert!(peer.is_quarantined());
+        assert!(!peer.is_banned());
+
+        // banned peers are not quarantined
+        peer.reputation = BANNED_REPUTATION - 1;
+        assert!(!peer.is_quarantined());
+        assert!(peer.is_banned());
+
+        // custom thresholds
+        peer.reputation = -100;
+        assert!(peer.is_quarantined_with_thresholds(-99, -200));
+        assert!(!peer.is_quarantined_with_thresholds(-100, -200));
+        assert!(!peer.is_quarantined_with_thresholds(-99, -99));
+    }
+
+    #[test]
+    fn test_new_discovered_reputation() {
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let inbound = Peer::new(addr);
+        let discovered = Peer::new_discovered(addr);
+
+        assert_eq!(inbound.reputation(), DEFAULT_REPUTATION);
+        assert_eq!(discovered.reputation(), DEFAULT_DISCOVERED_REPUTATION);
+        assert!(discovered.reputation() < inbound.reputation());
+        assert_eq!(discovered.kind, PeerKind::Basic);
+    }
+
+    #[test]
+    fn test_apply_reputation_detailed_delta() {
+        let mut peer = peer();
+        assert_eq!(
+            peer.apply_reputation_detailed(-100, ReputationChangeKind::BadMessage),
+            (ReputationChangeOutcome::None, -100)
+        );
+        assert_eq!(peer.reputation(), -100);
+
+        // capped at the max reputation
+        peer.reputation = MAX_REPUTATION - 10;
+        assert_eq!(
+            peer.apply_reputation_detailed(100, ReputationChangeKind::GoodResponse),
+            (ReputationChangeOutcome::None, 10)
+        );
+        assert_eq!(
+            peer.apply_reputation_detailed(100, ReputationChangeKind::GoodResponse),
+            (ReputationChangeOutcome::None, 0)
+        );
+
+        // saturates at the minimum
+        peer.reputation = i32::MIN + 10;
+        assert_eq!(
+            peer.apply_reputation_detailed(-100, ReputationChangeKind::BadMessage),
+            (ReputationChangeOutcome::None, -10)
+        );
+        assert_eq!(peer.reputation(), i32::MIN);
+
+        // the delta is reported alongside the outcome
+        peer.reputation = BANNED_REPUTATION;
+        assert_eq!(
+            peer.apply_reputation_detailed(-1, ReputationChangeKind::BadMessage),
+            (ReputationChangeOutcome::Ban, -1)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_estimate_persisted_size() {
+        let persisted = |i: usize| {
+            let record = NodeRecord::new(
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, (i / 256) as u8, i as u8)), 30303),
+                PeerId::repeat_byte(i as u8),
+            );
+            PersistedPeerInfo {
+                kind: if i.is_multiple_of(3) { PeerKind::Static } else { PeerKind::Basic },
+                reputation: -(i as i32) * 100,
+                last_ban_reason: i.is_multiple_of(7).then_some(ReputationChangeKind::Timeout),
+                ..PersistedPeerInfo::from_node_record(record)
+            }
+        };
+
+        assert_eq!(
+            estimate_persisted_size(&[]),
+            serde_json::to_vec_pretty(&[(); 0]).unwrap().len()
+        );
+
+        // measured exactly for small sets
+        let peers = (0..10).map(persisted).collect::<Vec<_>>();
+        assert_eq!(
+            estimate_persisted_size(&peers),
+            serde_json::to_vec_pretty(&peers).unwrap().len()
+        );
+
+        // extrapolated within 5% for large sets
+        let peers = (0..5_000).map(persisted).collect::<Vec<_>>();
+        let actual = serde_json::to_vec_pretty(&peers).unwrap().len();
+        let estimate = estimate_persisted_size(&peers);
+        assert!(estimate.abs_diff(actual) * 20 < actual, "estimate {estimate}, actual {actual}");
+    }
+
+    #[test]
+    fn test_reputation_tier() {
+        let mut peer = peer();
+        let config = PeersConfig::default();
+        let tier = |peer: &mut Peer, reputation| {
+            peer.reputation = reputation;
+            peer.reputation_tier(&config)
+        };
+
+        assert_eq!(tier(&mut peer, i32::MIN), ReputationTier::Banned);
+        assert_eq!(tier(&mut peer, BANNED_REPUTATION - 1), ReputationTier::Banned);
+        assert_eq!(tier(&mut peer, BANNED_REPUTATION), ReputationTier::Quarantined);
+        assert_eq!(tier(&mut peer, QUARANTINE_REPUTATION - 1), ReputationTier::Quarantined);
+        assert_eq!(tier(&mut peer, QUARANTINE_REPUTATION), ReputationTier::Normal);
+        assert_eq!(tier(&mut peer, DEFAULT_REPUTATION), ReputationTier::Normal);
+        assert_eq!(tier(&mut peer, -QUARANTINE_REPUTATION - 1), ReputationTier::Normal);
+        assert_eq!(tier(&mut peer, -QUARANTINE_REPUTATION), ReputationTier::Good);
+        assert_eq!(tier(&mut peer, MAX_REPUTATION - 1), ReputationTier::Good);
+        assert_eq!(tier(&mut peer, MAX_REPUTATION), ReputationTier::Excellent);
+
+        // configured thresholds are honored
+        let config = PeersConfig::default().with_ban_threshold(-100).with_quarantine_threshold(-10);
+        peer.reputation = -50;
+        assert_eq!(peer.reputation_tier(&config), ReputationTier::Quarantined);
+        peer.reputation = 10;
+        assert_eq!(peer.reputation_tier(&config), ReputationTier::Good);
+        peer.reputation = 100;
+        assert_eq!(peer.reputation_tier(&config), ReputationTier::Excellent);
+    }
+
+    #[test]
+    fn test_keepalive_interval() {
+        let config = PeersConfig::default().with_keepalive_interval(Duration::from_secs(10));
+        let mut peer = peer();
+        let mut interval = |reputation| {
+            peer.reputation = reputation;
+            peer.keepalive_interval(&config)
+        };
+
+        let low = interval(QUARANTINE_REPUTATION - 1);
+        let normal = interval(DEFAULT_REPUTATION);
+        let high = interval(MAX_REPUTATION);
+        assert_eq!(low, Duration::from_secs(10));
+        assert!(low < normal);
+        assert!(normal < interval(-QUARANTINE_REPUTATION));
+        assert!(normal < high);
+        assert_eq!(high, Duration::from_secs(80));
+
+        // trusted peers use the longest interval regardless of their reputation
+        let mut trusted = Peer::trusted(peer.addr);
+        trusted.reputation = QUARANTINE_REPUTATION - 1;
+        assert_eq!(trusted.keepalive_interval(&config), high);
+    }
+
+    #[test]
+    fn test_connect_timeout() {
+        let config = PeersConfig::default().with_connect_timeout(Duration::from_secs(20));
+        let mut peer = peer();
+        let mut timeout = |reputation| {
+            peer.reputation = reputation;
+            peer.connect_timeout(&config)
+        };
+
+        let low = timeout(QUARANTINE_REPUTATION - 1);
+        let normal = timeout(DEFAULT_REPUTATION);
+        let high = timeout(MAX_REPUTATION);
+        assert_eq!(low, Duration::from_secs(5));
+        assert_eq!(normal, Duration::from_secs(10));
+        assert_eq!(high, Duration::from_secs(20));
+
+        // trusted peers get the full timeout regardless of their reputation
+        let mut trusted = Peer::trusted(peer.addr);
+        trusted.reputation = QUARANTINE_REPUTATION - 1;
+        assert_eq!(trusted.connect_timeout(&config), high);
+        assert!(low < trusted.connect_timeout(&config));
+
+        let mut static_peer = Peer::with_kind(peer.addr, PeerKind::Static);
+        static_peer.reputation = QUARANTINE_REPUTATION - 1;
+        assert_eq!(static_peer.connect_timeout(&config), normal);
+    }
+
+    #[test]
+    fn test_log_level() {
+        let mut peer = peer();
+        let config = PeersConfig::default();
+        let level = |peer: &mut Peer, reputation| {
+            peer.reputation = reputation;
+            peer.log_level(&config)
+        };
+
+        assert_eq!(level(&mut peer, BANNED_REPUTATION - 1), tracing::Level::TRACE);
+        assert_eq!(level(&mut peer, QUARANTINE_REPUTATION - 1), tracing::Level::TRACE);
+        assert_eq!(level(&mut peer, DEFAULT_REPUTATION), tracing::Level::DEBUG);
+        assert_eq!(level(&mut peer, -QUARANTINE_REPUTATION), tracing::Level::DEBUG);
+        assert_eq!(level(&mut peer, MAX_REPUTATION), tracing::Level::DEBUG);
+    }
+
+    #[test]
+    fn test_protocol_reputation() {
+        let mut peer = peer();
+        peer.apply_protocol_reputation(
+            ProtocolId::Headers,
+            GOOD_RESPONSE_REPUTATION_CHANGE,
+            ReputationChangeKind::GoodResponse,
+        );
+        peer.apply_protocol_reputation(
+            ProtocolId::Headers,
+            GOOD_RESPONSE_REPUTATION_CHANGE,
+            ReputationChangeKind::GoodResponse,
+        );
+        peer.apply_protocol_reputation(ProtocolId::Bodies, -2048, ReputationChangeKind::Timeout);
+
+        assert_eq!(peer.protocol_score(ProtocolId::Headers), 2 * GOOD_RESPONSE_REPUTATION_CHANGE);
+        assert_eq!(peer.protocol_score(ProtocolId::Bodies), -2048);
+        assert_eq!(peer.protocol_score(ProtocolId::Receipts), DEFAULT_REPUTATION);
+        assert_eq!(peer.reputation(), 2 * GOOD_RESPONSE_REPUTATION_CHANGE - 2048);
+
+        // changes without a protocol only affect the aggregate
+        peer.apply_reputation(-100, ReputationChangeKind::BadMessage);
+        assert_eq!(peer.protocol_score(ProtocolId::Headers), 2 * GOOD_RESPONSE_REPUTATION_CHANGE);
+        assert_eq!(peer.reputation(), 2 * GOOD_RESPONSE_REPUTATION_CHANGE - 2148);
+    }
+
+    #[test]
+    fn test_decay_reputation() {
+        let mut peer = peer();
+        let start = peer.last_decay;
+        peer.reputation = BANNED_REPUTATION - 10;
+
+        // too short to decay, the elapsed time is not consumed
+        let outcome = peer.decay_reputation_with_rate(
+            start + Duration::from_millis(50),
+            10,
+            BANNED_REPUTATION,
+        );
+        assert_eq!(outcome, ReputationChangeOutcome::None);
+        assert_eq!(peer.reputation, BANNED_REPUTATION - 10);
+        assert_eq!(peer.last_decay, start);
+
+        let outcome = peer.decay_reputation_with_rate(
+            start + Duration::from_millis(500),
+            10,
+            BANNED_REPUTATION,
+        );
+        assert_eq!(outcome, ReputationChangeOutcome::None);
+        assert_eq!(peer.reputation, BANNED_REPUTATION - 5);
+
+        let outcome =
+            peer.decay_reputation_with_rate(start + Duration::from_secs(1), 10, BANNED_REPUTATION);
+        assert_eq!(outcome, ReputationChangeOutcome::Unban);
+        assert_eq!(peer.reputation, BANNED_REPUTATION);
+
+        // decay never overshoots the default reputation, from either side
+        peer.decay_reputation_with_rate(start + Duration::from_secs(3600), i32::MAX, 0);
+        assert_eq!(peer.reputation, DEFAULT_REPUTATION);
+        peer.reputation = MAX_REPUTATION;
+        peer.decay_reputation_with_rate(start + Duration::from_secs(7200), i32::MAX, 0);
+        assert_eq!(peer.reputation, DEFAULT_REPUTATION);
+    }
+
+    #[test]
+    fn test_is_long_term() {
+        let config = PeersConfig::default();
+        let good = config.quarantine_threshold.saturating_neg();
+        let mut peer = peer();
+        let start = peer.first_seen;
+        let old = start + config.long_term_peer_age;
+
+        // a fresh peer is transient, even with a good reputation
+        peer.apply_reputation(good, ReputationChangeKind::GoodResponse);
+        assert_eq!(peer.peak_reputation, good);
+        assert!(!peer.is_long_term_at(&config, start));
+        assert!(peer.is_long_term_at(&config, old));
+
+        // the peak reputation counts, not the current one
+        peer.apply_reputation(-good, ReputationChangeKind::BadMessage);
+        assert_eq!(peer.peak_reputation, good);
+        assert!(peer.is_long_term_at(&config, old));
+
+        // but banned peers are never long-term
+        peer.apply_reputation(BANNED_REPUTATION - 1, ReputationChangeKind::BadProtocol);
+        assert!(!peer.is_long_term_at(&config, old));
+
+        // an old peer that never had a good reputation is transient, unless it's static
+        let mut peer = Peer::new(peer.addr);
+        let old = peer.first_seen + config.long_term_peer_age;
+        assert!(!peer.is_long_term_at(&config, old));
+        peer.kind = PeerKind::Static;
+        assert!(peer.is_long_term_at(&config, old));
+        peer.kind = PeerKind::Observer;
+        assert!(!peer.is_long_term_at(&config, old));
+    }
+
+    #[test]
+    fn test_dial_history() {
+        let mut peer = peer();
+        let start = Instant::now();
+        let outcomes =
+            [DialOutcome::ConnectionFailed, DialOutcome::Connected, DialOutcome::HandshakeFailed];
+        for (secs, outcome) in outcomes.into_iter().enumerate() {
+            peer.record_dial_with_capacity(start + Duration::from_secs(secs as u64), outcome, 2);
+        }
+
+        // the oldest attempt was evicted
+        assert_eq!(
+            peer.dial_history().iter().copied().collect::<Vec<_>>(),
+            vec![
+                (start + Duration::from_secs(1), DialOutcome::Connected),
+                (start + Duration::from_secs(2), DialOutcome::HandshakeFailed),
+            ]
+        );
+
+        // no history is kept without capacity
+        peer.record_dial_with_capacity(start, DialOutcome::Connected, 0);
+        assert!(peer.dial_history().is_empty());
+    }
+
+    #[test]
+    fn test_history_memory_estimate() {
+        let mut peer = peer();
+        assert_eq!(peer.history_memory_estimate(), 0);
+
+        let now = Instant::now();
+        for _ in 0..8 {
+            peer.record_dial_with_capacity(now, DialOutcome::ConnectionFailed, 8);
+        }
+        peer.record_penalty(ReputationChangeKind::Timeout, now, Duration::from_secs(60));
+        let estimate = peer.history_memory_estimate();
+        assert!(estimate >= 8 * size_of::<(Instant, DialOutcome)>());
+
+        // shrinking keeps the history
+        peer.record_dial_with_capacity(now, DialOutcome::Connected, 1);
+        peer.shrink_history();
+        assert!(peer.history_memory_estimate() < estimate);
+        assert_eq!(peer.dial_history().len(), 1);
+
+        peer.clear_history();
+        assert_eq!(peer.history_memory_estimate(), 0);
+        assert!(peer.dial_history().is_empty());
+        assert!(peer.recent_penalties.is_empty());
+    }
+
+    #[test]
+    fn test_trusted_peer_ban_outcomes() {
+        let mut trusted = peer();
+        trusted.kind = PeerKind::Trusted;
+        trusted.state = PeerConnectionState::Out;
+        let mut basic = peer();
+        basic.state = PeerConnectionState::Out;
+
+        for peer in [&mut trusted, &mut basic] {
+            assert_eq!(
+                peer.apply_reputation(-1000, ReputationChangeKind::BadMessage),
+                ReputationChangeOutcome::None
+            );
+            assert_eq!(
+                peer.apply_reputation(BANNED_REPUTATION, ReputationChangeKind::BadMessage),
+                ReputationChangeOutcome::DisconnectAndBan
+            );
+            assert_eq!(
+                peer.apply_reputation(-BANNED_REPUTATION, ReputationChangeKind::GoodResponse),
+                ReputationChangeOutcome::Unban
+            );
+        }
+        assert_eq!(trusted.reputation, basic.reputation);
+    }
+
+    #[test]
+    fn test_decay_reputation_min_age() {
+        let min_age = Duration::from_secs(60);
+        let mut old = peer();
+        let start = old.first_seen;
+        old.reputation = BANNED_REPUTATION - 10;
+        let mut young = old.clone();
+        young.first_seen = start + min_age;
+        young.last_decay = young.first_seen;
+
+        let now = start + min_age + Duration::from_secs(1);
+        let outcome = young.decay_reputation_with_min_age(now, 10, BANNED_REPUTATION, min_age);
+        assert_eq!(outcome, ReputationChangeOutcome::None);
+        assert_eq!(young.reputation, BANNED_REPUTATION - 10);
+
+        let outcome = old.decay_reputation_with_min_age(now, 10, BANNED_REPUTATION, min_age);
+        assert_eq!(outcome, ReputationChangeOutcome::Unban);
+        assert_eq!(old.reputation, BANNED_REPUTATION);
+
+        // once matured, only the time since maturing is credited
+        let outcome = young.decay_reputation_with_min_age(
+            start + 2 * min_age + Duration::from_millis(500),
+            10,
+            BANNED_REPUTATION,
+            min_age,
+        );
+        assert_eq!(outcome, ReputationChangeOutcome::None);
+        assert_eq!(young.reputation, BANNED_REPUTATION - 5);
+    }
+
+    #[test]
+    fn test_min_connection_duration() {
+        let config = PeersConfig::default().with_min_connection_duration(Duration::from_secs(60));
+        let mut peer = peer();
+
+        peer.reputation = MAX_REPUTATION;
+        let high = peer.min_connection_duration(&config);
+        peer.reputation = config.quarantine_threshold.saturating_neg();
+        let good = peer.min_connection_duration(&config);
+        peer.reputation = DEFAULT_REPUTATION;
+        let normal = peer.min_connection_duration(&config);
+        peer.reputation = config.quarantine_threshold - 1;
+        let low = peer.min_connection_duration(&config);
+
+        assert_eq!(normal, Duration::from_secs(60));
+        assert!(high > good && good > normal && normal > low);
+        assert_eq!(low, Duration::ZERO);
+
+        peer.kind = PeerKind::Static;
+        peer.reputation = DEFAULT_REPUTATION;
+        assert_eq!(peer.min_connection_duration(&config), Duration::from_secs(120));
+
+        peer.kind = PeerKind::Trusted;
+        peer.reputation = config.ban_threshold - 1;
+        assert_eq!(peer.min_connection_duration(&config), Duration::MAX);
+    }
+
+    #[test]
+    fn test_unban_margin_prevents_flapping() {
+        let mut peer = peer();
+        let threshold = -1000;
+        let margin = 100;
+        let apply = |peer: &mut Peer, change| {
+            peer.apply_reputation_with_unban_margin(
+                change,
+                ReputationChangeKind::Other(change),
+                threshold,
+                margin,
+            )
+        };
+
+        assert_eq!(apply(&mut peer, -1001), ReputationChangeOutcome::Ban);
+        assert!(peer.pending_unban);
+
+        // hovering around the threshold neither unbans nor bans again
+        for _ in 0..3 {
+            assert_eq!(apply(&mut peer, 10), ReputationChangeOutcome::None);
+            assert_eq!(apply(&mut peer, -10), ReputationChangeOutcome::None);
+        }
+        assert_eq!(apply(&mut peer, 99), ReputationChangeOutcome::None);
+        assert_eq!(peer.reputation, threshold + margin - 2);
+
+        // clearing the margin unbans
+        assert_eq!(apply(&mut peer, 2), ReputationChangeOutcome::Unban);
+        assert!(!peer.pending_unban);
+        assert_eq!(apply(&mut peer, -1), ReputationChangeOutcome::None);
+
+        // without a margin the peer is unbanned as soon as it reaches the threshold
+        let mut peer = Peer::new(peer.addr);
+        peer.apply_reputation_with_threshold(-1001, ReputationChangeKind::BadMessage, threshold);
+        assert_eq!(
+            peer.apply_reputation_with_threshold(1, ReputationChangeKind::GoodResponse, threshold),
+            ReputationChangeOutcome::Unban
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let id = PeerId::repeat_byte(0xab);
+        let mut peer = Peer::with_kind(
+            PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 30303)),
+            PeerKind::Trusted,
+        );
+        peer.reputation = 50;
+        peer.state = PeerConnectionState::Out;
+        assert_eq!(
+            peer.summary(&id),
+            format!(
+                "id=0x{} ip=1.2.3.4 kind=trusted rep=50 state=out backed_off=false",
+                "ab".repeat(64)
+            )
+        );
+    }
+
+    #[test]
+    fn test_on_stable_connection() {
+        let mut peer = peer();
+        peer.record_backoff(&BackoffKind::High);
+        peer.record_backoff(&BackoffKind::High);
+        peer.backed_off = true;
+        let threshold = Duration::from_secs(60);
+
+        assert!(!peer.on_stable_connection(Duration::from_secs(5), threshold));
+        assert!(!peer.on_stable_connection(threshold, threshold));
+        assert!(peer.backed_off);
+        assert_eq!(peer.severe_backoff_counter, 2);
+
+        assert!(peer.on_stable_connection(Duration::from_secs(61), threshold));
+        assert!(!peer.backed_off);
+        assert_eq!(peer.severe_backoff_counter, 0);
+        assert_eq!(peer.backoff_kind(), BackoffKind::Low);
     }
 }
@@ -0,0 +1,256 @@
+//! Policy for when to persist the peer set.
+
+use std::time::Duration;
+
+use crate::PeersConfig;
+#[cfg(feature = "serde")]
+use crate::PersistedPeerInfo;
+#[cfg(feature = "serde")]
+use reth_network_peers::NodeRecord;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+/// Decides when the peer set should be written to disk.
+///
+/// Persisting is triggered if either enough peers changed since the last write or enough time
+/// elapsed, see [`PeersConfig::persist_dirty_threshold`] and [`PeersConfig::persist_interval`].
+/// This only implements the policy, the caller tracks the changes and performs the IO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PersistScheduler {
+    /// Number of changed peers that triggers a write.
+    dirty_threshold: usize,
+    /// Time since the last write that triggers a write.
+    interval: Duration,
+}
+
+impl PersistScheduler {
+    /// Returns a new scheduler with the given thresholds.
+    pub const fn new(dirty_threshold: usize, interval: Duration) -> Self {
+        Self { dirty_threshold, interval }
+    }
+
+    /// Returns a new scheduler with the thresholds of the given config.
+    pub const fn from_config(config: &PeersConfig) -> Self {
+        Self::new(config.persist_dirty_threshold, config.persist_interval)
+    }
+
+    /// Returns `true` if the peers should be persisted, given the number of peers that changed and
+    /// the time elapsed since the last write.
+    ///
+    /// Nothing needs to be persisted if no peer changed.
+    pub fn should_persist(&self, dirty_count: usize, elapsed: Duration) -> bool {
+        if dirty_count == 0 {
+            return false
+        }
+        dirty_count >= self.dirty_threshold || elapsed >= self.interval
+    }
+}
+
+impl Default for PersistScheduler {
+    fn default() -> Self {
+        Self::from_config(&PeersConfig::default())
+    }
+}
+
+/// The entries of a persisted peers file, loaded without failing on individual corrupt entries.
+#[cfg(feature = "serde")]
+#[derive(Debug, Default)]
+pub struct LenientPersistedPeers {
+    /// The entries that could be decoded, in file order.
+    pub peers: Vec<PersistedPeerInfo>,
+    /// The index and decoding error of every entry that could not be decoded.
+    pub invalid: Vec<(usize, serde_json::Error)>,
+}
+
+#[cfg(feature = "serde")]
+impl LenientPersistedPeers {
+    /// Parses the contents of a persisted peers file.
+    ///
+    /// Every entry of the top level array is decoded on its own, either as [`PersistedPeerInfo`]
+    /// or in the legacy [`NodeRecord`] format. Entries that match neither are collected in
+    /// [`Self::invalid`] instead of failing the whole file.
+    ///
+    /// Returns an error if the contents are not a JSON array.
+    pub fn parse(raw: &str) -> Result<Self, serde_json::Error> {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(raw)?;
+        let mut loaded = Self::default();
+        for (index, entry) in entries.iter().enumerate() {
+            match Self::decode_entry(entry) {
+                Ok(peer) => loaded.peers.push(peer),
+                Err(err) => loaded.invalid.push((index, err)),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Decodes a single entry, either as [`PersistedPeerInfo`] or in the legacy [`NodeRecord`]
+    /// format.
+    fn decode_entry(entry: &serde_json::Value) -> Result<PersistedPeerInfo, serde_json::Error> {
+        PersistedPeerInfo::deserialize(entry).or_else(|err| {
+            NodeRecord::deserialize(entry).map(PersistedPeerInfo::from_node_record).map_err(|_| err)
+        })
+    }
+
+    /// Reads and parses the persisted peers file at the given path, see [`Self::parse`].
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let raw = std::fs::read_to_string(path)?;
+        Self::parse(&raw).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    /// Returns `true` if every entry could be decoded.
+    pub const fn is_valid(&self) -> bool {
+        self.invalid.is_empty()
+    }
+
+    /// Removes duplicate entries of the same peer, keeping the last, most recently written, entry
+    /// of every peer. The remaining entries keep their file order.
+    ///
+    /// Returns the number of removed entries.
+    pub fn dedup(&mut self) -> usize {
+        let len = self.peers.len();
+        let mut seen = std::collections::HashSet::with_capacity(len);
+        self.peers.reverse();
+        self.peers.retain(|peer| seen.insert(peer.peer_id()));
+        self.peers.reverse();
+        len - self.peers.len()
+    }
+}
+
+/// What [`compact_persisted`] removed from a persisted peers file.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// Number of entries written.
+    pub written: usize,
+    /// Number of removed `null` placeholders.
+    pub tombstones: usize,
+    /// Number of removed duplicate entries of the same peer.
+    pub duplicates: usize,
+    /// Number of entries that could not be decoded and were removed.
+    pub dropped_corrupt: usize,
+    /// Number of entries that could not be decoded and were kept as is.
+    pub kept_corrupt: usize,
+}
+
+/// Rewrites the persisted peers file at the given path, removing tombstones and duplicate entries
+/// of the same peer.
+///
+/// Tombstones are `null` placeholders. Duplicates are removed like in
+/// [`LenientPersistedPeers::dedup`], keeping the last entry of every peer. Entries that can't be
+/// decoded are kept unchanged unless `drop_corrupt` is set, so they can still be inspected or
+/// repaired. The file is replaced atomically by renaming a temporary file.
+#[cfg(feature = "serde")]
+pub fn compact_persisted(
+    path: impl AsRef<std::path::Path>,
+    drop_corrupt: bool,
+) -> std::io::Result<CompactionStats> {
+    let path = path.as_ref();
+    let raw = std::fs::read_to_string(path)?;
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&raw)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+    let mut stats = CompactionStats::default();
+    // decoded peers, or the raw value of corrupt entries that are kept
+    let mut kept = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if entry.is_null() {
+            stats.tombstones += 1;
+            continue
+        }
+        match LenientPersistedPeers::decode_entry(&entry) {
+            Ok(peer) => kept.push(Ok(peer)),
+            Err(_) if drop_corrupt => stats.dropped_corrupt += 1,
+            Err(_) => {
+                stats.kept_corrupt += 1;
+                kept.push(Err(entry));
+            }
+        }
+    }
+
+    // keep the last entry of every peer
+    let mut seen = std::collections::HashSet::with_capacity(kept.len());
+    let mut compacted = Vec::with_capacity(kept.len());
+    for entry in kept.into_iter().rev() {
+        match entry {
+            Ok(peer) if !seen.insert(peer.peer_id()) => stats.duplicates += 1,
+            Ok(peer) => compacted.push(
+                serde_json::to_value(&peer)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?,
+            ),
+            Err(value) => compacted.push(value),
+        }
+    }
+    compacted.reverse();
+    stats.written = compacted.len();
+
+    let json = serde_json::to_string_pretty(&compacted)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_trigger() {
+        let scheduler = PersistScheduler::new(10, Duration::from_secs(60));
+        assert!(!scheduler.should_persist(9, Duration::ZERO));
+        assert!(scheduler.should_persist(10, Duration::ZERO));
+        assert!(scheduler.should_persist(11, Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_time_trigger() {
+        let scheduler = PersistScheduler::new(10, Duration::from_secs(60));
+        assert!(!scheduler.should_persist(1, Duration::from_secs(59)));
+        assert!(scheduler.should_persist(1, Duration::from_secs(60)));
+        // nothing to persist
+        assert!(!scheduler.should_persist(0, Duration::from_secs(120)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_lenient() {
+        use crate::PeerKind;
+
+        let raw = r#"[
+            {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303?discport=30301","kind":"trusted","reputation":0},
+            {"record":"not an enode","kind":"basic","reputation":0},
+            "enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a1@10.3.58.6:30303",
+            42
+        ]"#;
+        let loaded = LenientPersistedPeers::parse(raw).unwrap();
+        assert!(!loaded.is_valid());
+        assert_eq!(loaded.peers.len(), 2);
+        assert_eq!(loaded.peers[0].kind, PeerKind::Trusted);
+        assert_eq!(loaded.peers[1].kind, PeerKind::Basic);
+        assert_eq!(loaded.invalid.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 3]);
+
+        assert!(LenientPersistedPeers::parse("{}").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_dedup() {
+        let raw = r#"[
+            {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.6:30303","kind":"basic","reputation":-100},
+            null,
+            {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a1@10.3.58.6:30303","kind":"static","reputation":0},
+            {"record":"enode://6f8a80d14311c39f35f516fa664deaaaa13e85b2f7493f37f6144d86991ec012937307647bd3b9a82abe2974e1407241d54947bbb39763a4cac9f77166ad92a0@10.3.58.7:30303","kind":"basic","reputation":-200}
+        ]"#;
+        let mut loaded = LenientPersistedPeers::parse(raw).unwrap();
+        assert_eq!(loaded.invalid.len(), 1);
+        assert_eq!(loaded.dedup(), 1);
+        assert_eq!(loaded.dedup(), 0);
+
+        // the last entry of the duplicated peer is kept, in file order
+        assert_eq!(loaded.peers.len(), 2);
+        assert_eq!(loaded.peers[0].reputation, 0);
+        assert_eq!(loaded.peers[1].reputation, -200);
+        assert_eq!(loaded.peers[1].record.address.to_string(), "10.3.58.7");
+    }
+}
@@ -1,14 +1,62 @@
 //! Peer reputation management
 
+use std::time::Duration;
+
 /// The default reputation of a peer
 pub const DEFAULT_REPUTATION: Reputation = 0;
 
 /// The minimal unit we're measuring reputation
 const REPUTATION_UNIT: i32 = -1024;
 
+/// The default reputation of a peer that was learned via discovery but never connected.
+///
+/// This is slightly below [`DEFAULT_REPUTATION`] so that discovered peers are dialed after peers
+/// that already proved themselves.
+pub const DEFAULT_DISCOVERED_REPUTATION: Reputation = REPUTATION_UNIT;
+
 /// The reputation value below which new connection from/to peers are rejected.
 pub const BANNED_REPUTATION: i32 = 50 * REPUTATION_UNIT;
 
+/// The maximum reputation a peer can accumulate.
+///
+/// This mirrors [`BANNED_REPUTATION`] so that a well-behaved peer can't build up an unbounded
+/// buffer against future penalties.
+pub const MAX_REPUTATION: i32 = -BANNED_REPUTATION;
+
+/// How far above the ban threshold a banned peer's reputation must climb before it is unbanned.
+///
+/// This is the equivalent of a dropped connection, so that a single penalty doesn't ban a peer
+/// that was just unbanned again.
+pub const UNBAN_REPUTATION_MARGIN: i32 = -REMOTE_DISCONNECT_REPUTATION_CHANGE;
+
+/// The reputation value below which peers are quarantined.
+///
+/// Quarantined peers stay connected but should not be used for requests until their reputation
+/// recovers above this value.
+pub const QUARANTINE_REPUTATION: i32 = 25 * REPUTATION_UNIT;
+
+/// The default reputation below which peers don't receive our transaction and block
+/// announcements.
+pub const DEFAULT_GOSSIP_REPUTATION: i32 = 10 * REPUTATION_UNIT;
+
+/// The default reputation a peer recovers per second through decay.
+///
+/// At this rate a peer at [`BANNED_REPUTATION`] recovers to [`DEFAULT_REPUTATION`] in roughly 14
+/// hours.
+pub const DEFAULT_REPUTATION_DECAY_PER_SECOND: Reputation = 1;
+
+/// The default minimum time a peer must be known before its reputation decays.
+///
+/// This prevents forgiving peers that were penalized right after they were discovered.
+pub const DEFAULT_REPUTATION_DECAY_MIN_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// The default window in which penalties of the same kind escalate, see
+/// [`ReputationChangeWeights::escalation_percent`].
+pub const DEFAULT_PENALTY_ESCALATION_WINDOW: Duration = Duration::from_secs(60);
+
+/// The default factor by which penalties of peers on probation after an unban are amplified.
+pub const DEFAULT_PROBATION_PENALTY_FACTOR: u32 = 2;
+
 /// The reputation change to apply to a peer that dropped the connection.
 const REMOTE_DISCONNECT_REPUTATION_CHANGE: i32 = 4 * REPUTATION_UNIT;
 
@@ -28,6 +76,18 @@ const BAD_MESSAGE_REPUTATION_CHANGE: i32 = 16 * REPUTATION_UNIT;
 /// apply any changes to the peer's reputation, effectively ignoring it.
 const ALREADY_SEEN_TRANSACTION_REPUTATION_CHANGE: i32 = 0;
 
+/// The reputation change to apply to a peer that tried to open another session while it is
+/// already connected.
+///
+/// This is 0 by default, which ignores duplicate connection attempts.
+const ALREADY_CONNECTED_REPUTATION_CHANGE: i32 = 0;
+
+/// The reputation change to apply to a peer that successfully responded to a request.
+///
+/// This is intentionally small, a quarter of a reputation unit, so that a single penalty outweighs
+/// many good responses.
+pub const GOOD_RESPONSE_REPUTATION_CHANGE: i32 = -REPUTATION_UNIT / 4;
+
 /// The reputation change to apply to a peer which violates protocol rules: minimal reputation
 const BAD_PROTOCOL_REPUTATION_CHANGE: i32 = i32::MIN;
 
@@ -58,8 +118,25 @@ pub const fn is_connection_failed_reputation(reputation: i32) -> bool {
 /// The type that tracks the reputation score.
 pub type Reputation = i32;
 
+/// Identifies the kind of requests a peer serves, used to track a reputation per protocol in
+/// addition to the aggregate reputation of the peer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ProtocolId {
+    /// Block header requests.
+    Headers,
+    /// Block body requests.
+    Bodies,
+    /// Receipt requests.
+    Receipts,
+    /// Pooled transaction requests.
+    PooledTransactions,
+    /// Custom protocol.
+    Other(&'static str),
+}
+
 /// Various kinds of reputation changes.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReputationChangeKind {
     /// Received an unspecific bad message from the peer
     BadMessage,
@@ -82,6 +159,8 @@ pub enum ReputationChangeKind {
     /// > originally). This is usually achieved by remembering a set of transaction hashes recently
     /// > relayed by the peer.
     AlreadySeenTransaction,
+    /// Peer tried to open another session while it is already connected.
+    AlreadyConnected,
     /// Peer failed to respond in time.
     Timeout,
     /// Peer does not adhere to network protocol rules.
@@ -90,6 +169,8 @@ pub enum ReputationChangeKind {
     FailedToConnect,
     /// Connection dropped by peer.
     Dropped,
+    /// Peer successfully responded to a request.
+    GoodResponse,
     /// Reset the reputation to the default value.
     Reset,
     /// Apply a reputation change by value
@@ -106,6 +187,11 @@ impl ReputationChangeKind {
     pub const fn is_dropped(&self) -> bool {
         matches!(self, Self::Dropped)
     }
+
+    /// Returns true if the reputation change is [`ReputationChangeKind::GoodResponse`].
+    pub const fn is_good_response(&self) -> bool {
+        matches!(self, Self::GoodResponse)
+    }
 }
 
 /// How the [`ReputationChangeKind`] are weighted.
@@ -121,6 +207,8 @@ pub struct ReputationChangeWeights {
     pub bad_transactions: Reputation,
     /// Weight for [`ReputationChangeKind::AlreadySeenTransaction`]
     pub already_seen_transactions: Reputation,
+    /// Weight for [`ReputationChangeKind::AlreadyConnected`]
+    pub already_connected: Reputation,
     /// Weight for [`ReputationChangeKind::Timeout`]
     pub timeout: Reputation,
     /// Weight for [`ReputationChangeKind::BadProtocol`]
@@ -131,6 +219,17 @@ pub struct ReputationChangeWeights {
     pub dropped: Reputation,
     /// Weight for [`ReputationChangeKind::BadAnnouncement`]
     pub bad_announcement: Reputation,
+    /// Weight for [`ReputationChangeKind::GoodResponse`]
+    pub good_response: Reputation,
+    /// Additional cost of each repeated penalty of the same kind within the
+    /// [`escalation_window`](Self::escalation_window), in percent of the weight.
+    ///
+    /// With 50 the first penalty costs the weight, the second 150% of it, the third 200% and so
+    /// on. Zero disables escalation.
+    pub escalation_percent: u32,
+    /// How long a penalty counts towards the escalation of subsequent penalties of the same kind.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub escalation_window: Duration,
 }
 
 // === impl ReputationChangeWeights ===
@@ -142,13 +241,29 @@ impl ReputationChangeWeights {
             bad_block: 0,
             bad_transactions: 0,
             already_seen_transactions: 0,
+            already_connected: 0,
             bad_message: 0,
             timeout: 0,
             bad_protocol: 0,
             failed_to_connect: 0,
             dropped: 0,
             bad_announcement: 0,
+            good_response: 0,
+            escalation_percent: 0,
+            escalation_window: DEFAULT_PENALTY_ESCALATION_WINDOW,
+        }
+    }
+
+    /// Returns the cost of a penalty that repeats a penalty of the same kind `repeats` times
+    /// within the escalation window, see [`Self::escalation_percent`].
+    ///
+    /// Non-negative changes are returned unchanged.
+    pub fn escalated_change(&self, change: Reputation, repeats: u32) -> Reputation {
+        if change >= 0 || self.escalation_percent == 0 || repeats == 0 {
+            return change
         }
+        let percent = 100 + i64::from(self.escalation_percent) * i64::from(repeats);
+        Reputation::try_from(i64::from(change) * percent / 100).unwrap_or(Reputation::MIN)
     }
 
     /// Returns the quantifiable [`ReputationChange`] for the given [`ReputationChangeKind`] using
@@ -159,6 +274,7 @@ impl ReputationChangeWeights {
             ReputationChangeKind::BadBlock => self.bad_block.into(),
             ReputationChangeKind::BadTransactions => self.bad_transactions.into(),
             ReputationChangeKind::AlreadySeenTransaction => self.already_seen_transactions.into(),
+            ReputationChangeKind::AlreadyConnected => self.already_connected.into(),
             ReputationChangeKind::Timeout => self.timeout.into(),
             ReputationChangeKind::BadProtocol => self.bad_protocol.into(),
             ReputationChangeKind::FailedToConnect => self.failed_to_connect.into(),
@@ -166,8 +282,33 @@ impl ReputationChangeWeights {
             ReputationChangeKind::Reset => DEFAULT_REPUTATION.into(),
             ReputationChangeKind::Other(val) => val.into(),
             ReputationChangeKind::BadAnnouncement => self.bad_announcement.into(),
+            ReputationChangeKind::GoodResponse => self.good_response.into(),
         }
     }
+
+    /// Returns every weighted [`ReputationChangeKind`] with its configured weight, for example to
+    /// verify that configured overrides took effect.
+    ///
+    /// [`ReputationChangeKind::Reset`] and [`ReputationChangeKind::Other`] are not weighted and
+    /// therefore not included.
+    pub fn describe(&self) -> Vec<(ReputationChangeKind, Reputation)> {
+        [
+            ReputationChangeKind::BadMessage,
+            ReputationChangeKind::BadBlock,
+            ReputationChangeKind::BadTransactions,
+            ReputationChangeKind::BadAnnouncement,
+            ReputationChangeKind::AlreadySeenTransaction,
+            ReputationChangeKind::AlreadyConnected,
+            ReputationChangeKind::Timeout,
+            ReputationChangeKind::BadProtocol,
+            ReputationChangeKind::FailedToConnect,
+            ReputationChangeKind::Dropped,
+            ReputationChangeKind::GoodResponse,
+        ]
+        .into_iter()
+        .map(|kind| (kind, self.change(kind).as_i32()))
+        .collect()
+    }
 }
 
 impl Default for ReputationChangeWeights {
@@ -176,12 +317,16 @@ impl Default for ReputationChangeWeights {
             bad_block: BAD_MESSAGE_REPUTATION_CHANGE,
             bad_transactions: BAD_MESSAGE_REPUTATION_CHANGE,
             already_seen_transactions: ALREADY_SEEN_TRANSACTION_REPUTATION_CHANGE,
+            already_connected: ALREADY_CONNECTED_REPUTATION_CHANGE,
             bad_message: BAD_MESSAGE_REPUTATION_CHANGE,
             timeout: TIMEOUT_REPUTATION_CHANGE,
             bad_protocol: BAD_PROTOCOL_REPUTATION_CHANGE,
             failed_to_connect: FAILED_TO_CONNECT_REPUTATION_CHANGE,
             dropped: REMOTE_DISCONNECT_REPUTATION_CHANGE,
             bad_announcement: BAD_ANNOUNCEMENT_REPUTATION_CHANGE,
+            good_response: GOOD_RESPONSE_REPUTATION_CHANGE,
+            escalation_percent: 0,
+            escalation_window: DEFAULT_PENALTY_ESCALATION_WINDOW,
         }
     }
 }
@@ -213,7 +358,7 @@ impl From<Reputation> for ReputationChange {
 }
 
 /// Outcomes when a reputation change is applied to a peer
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReputationChangeOutcome {
     /// Nothing to do.
     None,
@@ -224,3 +369,138 @@ pub enum ReputationChangeOutcome {
     /// Unban the peer
     Unban,
 }
+
+/// Classification of a peer's reputation relative to the configured thresholds, see
+/// [`Peer::reputation_tier`](crate::Peer::reputation_tier).
+///
+/// The positive bands mirror the negative ones, so with the default thresholds a peer is
+/// [`ReputationTier::Excellent`] once it reached [`MAX_REPUTATION`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReputationTier {
+    /// Below the ban threshold.
+    Banned,
+    /// At or above the ban threshold, but below the quarantine threshold.
+    Quarantined,
+    /// At or above the quarantine threshold, but below the negated quarantine threshold.
+    Normal,
+    /// At or above the negated quarantine threshold, but below the negated ban threshold.
+    Good,
+    /// At or above the negated ban threshold.
+    Excellent,
+}
+
+impl ReputationTier {
+    /// Returns the tier of the given reputation for the given ban and quarantine thresholds.
+    pub const fn new(
+        reputation: Reputation,
+        ban_threshold: Reputation,
+        quarantine_threshold: Reputation,
+    ) -> Self {
+        if reputation < ban_threshold {
+            Self::Banned
+        } else if reputation < quarantine_threshold {
+            Self::Quarantined
+        } else if reputation < quarantine_threshold.saturating_neg() {
+            Self::Normal
+        } else if reputation < ban_threshold.saturating_neg() {
+            Self::Good
+        } else {
+            Self::Excellent
+        }
+    }
+}
+
+/// Policy for combining a peer's persisted reputation with its live reputation, see
+/// [`merge_reputation`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MergePolicy {
+    /// Keep the live reputation and discard the persisted one.
+    #[default]
+    TakeLive,
+    /// Keep the persisted reputation and discard the live one.
+    TakePersisted,
+    /// Keep the lower of both reputations.
+    TakeMin,
+    /// Average both reputations, weighting the live reputation with the given percentage.
+    ///
+    /// Percentages above 100 are treated as 100.
+    WeightedAverage {
+        /// The weight of the live reputation in percent.
+        live_percent: u8,
+    },
+}
+
+/// Combines a peer's persisted reputation with its live reputation according to the given
+/// [`MergePolicy`].
+pub fn merge_reputation(
+    persisted: Reputation,
+    live: Reputation,
+    policy: MergePolicy,
+) -> Reputation {
+    match policy {
+        MergePolicy::TakeLive => live,
+        MergePolicy::TakePersisted => persisted,
+        MergePolicy::TakeMin => persisted.min(live),
+        MergePolicy::WeightedAverage { live_percent } => {
+            let live_weight = i64::from(live_percent.min(100));
+            let merged =
+                (i64::from(live) * live_weight + i64::from(persisted) * (100 - live_weight)) / 100;
+            // a weighted average of two `i32` values always fits into an `i32`
+            merged as Reputation
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_weights() {
+        let weights = ReputationChangeWeights { timeout: -1, ..Default::default() };
+        let described = weights.describe();
+        assert_eq!(described.len(), 11);
+        for (kind, weight) in described {
+            match kind {
+                ReputationChangeKind::Timeout => assert_eq!(weight, -1),
+                kind => {
+                    assert_eq!(weight, ReputationChangeWeights::default().change(kind).as_i32())
+                }
+            }
+        }
+        assert!(weights
+            .describe()
+            .contains(&(ReputationChangeKind::BadMessage, BAD_MESSAGE_REPUTATION_CHANGE)));
+    }
+
+    #[test]
+    fn test_merge_reputation() {
+        let persisted = -2048;
+        let live = 1024;
+
+        assert_eq!(merge_reputation(persisted, live, MergePolicy::TakeLive), 1024);
+        assert_eq!(merge_reputation(persisted, live, MergePolicy::TakePersisted), -2048);
+        assert_eq!(merge_reputation(persisted, live, MergePolicy::TakeMin), -2048);
+        assert_eq!(merge_reputation(live, persisted, MergePolicy::TakeMin), -2048);
+
+        let average = |live_percent| {
+            merge_reputation(persisted, live, MergePolicy::WeightedAverage { live_percent })
+        };
+        assert_eq!(average(50), -512);
+        assert_eq!(average(75), 256);
+        assert_eq!(average(0), persisted);
+        assert_eq!(average(100), live);
+        assert_eq!(average(u8::MAX), live);
+
+        // extreme values don't overflow
+        assert_eq!(
+            merge_reputation(i32::MIN, i32::MIN, MergePolicy::WeightedAverage { live_percent: 50 }),
+            i32::MIN
+        );
+        assert_eq!(
+            merge_reputation(i32::MAX, i32::MAX, MergePolicy::WeightedAverage { live_percent: 30 }),
+            i32::MAX
+        );
+    }
+}
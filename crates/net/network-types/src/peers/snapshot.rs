@@ -0,0 +1,139 @@
+//! Point in time snapshots of peer reputations.
+
+use std::collections::HashMap;
+
+use reth_network_peers::PeerId;
+
+use crate::{Peer, Reputation};
+
+/// The reputations of a set of peers captured at a point in time.
+///
+/// Comparing two snapshots with [`ReputationSnapshot::diff`] reveals sudden reputation collapses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReputationSnapshot {
+    reputations: HashMap<PeerId, Reputation>,
+}
+
+impl ReputationSnapshot {
+    /// Captures the current reputation of the given peers.
+    pub fn capture<'a>(peers: impl IntoIterator<Item = (&'a PeerId, &'a Peer)>) -> Self {
+        peers.into_iter().map(|(peer_id, peer)| (*peer_id, peer.reputation)).collect()
+    }
+
+    /// Returns the captured reputation of the given peer.
+    pub fn get(&self, peer_id: &PeerId) -> Option<Reputation> {
+        self.reputations.get(peer_id).copied()
+    }
+
+    /// Returns the number of peers in the snapshot.
+    pub fn len(&self) -> usize {
+        self.reputations.len()
+    }
+
+    /// Returns `true` if the snapshot contains no peers.
+    pub fn is_empty(&self) -> bool {
+        self.reputations.is_empty()
+    }
+
+    /// Returns the peers whose reputation dropped by more than `threshold` from `prev` to `curr`,
+    /// largest drop first.
+    ///
+    /// Peers that are only present in one of the snapshots are ignored.
+    pub fn diff(prev: &Self, curr: &Self, threshold: u32) -> Vec<ReputationDelta> {
+        let mut deltas = curr
+            .reputations
+            .iter()
+            .filter_map(|(peer_id, current)| {
+                let previous = prev.get(peer_id)?;
+                let delta = ReputationDelta { peer_id: *peer_id, previous, current: *current };
+                (delta.decrease() > threshold as i64).then_some(delta)
+            })
+            .collect::<Vec<_>>();
+        deltas.sort_unstable_by(|a, b| {
+            b.decrease().cmp(&a.decrease()).then(a.peer_id.cmp(&b.peer_id))
+        });
+        deltas
+    }
+}
+
+impl FromIterator<(PeerId, Reputation)> for ReputationSnapshot {
+    fn from_iter<T: IntoIterator<Item = (PeerId, Reputation)>>(iter: T) -> Self {
+        Self { reputations: iter.into_iter().collect() }
+    }
+}
+
+/// The reputation change of a single peer between two [`ReputationSnapshot`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReputationDelta {
+    /// The peer whose reputation changed.
+    pub peer_id: PeerId,
+    /// The reputation in the previous snapshot.
+    pub previous: Reputation,
+    /// The reputation in the current snapshot.
+    pub current: Reputation,
+}
+
+impl ReputationDelta {
+    /// Returns by how much the reputation dropped, negative if it increased.
+    pub const fn decrease(&self) -> i64 {
+        self.previous as i64 - self.current as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PeerAddr;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    #[test]
+    fn test_capture() {
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let mut peer = Peer::new(addr);
+        peer.reputation = -7;
+        let peer_id = PeerId::repeat_byte(1);
+
+        let snapshot = ReputationSnapshot::capture([(&peer_id, &peer)]);
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot.get(&peer_id), Some(-7));
+        assert_eq!(snapshot.get(&PeerId::repeat_byte(2)), None);
+    }
+
+    #[test]
+    fn test_diff_reports_significant_drops() {
+        let stable = PeerId::repeat_byte(1);
+        let small_drop = PeerId::repeat_byte(2);
+        let collapse = PeerId::repeat_byte(3);
+        let larger_collapse = PeerId::repeat_byte(4);
+        let recovered = PeerId::repeat_byte(5);
+        let removed = PeerId::repeat_byte(6);
+        let added = PeerId::repeat_byte(7);
+
+        let prev = ReputationSnapshot::from_iter([
+            (stable, 0),
+            (small_drop, 0),
+            (collapse, 0),
+            (larger_collapse, i32::MAX),
+            (recovered, -5000),
+            (removed, 0),
+        ]);
+        let curr = ReputationSnapshot::from_iter([
+            (stable, 0),
+            (small_drop, -1000),
+            (collapse, -1001),
+            (larger_collapse, i32::MIN),
+            (recovered, 0),
+            (added, -50_000),
+        ]);
+
+        let deltas = ReputationSnapshot::diff(&prev, &curr, 1000);
+        assert_eq!(
+            deltas,
+            vec![
+                ReputationDelta { peer_id: larger_collapse, previous: i32::MAX, current: i32::MIN },
+                ReputationDelta { peer_id: collapse, previous: 0, current: -1001 },
+            ]
+        );
+        assert_eq!(deltas[1].decrease(), 1001);
+    }
+}
@@ -60,4 +60,16 @@ impl PeerConnectionState {
     pub const fn is_pending_out(&self) -> bool {
         matches!(self, Self::PendingOut)
     }
+
+    /// Returns the string representation of the state.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::DisconnectingIn => "disconnecting_in",
+            Self::DisconnectingOut => "disconnecting_out",
+            Self::In => "in",
+            Self::Out => "out",
+            Self::PendingOut => "pending_out",
+        }
+    }
 }
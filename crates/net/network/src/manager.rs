@@ -831,6 +831,7 @@ impl<N: NetworkPrimitives> NetworkManager<N> {
                 if direction.is_outgoing() {
                     self.swarm.peers_mut().on_active_outgoing_established(peer_id);
                 }
+                self.swarm.peers_mut().on_protocol_version(&peer_id, version.into());
 
                 self.update_active_connection_metrics();
 
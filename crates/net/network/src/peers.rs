@@ -7,25 +7,30 @@ use crate::{
     trusted_peers_resolver::TrustedPeersResolver,
 };
 use futures::StreamExt;
+use rand::Rng;
 
 use reth_eth_wire::{errors::EthStreamError, DisconnectReason};
-use reth_ethereum_forks::ForkId;
+use reth_ethereum_forks::{ForkFilter, ForkId};
 use reth_net_banlist::BanList;
 use reth_network_api::test_utils::{PeerCommand, PeersHandle};
-use reth_network_peers::{NodeRecord, PeerId};
+use reth_network_peers::{NodeRecord, NodeRecordParseError, PeerId};
 use reth_network_types::{
     is_connection_failed_reputation,
     peers::{
+        addr::find_id_conflicts,
         config::PeerBackoffDurations,
+        merge_reputation,
         reputation::{DEFAULT_REPUTATION, MAX_TRUSTED_PEER_REPUTATION_CHANGE},
     },
-    ConnectionsConfig, Peer, PeerAddr, PeerConnectionState, PeerKind, PeersConfig,
-    PersistedPeerInfo, ReputationChangeKind, ReputationChangeOutcome, ReputationChangeWeights,
+    ConnectionsConfig, DialOutcome, IpSubnet, Peer, PeerAddr, PeerConnectionState, PeerKind,
+    PeersConfig, PersistedPeerInfo, ReputationChangeKind, ReputationChangeOutcome,
+    ReputationChangeWeights, ReputationTier,
 };
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    cmp::Ordering,
+    collections::{hash_map::Entry, BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque},
     fmt::Display,
-    io::{self},
+    io::{self, BufRead, BufReader, Read},
     net::{IpAddr, SocketAddr},
     task::{Context, Poll},
     time::Duration,
@@ -38,6 +43,100 @@ use tokio::{
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::{trace, warn};
 
+/// How long bans are remembered for [`PeersManager::ban_rate`].
+const BAN_HISTORY_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Prefix length of the IPv4 subnets that qualify for the trusted subnet bonus, see
+/// [`PeersConfig::trusted_subnet_bonus`].
+const TRUSTED_SUBNET_V4_BITS: u8 = 24;
+
+/// Prefix length of the IPv6 subnets that qualify for the trusted subnet bonus, see
+/// [`PeersConfig::trusted_subnet_bonus`].
+const TRUSTED_SUBNET_V6_BITS: u8 = 64;
+
+/// Callback invoked with the peer and its old and new [`ReputationTier`], see
+/// [`PeersManager::on_tier_change`].
+pub type TierChangeCallback =
+    Box<dyn FnMut(PeerId, ReputationTier, ReputationTier) + Send + Sync + 'static>;
+
+/// A source of reputation changes that can be replayed into the [`PeersManager`], see
+/// [`PeersManager::replay_reputation_events`].
+///
+/// This is mostly useful for tests that replay a scripted sequence of reputation changes.
+pub trait ReputationEventSource {
+    /// Returns the next event, or `None` if the source is exhausted.
+    ///
+    /// An event is the peer it applies to, the raw reputation change, which is applied instead of
+    /// the configured weight of the kind, and the kind of the change.
+    fn next_event(&mut self) -> Option<(PeerId, i32, ReputationChangeKind)>;
+}
+
+/// Buffers reputation changes within a tick so they can be applied at once via
+/// [`PeersManager::apply_reputation_batch`].
+///
+/// Changes are kept in order per peer, so flushing yields the same reputation as applying each
+/// change individually.
+#[derive(Debug, Default)]
+pub struct ReputationAccumulator {
+    pending: HashMap<PeerId, Vec<ReputationChangeKind>>,
+    len: usize,
+}
+
+impl ReputationAccumulator {
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Buffers a reputation change for the peer.
+    pub fn record(&mut self, peer_id: PeerId, kind: ReputationChangeKind) {
+        self.pending.entry(peer_id).or_default().push(kind);
+        self.len += 1;
+    }
+
+    /// Returns the number of buffered changes.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no changes are buffered.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Applies all buffered changes to the [`PeersManager`] and clears the buffer.
+    pub fn flush(&mut self, peers: &mut PeersManager) -> ReputationBatchOutcome {
+        self.len = 0;
+        let changes = self
+            .pending
+            .drain()
+            .flat_map(|(peer_id, kinds)| kinds.into_iter().map(move |kind| (peer_id, kind)));
+        peers.apply_reputation_batch(changes)
+    }
+}
+
+/// Wrapper around the optional [`TierChangeCallback`] so that [`PeersManager`] can derive
+/// [`Debug`](std::fmt::Debug).
+#[derive(Default)]
+struct TierChangeListener(Option<TierChangeCallback>);
+
+impl TierChangeListener {
+    /// Invokes the callback if one is registered and the tier changed.
+    fn notify(&mut self, peer_id: PeerId, old: ReputationTier, new: ReputationTier) {
+        if old != new &&
+            let Some(callback) = self.0.as_mut()
+        {
+            callback(peer_id, old, new);
+        }
+    }
+}
+
+impl std::fmt::Debug for TierChangeListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TierChangeListener").field(&self.0.is_some()).finish()
+    }
+}
+
 /// Maintains the state of _all_ the peers known to the network.
 ///
 /// This is supposed to be owned by the network itself, but can be reached via the [`PeersHandle`].
@@ -66,12 +165,51 @@ pub struct PeersManager {
     refill_slots_interval: Interval,
     /// How to weigh reputation changes
     reputation_weights: ReputationChangeWeights,
+    /// The reputation below which peers are banned.
+    ban_threshold: i32,
+    /// How far above the ban threshold banned peers must climb before they are unbanned.
+    unban_margin: i32,
+    /// The reputation below which peers are quarantined.
+    quarantine_threshold: i32,
+    /// The reputation below which peers are excluded from gossip.
+    gossip_reputation_threshold: i32,
+    /// The initial reputation of peers learned via discovery.
+    discovered_peer_reputation: i32,
+    /// Initial reputation bonus for new peers in the same subnet as a trusted peer.
+    trusted_subnet_bonus: Option<i32>,
+    /// The reputation peers recover per second, see [`Self::decay_all`].
+    reputation_decay_per_second: i32,
+    /// How long peers must be known before their reputation decays.
+    reputation_decay_min_age: Duration,
+    /// The reputation below which penalties can't push trusted peers.
+    trusted_reputation_floor: Option<i32>,
+    /// The reputation below which penalties can't push static peers.
+    static_reputation_floor: Option<i32>,
+    /// Number of recent dial attempts kept per peer.
+    dial_history_capacity: usize,
+    /// How long peers are on probation after they were unbanned.
+    probation_duration: Duration,
+    /// The factor by which penalties of peers on probation are amplified.
+    probation_penalty_factor: u32,
     /// Tracks current slot stats.
     connection_info: ConnectionInfo,
     /// Tracks unwanted ips/peer ids.
     ban_list: BanList,
     /// Tracks currently backed off peers.
     backed_off_peers: HashMap<PeerId, std::time::Instant>,
+    /// Peers removed via [`Self::forget_peer`] that must not be re-added until the given instant.
+    forgotten_peers: HashMap<PeerId, std::time::Instant>,
+    /// When peers were banned, oldest first, kept for [`BAN_HISTORY_RETENTION`].
+    recent_bans: VecDeque<std::time::Instant>,
+    /// How long a trusted peer may fail to connect before it is reported as unreachable.
+    trusted_unreachable_warn_after: Option<Duration>,
+    /// Trusted peers that failed to connect since their last session, and when the first failure
+    /// occurred.
+    trusted_failing_since: HashMap<PeerId, std::time::Instant>,
+    /// Whether sessions are marked for eviction, see [`Self::evict_pending_sessions`].
+    has_pending_evictions: bool,
+    /// When outbound peers were last evicted to free slots reserved for trusted peers.
+    last_reserved_slot_eviction: Option<std::time::Instant>,
     /// Interval at which to check for peers to unban and release from the backoff map.
     release_interval: Interval,
     /// How long to ban bad peers.
@@ -86,6 +224,15 @@ pub struct PeersManager {
     last_tick: Instant,
     /// Maximum number of backoff attempts before we give up on a peer and dropping.
     max_backoff_count: u8,
+    /// Quiet period after which the severe backoff counter of a peer is decremented.
+    backoff_decay_period: Duration,
+    /// Number of severe backoffs after which a peer is considered unreachable.
+    max_dial_attempts: u8,
+    /// Maximum number of never connected basic peers to remember.
+    max_discovered_peers: usize,
+    /// Number of peers that can be added before the number of evictable discovered peers may
+    /// exceed `max_discovered_peers`, see [`Self::evict_discovered_peers`].
+    discovered_until_eviction_check: usize,
     /// Tracks the connection state of the node
     net_connection_state: NetworkConnectionState,
     /// How long to temporarily ban ip on an incoming connection attempt.
@@ -95,6 +242,13 @@ pub struct PeersManager {
     /// If true, discovered peers without a confirmed ENR fork ID will not be added until their
     /// fork ID is verified via EIP-868.
     enforce_enr_fork_id: bool,
+    /// If true, static peers get the same protections as trusted peers.
+    protect_static: bool,
+    /// If true, reputation changes are ignored, see [`Self::set_reputation_frozen`].
+    reputation_frozen: bool,
+    /// Notified when a reputation change moves a peer into another tier, see
+    /// [`Self::on_tier_change`].
+    tier_change_listener: TierChangeListener,
 }
 
 impl PeersManager {
@@ -104,6 +258,20 @@ impl PeersManager {
             refill_slots_interval,
             connection_info,
             reputation_weights,
+            ban_threshold,
+            unban_margin,
+            quarantine_threshold,
+            gossip_reputation_threshold,
+            discovered_peer_reputation,
+            trusted_subnet_bonus,
+            reputation_decay_per_second,
+            reputation_decay_min_age,
+            trusted_reputation_floor,
+            static_reputation_floor,
+            reputation_merge_policy,
+            dial_history_capacity,
+            probation_duration,
+            probation_penalty_factor,
             ban_list,
             ban_duration,
             backoff_durations,
@@ -113,9 +281,20 @@ impl PeersManager {
             basic_nodes,
             persisted_peers,
             max_backoff_count,
+            backoff_decay_period,
+            max_dial_attempts,
+            max_discovered_peers,
             incoming_ip_throttle_duration,
             ip_filter,
             enforce_enr_fork_id,
+            protect_static,
+            persist_dirty_threshold: _,
+            persist_interval: _,
+            min_connection_duration: _,
+            keepalive_interval: _,
+            connect_timeout: _,
+            trusted_unreachable_warn_after,
+            long_term_peer_age: _,
         } = config;
         let (manager_tx, handle_rx) = mpsc::unbounded_channel();
         let now = Instant::now();
@@ -141,7 +320,16 @@ impl PeersManager {
             }
         }
 
-        for PersistedPeerInfo { record, kind, fork_id, reputation } in persisted_peers {
+        for PersistedPeerInfo {
+            record,
+            kind,
+            fork_id,
+            reputation,
+            last_ban_reason,
+            protocol_version,
+            quic_port,
+        } in persisted_peers
+        {
             // When enforce_enr_fork_id is enabled, skip persisted peers that don't have a
             // confirmed fork ID. These were likely accumulated from a different network during
             // a prior run without the flag.
@@ -149,15 +337,28 @@ impl PeersManager {
                 continue
             }
             let NodeRecord { address, tcp_port, udp_port, id } = record;
-            peers.entry(id).or_insert_with(|| {
-                let mut peer = Peer::with_kind(
-                    PeerAddr::new_with_ports(address, tcp_port, Some(udp_port)),
-                    kind,
-                );
-                peer.fork_id = fork_id.map(Box::new);
-                peer.reputation = reputation;
-                peer
-            });
+            match peers.entry(id) {
+                Entry::Occupied(mut entry) => {
+                    // the peer is already configured, e.g. as trusted peer
+                    let peer = entry.get_mut();
+                    peer.reputation =
+                        merge_reputation(reputation, peer.reputation, reputation_merge_policy);
+                    peer.record_peak_reputation();
+                }
+                Entry::Vacant(entry) => {
+                    let mut peer = Peer::with_kind(
+                        PeerAddr::new_with_ports(address, tcp_port, Some(udp_port))
+                            .with_quic_port(quic_port),
+                        kind,
+                    );
+                    peer.fork_id = fork_id.map(Box::new);
+                    peer.reputation = reputation;
+                    peer.record_peak_reputation();
+                    peer.last_ban_reason = last_ban_reason;
+                    peer.protocol_version = protocol_version;
+                    entry.insert(peer);
+                }
+            }
         }
 
         for NodeRecord { address, tcp_port, udp_port, id } in basic_nodes {
@@ -179,21 +380,425 @@ impl PeersManager {
             handle_rx: UnboundedReceiverStream::new(handle_rx),
             queued_actions: Default::default(),
             reputation_weights,
+            ban_threshold,
+            unban_margin,
+            quarantine_threshold,
+            gossip_reputation_threshold,
+            discovered_peer_reputation,
+            trusted_subnet_bonus,
+            reputation_decay_per_second,
+            reputation_decay_min_age,
+            trusted_reputation_floor,
+            static_reputation_floor,
+            dial_history_capacity,
+            probation_duration,
+            probation_penalty_factor,
             refill_slots_interval: tokio::time::interval(refill_slots_interval),
             release_interval: tokio::time::interval_at(now + unban_interval, unban_interval),
             connection_info: ConnectionInfo::new(connection_info),
             ban_list,
             backed_off_peers: Default::default(),
+            forgotten_peers: Default::default(),
+            recent_bans: Default::default(),
+            trusted_unreachable_warn_after,
+            trusted_failing_since: Default::default(),
+            has_pending_evictions: false,
+            last_reserved_slot_eviction: None,
             ban_duration,
             backoff_durations,
             trusted_nodes_only,
             last_tick: Instant::now(),
             max_backoff_count,
+            backoff_decay_period,
+            max_dial_attempts,
+            max_discovered_peers,
+            discovered_until_eviction_check: 0,
             net_connection_state: NetworkConnectionState::default(),
             incoming_ip_throttle_duration,
             ip_filter,
             enforce_enr_fork_id,
+            protect_static,
+            reputation_frozen: false,
+            tier_change_listener: TierChangeListener::default(),
+        }
+    }
+
+    /// Applies a new [`PeersConfig`] at runtime.
+    ///
+    /// This updates all thresholds, limits and durations and re-evaluates the ban status of every
+    /// tracked peer against the new ban threshold: peers that are now below the threshold are
+    /// banned (and disconnected if connected), peers that are now above it are unbanned.
+    ///
+    /// Connected peers exceeding lowered connection limits are not dropped mid-session. Instead
+    /// the lowest reputation sessions above the new limits are marked for eviction and
+    /// disconnected at the next refill of the outbound slots, if the limits are still exceeded by
+    /// then. Trusted, static and observer peers are never marked.
+    ///
+    /// Peers whose address is no longer allowed by the new ip filter are removed, connected ones
+    /// are marked for eviction and removed once their session is closed. Trusted peers are kept.
+    ///
+    /// Note: the configured ban list and the initial trusted, basic and persisted peers are only
+    /// used on startup and are ignored here.
+    pub fn set_config(&mut self, config: PeersConfig) {
+        self.reevaluate_ban_state(&config);
+
+        let PeersConfig {
+            refill_slots_interval,
+            connection_info,
+            reputation_weights,
+            // applied by `reevaluate_ban_state`
+            ban_threshold: _,
+            unban_margin: _,
+            quarantine_threshold,
+            gossip_reputation_threshold,
+            discovered_peer_reputation,
+            trusted_subnet_bonus,
+            reputation_decay_per_second,
+            reputation_decay_min_age,
+            trusted_reputation_floor,
+            static_reputation_floor,
+            // only applied on startup
+            reputation_merge_policy: _,
+            dial_history_capacity,
+            probation_duration,
+            probation_penalty_factor,
+            ban_list: _,
+            ban_duration,
+            backoff_durations,
+            trusted_nodes: _,
+            trusted_nodes_only,
+            trusted_nodes_resolution_interval: _,
+            basic_nodes: _,
+            persisted_peers: _,
+            max_backoff_count,
+            backoff_decay_period,
+            max_dial_attempts,
+            max_discovered_peers,
+            incoming_ip_throttle_duration,
+            ip_filter,
+            enforce_enr_fork_id,
+            protect_static,
+            persist_dirty_threshold: _,
+            persist_interval: _,
+            min_connection_duration: _,
+            keepalive_interval: _,
+            connect_timeout: _,
+            trusted_unreachable_warn_after,
+            long_term_peer_age: _,
+        } = config;
+
+        if refill_slots_interval != self.refill_slots_interval.period() {
+            self.refill_slots_interval = tokio::time::interval(refill_slots_interval);
+        }
+        self.connection_info.config = connection_info;
+        self.reputation_weights = reputation_weights;
+        self.quarantine_threshold = quarantine_threshold;
+        self.gossip_reputation_threshold = gossip_reputation_threshold;
+        self.discovered_peer_reputation = discovered_peer_reputation;
+        self.trusted_subnet_bonus = trusted_subnet_bonus;
+        self.trusted_unreachable_warn_after = trusted_unreachable_warn_after;
+        self.reputation_decay_per_second = reputation_decay_per_second;
+        self.reputation_decay_min_age = reputation_decay_min_age;
+        self.trusted_reputation_floor = trusted_reputation_floor;
+        self.static_reputation_floor = static_reputation_floor;
+        self.dial_history_capacity = dial_history_capacity;
+        self.probation_duration = probation_duration;
+        self.probation_penalty_factor = probation_penalty_factor;
+        self.ban_duration = ban_duration;
+        self.backoff_durations = backoff_durations;
+        self.trusted_nodes_only = trusted_nodes_only;
+        self.max_backoff_count = max_backoff_count;
+        self.backoff_decay_period = backoff_decay_period;
+        self.max_dial_attempts = max_dial_attempts;
+        self.max_discovered_peers = max_discovered_peers;
+        self.discovered_until_eviction_check = 0;
+        self.incoming_ip_throttle_duration = incoming_ip_throttle_duration;
+        self.ip_filter = ip_filter;
+        self.enforce_enr_fork_id = enforce_enr_fork_id;
+        self.protect_static = protect_static;
+
+        self.recheck_ip_filter();
+        self.mark_excess_sessions();
+    }
+
+    /// Removes the peers whose address is not allowed by the ip filter, connected peers are marked
+    /// for eviction instead, see [`Self::evict_pending_sessions`].
+    ///
+    /// Trusted peers are exempt.
+    fn recheck_ip_filter(&mut self) {
+        let mut removed = Vec::new();
+        for (peer_id, peer) in &mut self.peers {
+            if peer.is_trusted() || self.ip_filter.is_allowed(&peer.addr.tcp().ip()) {
+                continue
+            }
+            if matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out) {
+                trace!(target: "net::peers", ?peer_id, addr=?peer.addr, "marking peer not allowed by ip filter for eviction");
+                peer.pending_eviction = true;
+                peer.remove_after_disconnect = true;
+                self.has_pending_evictions = true;
+            } else if peer.state.is_unconnected() {
+                removed.push(*peer_id);
+            }
+        }
+        for peer_id in removed {
+            trace!(target: "net::peers", ?peer_id, "removing peer not allowed by ip filter");
+            self.peers.remove(&peer_id);
+            self.queued_actions.push_back(PeerAction::PeerRemoved(peer_id));
+        }
+    }
+
+    /// Returns the number of outbound and inbound sessions exceeding the connection limits,
+    /// excluding sessions that are already disconnecting or marked for eviction.
+    fn excess_sessions(&self) -> (usize, usize) {
+        let (mut outbound, mut inbound) = (0, 0);
+        for peer in self.peers.values() {
+            if peer.is_observer() {
+                continue
+            }
+            let closing = peer.pending_eviction ||
+                matches!(
+                    peer.state,
+                    PeerConnectionState::DisconnectingOut | PeerConnectionState::DisconnectingIn
+                );
+            match peer.state {
+                PeerConnectionState::Out | PeerConnectionState::DisconnectingOut if closing => {
+                    outbound += 1
+                }
+                PeerConnectionState::In | PeerConnectionState::DisconnectingIn if closing => {
+                    inbound += 1
+                }
+                _ => {}
+            }
+        }
+        let info = &self.connection_info;
+        (
+            info.num_outbound.saturating_sub(info.config.max_outbound).saturating_sub(outbound),
+            info.num_inbound.saturating_sub(info.config.max_inbound).saturating_sub(inbound),
+        )
+    }
+
+    /// Marks the lowest reputation sessions exceeding the connection limits for eviction, see
+    /// [`Self::evict_pending_sessions`].
+    ///
+    /// Trusted, static and observer peers are never marked.
+    fn mark_excess_sessions(&mut self) {
+        let (excess_outbound, excess_inbound) = self.excess_sessions();
+        for (state, excess) in
+            [(PeerConnectionState::Out, excess_outbound), (PeerConnectionState::In, excess_inbound)]
+        {
+            if excess == 0 {
+                continue
+            }
+            let mut candidates = self
+                .peers
+                .iter()
+                .filter(|(_, peer)| {
+                    peer.state == state &&
+                        !peer.pending_eviction &&
+                        !peer.is_trusted() &&
+                        !peer.is_static() &&
+                        !peer.is_observer()
+                })
+                .map(|(peer_id, peer)| (peer.reputation, *peer_id))
+                .collect::<Vec<_>>();
+            candidates.sort_unstable();
+
+            for (_, peer_id) in candidates.into_iter().take(excess) {
+                trace!(target: "net::peers", ?peer_id, "marking peer exceeding connection limits for eviction");
+                if let Some(peer) = self.peers.get_mut(&peer_id) {
+                    peer.pending_eviction = true;
+                    self.has_pending_evictions = true;
+                }
+            }
+        }
+    }
+
+    /// Disconnects the sessions marked for eviction by [`Self::set_config`].
+    ///
+    /// Sessions marked because their address is no longer allowed are always disconnected, the
+    /// others only as long as the connection limits are still exceeded, e.g. if sessions were
+    /// closed in the meantime they are kept.
+    fn evict_pending_sessions(&mut self) {
+        if !std::mem::take(&mut self.has_pending_evictions) {
+            return
+        }
+
+        let mut marked = Vec::new();
+        for (peer_id, peer) in &mut self.peers {
+            if std::mem::take(&mut peer.pending_eviction) &&
+                matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out)
+            {
+                let allowed = peer.is_trusted() || self.ip_filter.is_allowed(&peer.addr.tcp().ip());
+                marked.push((allowed, peer.reputation, *peer_id));
+            }
+        }
+        // disallowed peers first, since they're disconnected regardless of the limits, then the
+        // lowest reputation peers
+        marked.sort_unstable();
+
+        let (mut excess_outbound, mut excess_inbound) = self.excess_sessions();
+        for (allowed, _, peer_id) in marked {
+            let Some(peer) = self.peers.get_mut(&peer_id) else { continue };
+            let excess = if peer.state == PeerConnectionState::Out {
+                &mut excess_outbound
+            } else {
+                &mut excess_inbound
+            };
+            if allowed && *excess == 0 {
+                continue
+            }
+            *excess = excess.saturating_sub(1);
+
+            trace!(target: "net::peers", ?peer_id, "evicting peer marked for eviction");
+            peer.state.disconnect();
+            self.queued_actions.push_back(PeerAction::Disconnect {
+                peer_id,
+                reason: Some(DisconnectReason::TooManyPeers),
+            });
+        }
+    }
+
+    /// Applies the ban threshold and unban margin of the given config and re-evaluates the ban
+    /// status of every tracked peer against them.
+    ///
+    /// Peers that are now below the threshold are banned, and disconnected if connected. Banned
+    /// peers that are now above the threshold and the unban margin are unbanned. The transitions
+    /// are returned sorted by peer id, the corresponding [`PeerAction`]s are queued.
+    pub fn reevaluate_ban_state(
+        &mut self,
+        config: &PeersConfig,
+    ) -> Vec<(PeerId, ReputationChangeOutcome)> {
+        let previous_threshold = std::mem::replace(&mut self.ban_threshold, config.ban_threshold);
+        self.unban_margin = config.unban_margin;
+        let unban_threshold = self.ban_threshold.saturating_add(self.unban_margin);
+
+        let mut transitions = Vec::new();
+        for (peer_id, peer) in &mut self.peers {
+            if peer.is_observer() {
+                continue
+            }
+            let was_banned =
+                peer.is_banned_with_threshold(previous_threshold) || peer.pending_unban;
+            let is_banned = peer.is_banned_with_threshold(self.ban_threshold);
+            self.tier_change_listener.notify(
+                *peer_id,
+                ReputationTier::new(peer.reputation, previous_threshold, self.quarantine_threshold),
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold),
+            );
+            if is_banned && !was_banned {
+                peer.pending_unban = true;
+                if peer.state.is_connected() {
+                    peer.state.disconnect();
+                    self.queued_actions.push_back(PeerAction::Disconnect {
+                        peer_id: *peer_id,
+                        reason: Some(DisconnectReason::DisconnectRequested),
+                    });
+                    transitions.push((*peer_id, ReputationChangeOutcome::DisconnectAndBan));
+                } else {
+                    transitions.push((*peer_id, ReputationChangeOutcome::Ban));
+                }
+            } else if was_banned && !is_banned {
+                if peer.reputation < unban_threshold {
+                    // still within the unban margin
+                    peer.pending_unban = true;
+                } else {
+                    peer.pending_unban = false;
+                    transitions.push((*peer_id, ReputationChangeOutcome::Unban));
+                }
+            }
         }
+        transitions.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+
+        for (peer_id, outcome) in &transitions {
+            match outcome {
+                ReputationChangeOutcome::Unban => self.unban_peer(*peer_id),
+                _ => self.ban_peer(*peer_id),
+            }
+        }
+        transitions
+    }
+
+    /// Returns how applying the given [`ConnectionsConfig`] would affect the active sessions.
+    ///
+    /// This reports how many active sessions exceed the new caps and would have to be drained, and
+    /// how many slots the new caps open up compared to the current ones. Nothing is changed.
+    pub const fn slots_delta(&self, new: &ConnectionsConfig) -> SlotDelta {
+        let info = &self.connection_info;
+        SlotDelta {
+            excess_outbound: info.num_outbound.saturating_sub(new.max_outbound),
+            excess_inbound: info.num_inbound.saturating_sub(new.max_inbound),
+            opened_outbound: new.max_outbound.saturating_sub(info.config.max_outbound),
+            opened_inbound: new.max_inbound.saturating_sub(info.config.max_inbound),
+        }
+    }
+
+    /// Returns the peers to disconnect, lowest reputation first, to reduce the number of active
+    /// sessions to `target_count`.
+    ///
+    /// Trusted and static peers are protected and never included, so the plan may not reach the
+    /// target if there are not enough unprotected peers. This is useful to gracefully drain
+    /// sessions after lowering the connection limits with [`Self::set_config`], see also
+    /// [`Self::drain`].
+    pub fn plan_drain(&self, target_count: usize) -> Vec<PeerId> {
+        let active = self
+            .peers
+            .values()
+            .filter(|peer| {
+                !peer.is_observer() &&
+                    matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out)
+            })
+            .count();
+        let Some(excess) = active.checked_sub(target_count).filter(|excess| *excess > 0) else {
+            return Vec::new()
+        };
+
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| {
+                matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out) &&
+                    !peer.is_trusted() &&
+                    !peer.is_static() &&
+                    !peer.is_observer()
+            })
+            .map(|(peer_id, peer)| (peer.reputation, *peer_id))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+
+        candidates.into_iter().take(excess).map(|(_, peer_id)| peer_id).collect()
+    }
+
+    /// Returns the peers with an active session as an eviction queue, see [`EvictionCandidate`].
+    ///
+    /// Popping the heap yields the peer to evict first. Protected peers are included, so callers
+    /// should stop once [`EvictionCandidate::is_evictable`] returns `false`.
+    pub fn eviction_queue(&self) -> BinaryHeap<EvictionCandidate> {
+        self.peers
+            .iter()
+            .filter(|(_, peer)| {
+                matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out)
+            })
+            .map(|(peer_id, peer)| EvictionCandidate::new(*peer_id, peer))
+            .collect()
+    }
+
+    /// Disconnects the peers returned by [`Self::plan_drain`] and removes them from the peer set
+    /// once their sessions are closed.
+    ///
+    /// Returns the drained peers.
+    pub fn drain(&mut self, target_count: usize) -> Vec<PeerId> {
+        let plan = self.plan_drain(target_count);
+        for peer_id in &plan {
+            let Some(peer) = self.peers.get_mut(peer_id) else { continue };
+            trace!(target: "net::peers", ?peer_id, reputation=peer.reputation, "draining peer");
+            peer.remove_after_disconnect = true;
+            peer.state.disconnect();
+            self.queued_actions.push_back(PeerAction::Disconnect {
+                peer_id: *peer_id,
+                reason: Some(DisconnectReason::TooManyPeers),
+            });
+        }
+        plan
     }
 
     /// Returns a new [`PeersHandle`] that can send commands to this type.
@@ -212,121 +817,501 @@ impl PeersManager {
         self.peers.len()
     }
 
-    /// Returns an iterator over all peers as [`NodeRecord`]s.
-    pub(crate) fn iter_peers(&self) -> impl Iterator<Item = NodeRecord> + '_ {
-        self.peers.iter().map(|(peer_id, v)| {
-            NodeRecord::new_with_ports(
-                v.addr.tcp().ip(),
-                v.addr.tcp().port(),
-                v.addr.udp().map(|addr| addr.port()),
-                *peer_id,
-            )
-        })
-    }
-
-    /// Returns an iterator over peers suitable for persisting to disk.
+    /// Returns `true` if the given peer is tracked in the peer set.
     ///
-    /// Filters out backed-off and banned peers, and includes metadata like kind, fork ID, and
-    /// reputation.
-    pub(crate) fn persistable_peers(&self) -> impl Iterator<Item = PersistedPeerInfo> + '_ {
-        self.peers.iter().filter(|(_, peer)| !peer.is_backed_off() && !peer.is_banned()).map(
-            |(peer_id, peer)| PersistedPeerInfo {
-                record: NodeRecord::new_with_ports(
-                    peer.addr.tcp().ip(),
-                    peer.addr.tcp().port(),
-                    peer.addr.udp().map(|addr| addr.port()),
-                    *peer_id,
-                ),
-                kind: peer.kind,
-                fork_id: peer.fork_id.as_deref().copied(),
-                reputation: peer.reputation,
-            },
-        )
+    /// This is a constant time lookup in the peer map, so it's suitable for hot paths.
+    #[inline]
+    pub fn contains_peer(&self, peer_id: &PeerId) -> bool {
+        self.peers.contains_key(peer_id)
     }
 
-    /// Returns the `NodeRecord` and `PeerKind` for the given peer id
-    pub(crate) fn peer_by_id(&self, peer_id: PeerId) -> Option<(NodeRecord, PeerKind)> {
-        self.peers.get(&peer_id).map(|v| {
-            (
-                NodeRecord::new_with_ports(
-                    v.addr.tcp().ip(),
-                    v.addr.tcp().port(),
-                    v.addr.udp().map(|addr| addr.port()),
-                    peer_id,
-                ),
-                v.kind,
-            )
+    /// Returns `true` if the given peer is tracked and its reputation is in the quarantine band.
+    ///
+    /// Quarantined peers remain connected but should be skipped when routing requests.
+    pub fn is_quarantined(&self, peer_id: &PeerId) -> bool {
+        self.peers.get(peer_id).is_some_and(|peer| {
+            peer.is_quarantined_with_thresholds(self.quarantine_threshold, self.ban_threshold)
         })
     }
 
-    /// Returns `true` if the given peer is connected via an inbound session.
-    pub(crate) fn is_inbound_peer(&self, peer_id: &PeerId) -> bool {
-        self.peers.get(peer_id).is_some_and(|p| {
-            matches!(p.state, PeerConnectionState::In | PeerConnectionState::DisconnectingIn)
-        })
+    /// Returns `true` if requests may be routed to the given peer.
+    ///
+    /// This is the case for tracked peers that are neither observers, see [`PeerKind::Observer`],
+    /// nor quarantined, see [`Self::is_quarantined`].
+    pub fn is_routable(&self, peer_id: &PeerId) -> bool {
+        self.peers.get(peer_id).is_some_and(|peer| !peer.is_observer()) &&
+            !self.is_quarantined(peer_id)
     }
 
-    /// Returns an iterator over all peer ids for peers with the given kind
-    pub(crate) fn peers_by_kind(&self, kind: PeerKind) -> impl Iterator<Item = PeerId> + '_ {
-        self.peers.iter().filter_map(move |(peer_id, peer)| (peer.kind == kind).then_some(*peer_id))
+    /// Returns the connected peers that should receive our transaction and block announcements,
+    /// sorted by peer id.
+    ///
+    /// These are the peers at or above [`PeersConfig::gossip_reputation_threshold`], trusted peers
+    /// are always included.
+    pub fn gossip_eligible_peers(&self) -> Vec<PeerId> {
+        let mut peers = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| {
+                matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out) &&
+                    (peer.is_trusted() || peer.reputation >= self.gossip_reputation_threshold)
+            })
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+        peers.sort_unstable();
+        peers
     }
 
-    /// Returns the number of currently active inbound connections.
-    #[inline]
-    pub(crate) const fn num_inbound_connections(&self) -> usize {
-        self.connection_info.num_inbound
+    /// Returns the trusted peers whose reputation fell below the quarantine threshold, worst
+    /// first.
+    ///
+    /// Trusted peers receive softened penalties and are never evicted for slots, so a flaky
+    /// configured peer may linger in the quarantine band for a long time, although it can still
+    /// be banned. This is how such a peer can be surfaced to operators. This doesn't affect the
+    /// connection handling of these peers.
+    pub fn trusted_peer_warnings(&self) -> Vec<(PeerId, i32)> {
+        let mut warnings = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.is_trusted() && peer.reputation < self.quarantine_threshold)
+            .map(|(peer_id, peer)| (*peer_id, peer.reputation))
+            .collect::<Vec<_>>();
+        warnings.sort_unstable_by_key(|(peer_id, reputation)| (*reputation, *peer_id));
+        warnings
     }
 
-    /// Returns the number of currently __active__ outbound connections.
-    #[inline]
-    pub(crate) const fn num_outbound_connections(&self) -> usize {
-        self.connection_info.num_outbound
+    /// Calls `f` for every tracked peer, in no particular order.
+    ///
+    /// Unlike collecting the peers, this doesn't allocate.
+    pub fn for_each_peer(&self, mut f: impl FnMut(&PeerId, &Peer)) {
+        for (peer_id, peer) in &self.peers {
+            f(peer_id, peer);
+        }
     }
 
-    /// Returns the number of currently pending outbound connections.
-    #[inline]
-    pub(crate) const fn num_pending_outbound_connections(&self) -> usize {
-        self.connection_info.num_pending_out
+    /// Folds over all tracked peers, in no particular order, stopping at the first error.
+    pub fn try_fold_peers<B, E>(
+        &self,
+        init: B,
+        mut f: impl FnMut(B, &PeerId, &Peer) -> Result<B, E>,
+    ) -> Result<B, E> {
+        self.peers.iter().try_fold(init, |acc, (peer_id, peer)| f(acc, peer_id, peer))
     }
 
-    /// Returns the number of currently backed off peers.
-    #[inline]
-    pub(crate) fn num_backed_off_peers(&self) -> usize {
-        self.backed_off_peers.len()
+    /// Returns the number of known peers per age bucket, where the age of a peer is the time since
+    /// it was first seen.
+    ///
+    /// The `buckets` are the ascending upper bounds of the buckets: a peer is counted in the first
+    /// bucket whose bound exceeds its age. The returned vector has one more entry than `buckets`,
+    /// the last one counting the peers at least as old as the last bound.
+    pub fn age_histogram(&self, now: std::time::Instant, buckets: &[Duration]) -> Vec<usize> {
+        let mut histogram = vec![0; buckets.len() + 1];
+        for peer in self.peers.values() {
+            let age = now.saturating_duration_since(peer.first_seen);
+            histogram[buckets.partition_point(|bound| *bound <= age)] += 1;
+        }
+        histogram
     }
 
-    /// Returns the number of idle trusted peers.
-    fn num_idle_trusted_peers(&self) -> usize {
-        self.peers.iter().filter(|(_, peer)| peer.kind.is_trusted() && peer.state.is_idle()).count()
+    /// Returns an estimate of the heap memory in bytes allocated by the history buffers of all
+    /// peers, see [`Peer::history_memory_estimate`].
+    pub fn history_memory_estimate(&self) -> usize {
+        self.peers.values().map(Peer::history_memory_estimate).sum()
     }
 
-    /// Invoked when a new _incoming_ tcp connection is accepted.
+    /// Trims the history buffers of the peers so that [`Self::history_memory_estimate`] stays
+    /// within the given number of bytes.
     ///
-    /// returns an error if the inbound ip address is on the ban list
-    pub(crate) fn on_incoming_pending_session(
-        &mut self,
-        addr: IpAddr,
-    ) -> Result<(), InboundConnectionError> {
-        // Check if the IP is in the allowed ranges (netrestrict)
-        if !self.ip_filter.is_allowed(&addr) {
-            trace!(target: "net", ?addr, "Rejecting connection from IP not in allowed ranges");
-            return Err(InboundConnectionError::IpBanned)
+    /// Unused capacity is released first. If that is not enough, the history of peers is dropped
+    /// in order of their reputation, so that banned and low reputation peers lose their history
+    /// first. Trusted peers are trimmed last.
+    pub fn trim_history_to_budget(&mut self, bytes: usize) {
+        if self.history_memory_estimate() <= bytes {
+            return
         }
 
-        if self.ban_list.is_banned_ip(&addr) {
-            return Err(InboundConnectionError::IpBanned)
-        }
+        self.peers.values_mut().for_each(Peer::shrink_history);
+        let mut estimate = self.history_memory_estimate();
 
-        // check if we even have slots for a new incoming connection
-        if !self.connection_info.has_in_capacity() {
-            if self.trusted_peer_ids.is_empty() {
-                // if we don't have any incoming slots and no trusted peers, we don't accept any new
-                // connections
-                return Err(InboundConnectionError::ExceedsCapacity)
-            }
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| peer.history_memory_estimate() > 0)
+            .map(|(peer_id, peer)| (peer.is_trusted(), peer.reputation, *peer_id))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
 
-            // there's an edge case here where no incoming connections besides from trusted peers
-            // are allowed (max_inbound == 0), in which case we still need to allow new pending
+        for (_, _, peer_id) in candidates {
+            if estimate <= bytes {
+                break
+            }
+            let Some(peer) = self.peers.get_mut(&peer_id) else { continue };
+            estimate -= peer.history_memory_estimate();
+            peer.clear_history();
+        }
+    }
+
+    /// Returns the peers with a known `eth` protocol version, grouped by version.
+    ///
+    /// The peer ids of each version are sorted.
+    pub fn peers_by_protocol_version(&self) -> BTreeMap<u8, Vec<PeerId>> {
+        let mut by_version = BTreeMap::<u8, Vec<PeerId>>::new();
+        for (peer_id, peer) in &self.peers {
+            if let Some(version) = peer.protocol_version {
+                by_version.entry(version).or_default().push(*peer_id);
+            }
+        }
+        for peers in by_version.values_mut() {
+            peers.sort_unstable();
+        }
+        by_version
+    }
+
+    /// Returns the trusted peers that have been failing to connect for longer than
+    /// [`PeersConfig::trusted_unreachable_warn_after`] at the given instant, and logs a warning for
+    /// each of them.
+    ///
+    /// The peers are still retried, this is meant to surface misconfigured trusted peers. The
+    /// result is sorted and always empty if the warning is disabled.
+    pub fn sweep_unreachable_trusted(&self, now: std::time::Instant) -> Vec<PeerId> {
+        let Some(warn_after) = self.trusted_unreachable_warn_after else { return Vec::new() };
+        let mut unreachable = self
+            .trusted_failing_since
+            .iter()
+            .filter(|(peer_id, _)| self.trusted_peer_ids.contains(*peer_id))
+            .filter_map(|(peer_id, since)| {
+                let failing_for = now.saturating_duration_since(*since);
+                (failing_for > warn_after).then_some((*peer_id, failing_for))
+            })
+            .collect::<Vec<_>>();
+        unreachable.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+
+        unreachable
+            .into_iter()
+            .map(|(peer_id, failing_for)| {
+                warn!(target: "net::peers", ?peer_id, ?failing_for, "trusted peer is unreachable");
+                peer_id
+            })
+            .collect()
+    }
+
+    /// Returns the number of known peers per subnet, grouping IPv4 addresses by their first
+    /// `v4_bits` and IPv6 addresses by their first `v6_bits`, see [`PeerAddr::subnet_group`].
+    ///
+    /// This can be used to detect if a single subnet dominates the peer set.
+    pub fn subnet_distribution(&self, v4_bits: u8, v6_bits: u8) -> HashMap<IpSubnet, usize> {
+        let mut distribution = HashMap::new();
+        for peer in self.peers.values() {
+            *distribution.entry(peer.addr.subnet_group(v4_bits, v6_bits)).or_default() += 1;
+        }
+        distribution
+    }
+
+    /// Returns the peer ids that are mapped to more than one address, see
+    /// [`find_id_conflicts`](reth_network_types::peers::addr::find_id_conflicts).
+    ///
+    /// The peer set holds a single entry per id and address changes replace the previous address,
+    /// so this is expected to be empty. Useful for diagnostics and as an invariant check.
+    pub fn find_id_conflicts(&self) -> Vec<(PeerId, Vec<PeerAddr>)> {
+        find_id_conflicts(self.peers.iter().map(|(peer_id, peer)| (*peer_id, peer.addr)))
+    }
+
+    /// Re-classifies the announced [`ForkId`] of every known peer against the given [`ForkFilter`],
+    /// for example after the local fork id changed at a network upgrade.
+    ///
+    /// Basic peers whose fork id is now incompatible are marked for removal once their session is
+    /// closed. Peers that didn't announce a fork id are counted as unknown and left as is.
+    pub fn apply_fork_transition(&mut self, new_filter: &ForkFilter) -> ForkTransitionReport {
+        let mut report = ForkTransitionReport::default();
+        for (peer_id, peer) in &mut self.peers {
+            let Some(fork_id) = peer.fork_id.as_deref().copied() else {
+                report.unknown += 1;
+                continue
+            };
+            if new_filter.validate(fork_id).is_ok() {
+                report.compatible += 1;
+                continue
+            }
+            report.incompatible += 1;
+            if peer.kind == PeerKind::Basic {
+                trace!(target: "net::peers", ?peer_id, ?fork_id, "marking peer with stale fork id for removal");
+                peer.remove_after_disconnect = true;
+            }
+        }
+        report
+    }
+
+    /// Returns the peers with an active session grouped by the [`ForkId`] they announced.
+    ///
+    /// Peers that didn't announce a fork id are grouped under `None`. The peers of each group are
+    /// sorted by id. Near a fork boundary this shows whether the node is about to be partitioned.
+    pub fn peers_by_fork(&self) -> HashMap<Option<ForkId>, Vec<PeerId>> {
+        let mut buckets = HashMap::<_, Vec<_>>::new();
+        for (peer_id, peer) in &self.peers {
+            if matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out) {
+                buckets.entry(peer.fork_id.as_deref().copied()).or_default().push(*peer_id);
+            }
+        }
+        for peers in buckets.values_mut() {
+            peers.sort_unstable();
+        }
+        buckets
+    }
+
+    /// Returns a [`PeersMetricsSnapshot`] of the peer set, computed in a single pass.
+    pub fn snapshot_metrics(&self) -> PeersMetricsSnapshot {
+        let mut snapshot = PeersMetricsSnapshot { total: self.peers.len(), ..Default::default() };
+        for (peer_id, peer) in &self.peers {
+            if matches!(peer.state, PeerConnectionState::In | PeerConnectionState::Out) {
+                snapshot.connected += 1;
+            }
+            if peer.is_banned_with_threshold(self.ban_threshold) ||
+                self.ban_list.is_banned_peer(peer_id)
+            {
+                snapshot.banned += 1;
+            }
+            if peer.is_backed_off() {
+                snapshot.backed_off += 1;
+            }
+            match peer.kind {
+                PeerKind::Trusted => snapshot.trusted += 1,
+                PeerKind::Static => snapshot.static_peers += 1,
+                PeerKind::Basic if peer.state.is_unconnected() => snapshot.discovered_only += 1,
+                PeerKind::Basic | PeerKind::Observer => {}
+            }
+        }
+        snapshot
+    }
+
+    /// Decays the reputation of all non-trusted peers towards the default for the time elapsed
+    /// since their last decay, see [`Peer::decay_reputation_with_min_age`].
+    ///
+    /// Peers that are known for less than the configured minimum age keep their reputation.
+    ///
+    /// Returns the peers that are no longer banned as a result, these are also removed from the
+    /// ban list.
+    pub fn decay_all(&mut self, now: std::time::Instant) -> Vec<PeerId> {
+        let mut unbanned = Vec::new();
+        for (peer_id, peer) in &mut self.peers {
+            if peer.is_trusted() {
+                continue
+            }
+            let old_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            let outcome = peer.decay_reputation_with_min_age(
+                now,
+                self.reputation_decay_per_second,
+                self.ban_threshold,
+                self.reputation_decay_min_age,
+            );
+            let new_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            self.tier_change_listener.notify(*peer_id, old_tier, new_tier);
+            if outcome == ReputationChangeOutcome::Unban {
+                unbanned.push(*peer_id);
+            }
+        }
+
+        for peer_id in &unbanned {
+            self.unban_peer(*peer_id);
+        }
+        unbanned
+    }
+
+    /// Decrements the severe backoff counter of all peers, including trusted peers, that weren't
+    /// severely backed off within the configured quiet period, see
+    /// [`Peer::decay_backoff_counter`].
+    ///
+    /// Returns the number of peers whose counter was decremented.
+    pub fn decay_backoff_counters(&mut self, now: std::time::Instant) -> usize {
+        let mut decayed = 0;
+        for peer in self.peers.values_mut() {
+            if peer.decay_backoff_counter(now, self.backoff_decay_period) {
+                decayed += 1;
+            }
+        }
+        decayed
+    }
+
+    /// Samples up to `k` distinct peers with a probability proportional to their reputation.
+    ///
+    /// Banned and backed off peers are never sampled. The weight of a peer is its reputation
+    /// shifted above the ban threshold, so peers with a poor reputation are picked less often but
+    /// are not excluded entirely.
+    ///
+    /// The draws are fully determined by `rng`, which makes this reproducible with a seeded rng.
+    pub fn sample_weighted(&self, k: usize, rng: &mut impl Rng) -> Vec<PeerId> {
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter(|(peer_id, peer)| {
+                !peer.is_backed_off() &&
+                    !peer.is_banned_with_threshold(self.ban_threshold) &&
+                    !self.ban_list.is_banned_peer(peer_id)
+            })
+            .map(|(peer_id, peer)| {
+                let weight = peer.reputation as i64 - self.ban_threshold as i64 + 1;
+                (*peer_id, weight)
+            })
+            .collect::<Vec<_>>();
+
+        // the map's iteration order is random, sort so the same rng yields the same sample
+        candidates.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+
+        // weighted sampling without replacement: each peer gets the key `-ln(u) / weight` and the
+        // `k` smallest keys win (Efraimidis-Spirakis)
+        let mut keyed = candidates
+            .into_iter()
+            .map(|(peer_id, weight)| {
+                let u = 1.0 - rng.random::<f64>();
+                (-u.ln() / weight as f64, peer_id)
+            })
+            .collect::<Vec<_>>();
+        keyed.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        keyed.into_iter().take(k).map(|(_, peer_id)| peer_id).collect()
+    }
+
+    /// Returns an iterator over all peers with a dialable address as [`NodeRecord`]s.
+    ///
+    /// Placeholders without an address, e.g. of peers imported via [`Self::import_banned`], are
+    /// skipped.
+    pub(crate) fn iter_peers(&self) -> impl Iterator<Item = NodeRecord> + '_ {
+        self.peers.iter().filter(|(_, peer)| peer.addr.tcp_addr().is_some()).map(|(peer_id, v)| {
+            NodeRecord::new_with_ports(
+                v.addr.tcp().ip(),
+                v.addr.tcp().port(),
+                v.addr.udp().map(|addr| addr.port()),
+                *peer_id,
+            )
+        })
+    }
+
+    /// Returns an iterator over peers suitable for persisting to disk.
+    ///
+    /// Filters out backed-off and banned peers as well as peers without a dialable address, and
+    /// includes metadata like kind, fork ID, and reputation.
+    pub(crate) fn persistable_peers(&self) -> impl Iterator<Item = PersistedPeerInfo> + '_ {
+        self.peers
+            .iter()
+            .filter(|(_, peer)| {
+                !peer.is_backed_off() &&
+                    !peer.is_banned_with_threshold(self.ban_threshold) &&
+                    peer.addr.tcp_addr().is_some()
+            })
+            .map(|(peer_id, peer)| Self::persisted_peer_info(peer_id, peer))
+    }
+
+    /// Returns a point-in-time snapshot of all peers as [`PersistedPeerInfo`], e.g. for live
+    /// backups.
+    ///
+    /// Unlike the peers that are persisted on shutdown, this includes backed off and banned peers.
+    /// The result can be written with the persisted peers file helpers.
+    pub fn snapshot_persisted(&self) -> Vec<PersistedPeerInfo> {
+        self.peers.iter().map(|(peer_id, peer)| Self::persisted_peer_info(peer_id, peer)).collect()
+    }
+
+    /// Converts the peer to its [`PersistedPeerInfo`].
+    fn persisted_peer_info(peer_id: &PeerId, peer: &Peer) -> PersistedPeerInfo {
+        PersistedPeerInfo {
+            record: NodeRecord::new_with_ports(
+                peer.addr.tcp().ip(),
+                peer.addr.tcp().port(),
+                peer.addr.udp().map(|addr| addr.port()),
+                *peer_id,
+            ),
+            kind: peer.kind,
+            fork_id: peer.fork_id.as_deref().copied(),
+            reputation: peer.reputation,
+            last_ban_reason: peer.last_ban_reason,
+            protocol_version: peer.protocol_version,
+            quic_port: peer.addr.quic_port(),
+        }
+    }
+
+    /// Returns the `NodeRecord` and `PeerKind` for the given peer id
+    pub(crate) fn peer_by_id(&self, peer_id: PeerId) -> Option<(NodeRecord, PeerKind)> {
+        self.peers.get(&peer_id).map(|v| {
+            (
+                NodeRecord::new_with_ports(
+                    v.addr.tcp().ip(),
+                    v.addr.tcp().port(),
+                    v.addr.udp().map(|addr| addr.port()),
+                    peer_id,
+                ),
+                v.kind,
+            )
+        })
+    }
+
+    /// Returns `true` if the given peer is connected via an inbound session.
+    pub(crate) fn is_inbound_peer(&self, peer_id: &PeerId) -> bool {
+        self.peers.get(peer_id).is_some_and(|p| {
+            matches!(p.state, PeerConnectionState::In | PeerConnectionState::DisconnectingIn)
+        })
+    }
+
+    /// Returns an iterator over all peer ids for peers with the given kind
+    pub(crate) fn peers_by_kind(&self, kind: PeerKind) -> impl Iterator<Item = PeerId> + '_ {
+        self.peers.iter().filter_map(move |(peer_id, peer)| (peer.kind == kind).then_some(*peer_id))
+    }
+
+    /// Returns the number of currently active inbound connections.
+    #[inline]
+    pub(crate) const fn num_inbound_connections(&self) -> usize {
+        self.connection_info.num_inbound
+    }
+
+    /// Returns the number of currently __active__ outbound connections.
+    #[inline]
+    pub(crate) const fn num_outbound_connections(&self) -> usize {
+        self.connection_info.num_outbound
+    }
+
+    /// Returns the number of currently pending outbound connections.
+    #[inline]
+    pub(crate) const fn num_pending_outbound_connections(&self) -> usize {
+        self.connection_info.num_pending_out
+    }
+
+    /// Returns the number of currently backed off peers.
+    #[inline]
+    pub(crate) fn num_backed_off_peers(&self) -> usize {
+        self.backed_off_peers.len()
+    }
+
+    /// Returns the number of idle trusted peers.
+    fn num_idle_trusted_peers(&self) -> usize {
+        self.peers.iter().filter(|(_, peer)| peer.kind.is_trusted() && peer.state.is_idle()).count()
+    }
+
+    /// Invoked when a new _incoming_ tcp connection is accepted.
+    ///
+    /// returns an error if the inbound ip address is on the ban list
+    pub(crate) fn on_incoming_pending_session(
+        &mut self,
+        addr: IpAddr,
+    ) -> Result<(), InboundConnectionError> {
+        // Check if the IP is in the allowed ranges (netrestrict)
+        if !self.ip_filter.is_allowed(&addr) {
+            trace!(target: "net", ?addr, "Rejecting connection from IP not in allowed ranges");
+            return Err(InboundConnectionError::IpBanned)
+        }
+
+        if self.ban_list.is_banned_ip(&addr) {
+            return Err(InboundConnectionError::IpBanned)
+        }
+
+        // check if we even have slots for a new incoming connection
+        if !self.connection_info.has_in_capacity() {
+            if self.trusted_peer_ids.is_empty() {
+                // if we don't have any incoming slots and no trusted peers, we don't accept any new
+                // connections
+                return Err(InboundConnectionError::ExceedsCapacity)
+            }
+
+            // there's an edge case here where no incoming connections besides from trusted peers
+            // are allowed (max_inbound == 0), in which case we still need to allow new pending
             // incoming connections until all trusted peers are connected.
             let num_idle_trusted_peers = self.num_idle_trusted_peers();
             if num_idle_trusted_peers <= self.trusted_peer_ids.len() {
@@ -409,21 +1394,40 @@ impl PeersManager {
 
         // start a new tick, so the peer is not immediately rewarded for the time since last tick
         self.tick();
+        self.trusted_failing_since.remove(&peer_id);
 
+        let bonus = self.trusted_subnet_bonus_for(&PeerAddr::from_tcp(addr));
         match self.peers.entry(peer_id) {
             Entry::Occupied(mut entry) => {
                 let peer = entry.get_mut();
-                if peer.is_banned() {
+                if !peer.is_observer() && peer.is_banned_with_threshold(self.ban_threshold) {
                     self.queued_actions.push_back(PeerAction::DisconnectBannedIncoming { peer_id });
                     return
                 }
+                if !is_trusted &&
+                    !peer.is_trusted() &&
+                    self.connection_info.is_below_min_inbound_reputation(peer.reputation)
+                {
+                    trace!(target: "net::peers", ?peer_id, reputation=peer.reputation, "refusing inbound session of low reputation peer");
+                    self.queued_actions
+                        .push_back(PeerAction::DisconnectLowReputationIncoming { peer_id });
+                    return
+                }
                 // it might be the case that we're also trying to connect to this peer at the same
                 // time, so we need to adjust the state here
                 if peer.state.is_pending_out() {
-                    self.connection_info.decr_state(peer.state);
+                    self.connection_info.decr_peer(peer);
                 }
 
                 peer.state = PeerConnectionState::In;
+                peer.unreachable = false;
+                peer.ever_connected = true;
+                peer.pending_eviction = false;
+
+                if peer.is_observer() {
+                    // observers don't occupy an inbound slot
+                    return
+                }
 
                 is_trusted = is_trusted || peer.is_trusted();
             }
@@ -432,6 +1436,8 @@ impl PeersManager {
                 // disconnect, because we only know the outgoing port
                 let mut peer = Peer::with_state(PeerAddr::from_tcp(addr), PeerConnectionState::In);
                 peer.remove_after_disconnect = true;
+                peer.ever_connected = true;
+                Self::apply_initial_bonus(&mut peer, bonus);
                 entry.insert(peer);
                 self.queued_actions.push_back(PeerAction::PeerAdded(peer_id));
             }
@@ -462,8 +1468,40 @@ impl PeersManager {
             self.ban_duration
         };
 
-        self.ban_list.ban_peer_until(peer_id, std::time::Instant::now() + ban_duration);
+        let now = std::time::Instant::now();
+        self.ban_list.ban_peer_until(peer_id, now + ban_duration);
         self.queued_actions.push_back(PeerAction::BanPeer { peer_id });
+        self.record_ban(now);
+    }
+
+    /// Records a ban for [`Self::ban_rate`] and forgets bans older than [`BAN_HISTORY_RETENTION`].
+    fn record_ban(&mut self, now: std::time::Instant) {
+        while let Some(banned_at) = self.recent_bans.front() &&
+            now.saturating_duration_since(*banned_at) > BAN_HISTORY_RETENTION
+        {
+            self.recent_bans.pop_front();
+        }
+        self.recent_bans.push_back(now);
+    }
+
+    /// Returns the number of peers banned per second within the `window` before `now`.
+    ///
+    /// A sudden spike of bans likely indicates a problem with the local node rather than with the
+    /// peers, so consumers can alert on a rate above a threshold and, for example, freeze the
+    /// reputation with [`Self::set_reputation_frozen`]. Bans are remembered for an hour, so longer
+    /// windows underestimate the rate. Returns zero for an empty window.
+    pub fn ban_rate(&self, window: Duration, now: std::time::Instant) -> f64 {
+        if window.is_zero() {
+            return 0.0
+        }
+        let bans = self
+            .recent_bans
+            .iter()
+            .rev()
+            .skip_while(|banned_at| **banned_at > now)
+            .take_while(|banned_at| now.saturating_duration_since(**banned_at) <= window)
+            .count();
+        bans as f64 / window.as_secs_f64()
     }
 
     /// Bans the IP temporarily with the configured ban timeout
@@ -487,8 +1525,11 @@ impl PeersManager {
         }
     }
 
-    /// Unbans the peer
+    /// Unbans the peer and puts it on probation.
     fn unban_peer(&mut self, peer_id: PeerId) {
+        if let Some(peer) = self.peers.get_mut(&peer_id) {
+            peer.start_probation(std::time::Instant::now(), self.probation_duration);
+        }
         self.ban_list.unban_peer(&peer_id);
         self.queued_actions.push_back(PeerAction::UnBanPeer { peer_id });
     }
@@ -511,40 +1552,163 @@ impl PeersManager {
             // update reputation via seconds connected, but keep the target _around_ the default
             // reputation.
             if peer.1.reputation < DEFAULT_REPUTATION {
+                let old_tier = ReputationTier::new(
+                    peer.1.reputation,
+                    self.ban_threshold,
+                    self.quarantine_threshold,
+                );
                 peer.1.reputation += secs_since_last_tick;
+                peer.1.record_peak_reputation();
+                let new_tier = ReputationTier::new(
+                    peer.1.reputation,
+                    self.ban_threshold,
+                    self.quarantine_threshold,
+                );
+                self.tier_change_listener.notify(*peer.0, old_tier, new_tier);
             }
         }
     }
 
+    /// Freezes or unfreezes the reputation of all peers.
+    ///
+    /// While frozen, reputation changes are ignored, so that transient failures during a known
+    /// noisy event don't ban honest peers. Existing bans are not affected.
+    pub const fn set_reputation_frozen(&mut self, frozen: bool) {
+        self.reputation_frozen = frozen;
+    }
+
+    /// Registers a callback that is invoked whenever a reputation change moves a peer into another
+    /// [`ReputationTier`], with the old and the new tier.
+    ///
+    /// This covers every reputation update, including failed connections, decay, imported bans,
+    /// external scores and a changed ban threshold. Changes that keep the peer within its tier
+    /// don't invoke the callback. This replaces any previously registered callback.
+    pub fn on_tier_change<F>(&mut self, callback: F)
+    where
+        F: FnMut(PeerId, ReputationTier, ReputationTier) + Send + Sync + 'static,
+    {
+        self.tier_change_listener = TierChangeListener(Some(Box::new(callback)));
+    }
+
+    /// Returns true if reputation changes are currently ignored, see
+    /// [`Self::set_reputation_frozen`].
+    pub const fn is_reputation_frozen(&self) -> bool {
+        self.reputation_frozen
+    }
+
     /// Returns the tracked reputation for a peer.
     pub(crate) fn get_reputation(&self, peer_id: &PeerId) -> Option<i32> {
         self.peers.get(peer_id).map(|peer| peer.reputation)
     }
 
+    /// Applies all reputation changes of the source in order, as if they were reported via
+    /// [`Self::apply_reputation_change`], and returns the number of applied events.
+    ///
+    /// The scripted change of an event replaces the configured weight of its kind, everything
+    /// else, e.g. the handling of trusted peers, penalty escalation and probation, is based on the
+    /// kind.
+    pub fn replay_reputation_events(&mut self, source: &mut impl ReputationEventSource) -> usize {
+        let mut applied = 0;
+        while let Some((peer_id, change, kind)) = source.next_event() {
+            self.apply_reputation_change_with_base(&peer_id, kind, change);
+            applied += 1;
+        }
+        applied
+    }
+
     /// Apply the corresponding reputation change to the given peer.
     ///
     /// If the peer is a trusted peer, it will be exempt from reputation slashing for certain
     /// reputation changes that can be attributed to network conditions. If the peer is a
     /// trusted peer, it will also be less strict with the reputation slashing.
     pub(crate) fn apply_reputation_change(&mut self, peer_id: &PeerId, rep: ReputationChangeKind) {
+        self.apply_reputation_change_with_outcome(peer_id, rep);
+    }
+
+    /// Applies all reputation changes in order, as if they were reported via
+    /// [`Self::apply_reputation_change`], and returns the aggregated outcomes.
+    pub fn apply_reputation_batch(
+        &mut self,
+        changes: impl IntoIterator<Item = (PeerId, ReputationChangeKind)>,
+    ) -> ReputationBatchOutcome {
+        let mut outcome = ReputationBatchOutcome::default();
+        for (peer_id, kind) in changes {
+            match self.apply_reputation_change_with_outcome(&peer_id, kind) {
+                None => outcome.ignored += 1,
+                Some(change) => {
+                    outcome.applied += 1;
+                    match change {
+                        ReputationChangeOutcome::None => {}
+                        ReputationChangeOutcome::Ban |
+                        ReputationChangeOutcome::DisconnectAndBan => outcome.banned.push(peer_id),
+                        ReputationChangeOutcome::Unban => outcome.unbanned.push(peer_id),
+                    }
+                }
+            }
+        }
+        outcome.banned.sort_unstable();
+        outcome.banned.dedup();
+        outcome.unbanned.sort_unstable();
+        outcome.unbanned.dedup();
+        outcome
+    }
+
+    /// Applies the reputation change and returns its outcome, or `None` if the change was ignored
+    /// because the reputation is frozen, the peer is unknown or exempt from the change.
+    fn apply_reputation_change_with_outcome(
+        &mut self,
+        peer_id: &PeerId,
+        rep: ReputationChangeKind,
+    ) -> Option<ReputationChangeOutcome> {
+        let base_change = self.reputation_weights.change(rep).as_i32();
+        self.apply_reputation_change_with_base(peer_id, rep, base_change)
+    }
+
+    /// Applies a reputation change of the given kind, starting from `base_change` instead of the
+    /// configured weight of the kind.
+    fn apply_reputation_change_with_base(
+        &mut self,
+        peer_id: &PeerId,
+        rep: ReputationChangeKind,
+        base_change: i32,
+    ) -> Option<ReputationChangeOutcome> {
+        if self.reputation_frozen {
+            trace!(target: "net::peers", ?peer_id, reputation=?rep, "reputation frozen, ignoring change");
+            return None
+        }
         trace!(target: "net::peers", ?peer_id, reputation=?rep, "applying reputation change");
 
-        let outcome = if let Some(peer) = self.peers.get_mut(peer_id) {
+        let (outcome, old_tier, new_tier) = if let Some(peer) = self.peers.get_mut(peer_id) {
+            let old_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
             // First check if we should reset the reputation
-            if rep.is_reset() {
+            let outcome = if rep.is_reset() {
                 peer.reset_reputation()
             } else {
-                let mut reputation_change = self.reputation_weights.change(rep).as_i32();
+                let now = std::time::Instant::now();
+                let mut reputation_change = base_change;
+                if reputation_change < 0 && self.reputation_weights.escalation_percent > 0 {
+                    let repeats =
+                        peer.record_penalty(rep, now, self.reputation_weights.escalation_window);
+                    reputation_change =
+                        self.reputation_weights.escalated_change(reputation_change, repeats);
+                }
+                reputation_change = peer.probation_adjusted_change(
+                    reputation_change,
+                    now,
+                    self.probation_penalty_factor,
+                );
                 if peer.is_trusted() || peer.is_static() {
                     // exempt trusted and static peers from reputation slashing for
                     if matches!(
                         rep,
                         ReputationChangeKind::Dropped |
+                            ReputationChangeKind::AlreadyConnected |
                             ReputationChangeKind::BadAnnouncement |
                             ReputationChangeKind::Timeout |
                             ReputationChangeKind::AlreadySeenTransaction
                     ) {
-                        return
+                        return None
                     }
 
                     // also be less strict with the reputation slashing for trusted peers
@@ -553,53 +1717,263 @@ impl PeersManager {
                         reputation_change = MAX_TRUSTED_PEER_REPUTATION_CHANGE;
                     }
                 }
-                peer.apply_reputation(reputation_change, rep)
-            }
+                // penalties can't push the peer below the floor of its kind
+                let floor = peer.kind.reputation_floor_with(
+                    self.trusted_reputation_floor,
+                    self.static_reputation_floor,
+                );
+                if reputation_change < 0 && floor > i32::MIN {
+                    reputation_change =
+                        reputation_change.max(floor.saturating_sub(peer.reputation).min(0));
+                }
+                peer.apply_reputation_with_unban_margin(
+                    reputation_change,
+                    rep,
+                    self.ban_threshold,
+                    self.unban_margin,
+                )
+            };
+            let new_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            (outcome, old_tier, new_tier)
         } else {
-            return
+            return None
         };
 
+        self.tier_change_listener.notify(*peer_id, old_tier, new_tier);
+        self.on_reputation_outcome(*peer_id, outcome);
+        Some(outcome)
+    }
+
+    /// Bans, unbans or disconnects the peer according to the outcome of a reputation change.
+    fn on_reputation_outcome(&mut self, peer_id: PeerId, outcome: ReputationChangeOutcome) {
         match outcome {
             ReputationChangeOutcome::None => {}
             ReputationChangeOutcome::Ban => {
-                self.ban_peer(*peer_id);
+                self.ban_peer(peer_id);
             }
-            ReputationChangeOutcome::Unban => self.unban_peer(*peer_id),
+            ReputationChangeOutcome::Unban => self.unban_peer(peer_id),
             ReputationChangeOutcome::DisconnectAndBan => {
                 self.queued_actions.push_back(PeerAction::Disconnect {
-                    peer_id: *peer_id,
+                    peer_id,
                     reason: Some(DisconnectReason::DisconnectRequested),
                 });
-                self.ban_peer(*peer_id);
+                self.ban_peer(peer_id);
             }
         }
     }
 
-    /// Gracefully disconnected a pending _outgoing_ session
-    pub(crate) fn on_outgoing_pending_session_gracefully_closed(&mut self, peer_id: &PeerId) {
-        if let Some(peer) = self.peers.get_mut(peer_id) {
-            self.connection_info.decr_state(peer.state);
-            peer.state = PeerConnectionState::Idle;
+    /// Resets the reputation of all peers whose most recent penalty was of the given kind, for
+    /// example after a protocol bug caused unfair penalties.
+    ///
+    /// Affected peers are set back to the default reputation and unbanned without probation.
+    /// Returns the number of peers that were reset.
+    pub fn reset_reputation_for_kind(&mut self, kind: ReputationChangeKind) -> usize {
+        let mut reset = 0;
+        let mut unbanned = Vec::new();
+        for (peer_id, peer) in &mut self.peers {
+            if peer.last_penalty != Some(kind) {
+                continue
+            }
+            if peer.is_banned_with_threshold(self.ban_threshold) || peer.pending_unban {
+                unbanned.push(*peer_id);
+            }
+            peer.reset_reputation();
+            peer.last_penalty = None;
+            peer.pending_unban = false;
+            peer.probation_until = None;
+            reset += 1;
         }
+
+        for peer_id in unbanned {
+            self.ban_list.unban_peer(&peer_id);
+            self.queued_actions.push_back(PeerAction::UnBanPeer { peer_id });
+        }
+        trace!(target: "net::peers", ?kind, reset, "reset reputation for penalty kind");
+        reset
     }
 
-    /// Invoked when an _outgoing_ pending session was closed during authentication or the
-    /// handshake.
+    /// Returns the currently banned peers with their reputation, sorted by peer id.
+    ///
+    /// This is meant for sharing a blocklist between nodes, see [`Self::import_banned`].
+    pub fn export_banned(&self) -> Vec<(PeerId, i32)> {
+        let mut banned = self
+            .peers
+            .iter()
+            .filter(|(peer_id, peer)| {
+                !peer.is_observer() &&
+                    (peer.is_banned_with_threshold(self.ban_threshold) ||
+                        self.ban_list.is_banned_peer(peer_id))
+            })
+            .map(|(peer_id, peer)| (*peer_id, peer.reputation))
+            .collect::<Vec<_>>();
+        banned.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+        banned
+    }
+
+    /// Bans the peers of a blocklist exported with [`Self::export_banned`].
+    ///
+    /// Each peer is set to the given reputation, capped just below the ban threshold, and banned.
+    /// Unknown peers are added as placeholders without an address that are never dialed until
+    /// they're discovered. Trusted peers are skipped. Returns the number of imported peers.
+    pub fn import_banned(&mut self, list: impl IntoIterator<Item = (PeerId, i32)>) -> usize {
+        let unspecified =
+            PeerAddr::from_tcp(SocketAddr::from((std::net::Ipv4Addr::UNSPECIFIED, 0)));
+        let reputation_cap = self.ban_threshold.saturating_sub(1);
+        let mut imported = 0;
+        for (peer_id, reputation) in list {
+            if self.trusted_peer_ids.contains(&peer_id) {
+                continue
+            }
+            let peer = self.peers.entry(peer_id).or_insert_with(|| {
+                self.queued_actions.push_back(PeerAction::PeerAdded(peer_id));
+                Peer { unreachable: true, ..Peer::new(unspecified) }
+            });
+            if peer.is_trusted() {
+                continue
+            }
+            let old_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            let reputation = reputation.min(reputation_cap);
+            let outcome = peer.set_reputation_with_unban_margin(
+                reputation,
+                ReputationChangeKind::Other(reputation),
+                self.ban_threshold,
+                self.unban_margin,
+            );
+            let new_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            self.tier_change_listener.notify(peer_id, old_tier, new_tier);
+            trace!(target: "net::peers", ?peer_id, reputation, ?outcome, "imported banned peer");
+            self.on_reputation_outcome(peer_id, outcome);
+            imported += 1;
+        }
+        imported
+    }
+
+    /// Applies the reputation change to all peers whose address is in the given subnet at once, for
+    /// example when many peers of one subnet misbehave in a coordinated way.
+    ///
+    /// The change is applied as is, without weights, escalation or probation. Trusted peers are
+    /// exempt and nothing is changed while the reputation is frozen. Returns the peers that were
+    /// banned as a result, sorted by peer id.
+    pub fn apply_subnet_reputation(
+        &mut self,
+        subnet: IpSubnet,
+        change: i32,
+        kind: ReputationChangeKind,
+    ) -> Vec<PeerId> {
+        if self.reputation_frozen {
+            trace!(target: "net::peers", %subnet, "reputation frozen, ignoring subnet change");
+            return Vec::new()
+        }
+
+        let prefix_len = subnet.prefix_len();
+        let mut outcomes = Vec::new();
+        for (peer_id, peer) in &mut self.peers {
+            if peer.is_trusted() || peer.addr.subnet_group(prefix_len, prefix_len) != subnet {
+                continue
+            }
+            let old_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            let outcome = peer.apply_reputation_with_unban_margin(
+                change,
+                kind,
+                self.ban_threshold,
+                self.unban_margin,
+            );
+            let new_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            self.tier_change_listener.notify(*peer_id, old_tier, new_tier);
+            outcomes.push((*peer_id, outcome));
+        }
+        trace!(target: "net::peers", %subnet, change, peers=outcomes.len(), "applied subnet reputation change");
+
+        let mut banned = Vec::new();
+        for (peer_id, outcome) in outcomes {
+            if matches!(
+                outcome,
+                ReputationChangeOutcome::Ban | ReputationChangeOutcome::DisconnectAndBan
+            ) {
+                banned.push(peer_id);
+            }
+            self.on_reputation_outcome(peer_id, outcome);
+        }
+        banned.sort_unstable();
+        banned
+    }
+
+    /// Sets the reputation of known peers to the scores provided by an external scoring service,
+    /// for example to seed reputations at startup.
+    ///
+    /// Scores are capped at the maximum reputation and peers crossing the ban threshold are banned
+    /// or unbanned accordingly. Unknown peer ids are ignored. Returns the resulting ban
+    /// transitions, sorted by peer id.
+    pub fn apply_external_scores(
+        &mut self,
+        scores: impl IntoIterator<Item = (PeerId, i32)>,
+    ) -> Vec<(PeerId, ReputationChangeOutcome)> {
+        let mut transitions = Vec::new();
+        for (peer_id, score) in scores {
+            let Some(peer) = self.peers.get_mut(&peer_id) else { continue };
+            let old_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            let outcome = peer.set_reputation_with_unban_margin(
+                score,
+                ReputationChangeKind::Other(score),
+                self.ban_threshold,
+                self.unban_margin,
+            );
+            let new_tier =
+                ReputationTier::new(peer.reputation, self.ban_threshold, self.quarantine_threshold);
+            self.tier_change_listener.notify(peer_id, old_tier, new_tier);
+            trace!(target: "net::peers", ?peer_id, score, ?outcome, "applied external score");
+            if outcome != ReputationChangeOutcome::None {
+                self.on_reputation_outcome(peer_id, outcome);
+                transitions.push((peer_id, outcome));
+            }
+        }
+        transitions.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+        transitions
+    }
+
+    /// Gracefully disconnected a pending _outgoing_ session
+    pub(crate) fn on_outgoing_pending_session_gracefully_closed(&mut self, peer_id: &PeerId) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            self.connection_info.decr_peer(peer);
+            peer.state = PeerConnectionState::Idle;
+        }
+    }
+
+    /// Invoked when an _outgoing_ pending session was closed during authentication or the
+    /// handshake.
     pub(crate) fn on_outgoing_pending_session_dropped(
         &mut self,
         remote_addr: &SocketAddr,
         peer_id: &PeerId,
         err: &PendingSessionHandshakeError,
     ) {
+        self.record_dial(peer_id, DialOutcome::HandshakeFailed);
         self.on_connection_failure(remote_addr, peer_id, err, ReputationChangeKind::FailedToConnect)
     }
 
+    /// Records the outcome of a dial attempt in the peer's dial history.
+    fn record_dial(&mut self, peer_id: &PeerId, outcome: DialOutcome) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.record_dial_with_capacity(
+                std::time::Instant::now(),
+                outcome,
+                self.dial_history_capacity,
+            );
+        }
+    }
+
     /// Gracefully disconnected an active session
     pub(crate) fn on_active_session_gracefully_closed(&mut self, peer_id: PeerId) {
         match self.peers.entry(peer_id) {
             Entry::Occupied(mut entry) => {
                 trace!(target: "net::peers", ?peer_id, direction=?entry.get().state, "active session gracefully closed");
-                self.connection_info.decr_state(entry.get().state);
+                self.connection_info.decr_peer(entry.get());
 
                 if entry.get().remove_after_disconnect && !entry.get().is_trusted() {
                     // this peer should be removed from the set
@@ -631,13 +2005,29 @@ impl PeersManager {
         self.fill_outbound_slots();
     }
 
+    /// Records the `eth` protocol version negotiated in the handshake with the peer.
+    pub(crate) fn on_protocol_version(&mut self, peer_id: &PeerId, version: u8) {
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.set_protocol_version(version);
+        }
+    }
+
     /// Called when a _pending_ outbound connection is successful.
     pub(crate) fn on_active_outgoing_established(&mut self, peer_id: PeerId) {
+        self.trusted_failing_since.remove(&peer_id);
         if let Some(peer) = self.peers.get_mut(&peer_id) {
             trace!(target: "net::peers", ?peer_id, "established active outgoing connection");
-            self.connection_info.decr_state(peer.state);
-            self.connection_info.inc_out();
+            self.connection_info.decr_peer(peer);
             peer.state = PeerConnectionState::Out;
+            self.connection_info.inc_peer(peer);
+            peer.unreachable = false;
+            peer.ever_connected = true;
+            peer.pending_eviction = false;
+            peer.record_dial_with_capacity(
+                std::time::Instant::now(),
+                DialOutcome::Connected,
+                self.dial_history_capacity,
+            );
         }
     }
 
@@ -678,6 +2068,7 @@ impl PeersManager {
             }
         }
 
+        self.record_dial(peer_id, DialOutcome::ConnectionFailed);
         self.on_connection_failure(remote_addr, peer_id, err, ReputationChangeKind::FailedToConnect)
     }
 
@@ -690,14 +2081,18 @@ impl PeersManager {
     ) {
         trace!(target: "net::peers", ?remote_addr, ?peer_id, %err, "handling failed connection");
 
+        if self.trusted_peer_ids.contains(peer_id) {
+            self.trusted_failing_since.entry(*peer_id).or_insert_with(std::time::Instant::now);
+        }
+
         if err.is_fatal_protocol_error() {
             trace!(target: "net::peers", ?remote_addr, ?peer_id, %err, "fatal connection error");
             // remove the peer to which we can't establish a connection due to protocol related
             // issues.
             if let Entry::Occupied(mut entry) = self.peers.entry(*peer_id) {
-                self.connection_info.decr_state(entry.get().state);
-                // only remove if the peer is not trusted
-                if entry.get().is_trusted() {
+                self.connection_info.decr_peer(entry.get());
+                // only remove if the peer is not trusted or a protected static peer
+                if entry.get().is_trusted() || (self.protect_static && entry.get().is_static()) {
                     entry.get_mut().state = PeerConnectionState::Idle;
                 } else {
                     entry.remove();
@@ -717,6 +2112,7 @@ impl PeersManager {
         } else {
             let mut backoff_until = None;
             let mut remove_peer = false;
+            let mut penalty = None;
 
             if let Some(peer) = self.peers.get_mut(peer_id) {
                 if let Some(kind) = err.should_backoff() {
@@ -731,12 +2127,14 @@ impl PeersManager {
                         trace!(target: "net::peers", ?peer_id, ?backoff, "backing off trusted peer");
                     } else {
                         // Increment peer.backoff_counter
-                        if kind.is_severe() {
-                            peer.severe_backoff_counter =
-                                peer.severe_backoff_counter.saturating_add(1);
-                        }
+                        peer.record_backoff_at(&kind, std::time::Instant::now());
                         trace!(target: "net::peers", ?peer_id, ?kind, severe_backoff_counter=peer.severe_backoff_counter, "backing off basic peer");
 
+                        if peer.severe_backoff_counter > self.max_dial_attempts {
+                            trace!(target: "net::peers", ?peer_id, "marking peer as unreachable");
+                            peer.unreachable = true;
+                        }
+
                         let backoff_time =
                             self.backoff_durations.backoff_until(kind, peer.severe_backoff_counter);
 
@@ -747,11 +2145,10 @@ impl PeersManager {
                     }
                 } else {
                     // If the error was not a backoff error, we reduce the peer's reputation
-                    let reputation_change = self.reputation_weights.change(reputation_change);
-                    peer.reputation = peer.reputation.saturating_add(reputation_change.as_i32());
+                    penalty = Some(reputation_change);
                 };
 
-                self.connection_info.decr_state(peer.state);
+                self.connection_info.decr_peer(peer);
                 peer.state = PeerConnectionState::Idle;
 
                 if peer.severe_backoff_counter > self.max_backoff_count &&
@@ -764,6 +2161,12 @@ impl PeersManager {
                 }
             }
 
+            // apply the penalty like any other reputation change, so that it respects frozen
+            // reputation, the floors of trusted and static peers and the ban threshold
+            if let Some(kind) = penalty {
+                self.apply_reputation_change(peer_id, kind);
+            }
+
             // remove peer if it has been marked for removal
             if remove_peer {
                 trace!(target: "net", ?peer_id, "removed peer after exceeding backoff counter");
@@ -782,12 +2185,14 @@ impl PeersManager {
     /// peer.
     ///
     /// If the session was an outgoing connection, this means that the peer initiated a connection
-    /// to us at the same time and this connection is already established.
-    pub(crate) const fn on_already_connected(&mut self, direction: Direction) {
+    /// to us at the same time and this connection is already established. If it was an incoming
+    /// connection, the peer is penalized with [`ReputationChangeKind::AlreadyConnected`].
+    pub(crate) fn on_already_connected(&mut self, peer_id: PeerId, direction: Direction) {
         match direction {
             Direction::Incoming => {
                 // need to decrement the ingoing counter
                 self.connection_info.decr_pending_in();
+                self.penalize_duplicate_connection(&peer_id);
             }
             Direction::Outgoing(_) => {
                 // cleanup is handled when the incoming active session is activated in
@@ -796,6 +2201,15 @@ impl PeersManager {
         }
     }
 
+    /// Applies the [`ReputationChangeKind::AlreadyConnected`] penalty to a peer that tried to open
+    /// another session while it is already connected.
+    ///
+    /// The default weight of this penalty is zero, see
+    /// [`ReputationChangeWeights::already_connected`].
+    pub(crate) fn penalize_duplicate_connection(&mut self, peer_id: &PeerId) {
+        self.apply_reputation_change(peer_id, ReputationChangeKind::AlreadyConnected)
+    }
+
     /// Called for a newly discovered peer.
     ///
     /// If the peer already exists, then the address, kind and `fork_id` will be updated.
@@ -803,11 +2217,107 @@ impl PeersManager {
         self.add_peer_kind(peer_id, None, addr, fork_id)
     }
 
+    /// Called for a newly discovered peer, seeding it from the given memory of a previous
+    /// session.
+    ///
+    /// If the peer isn't tracked yet and is remembered, it's added with the remembered kind,
+    /// fork id and reputation instead of the defaults for discovered peers. Otherwise this is the
+    /// same as [`Self::add_peer`].
+    pub fn add_discovered_with_memory(
+        &mut self,
+        record: NodeRecord,
+        memory: &HashMap<PeerId, PersistedPeerInfo>,
+    ) {
+        let NodeRecord { address, tcp_port, udp_port, id } = record;
+        let addr = PeerAddr::new_with_ports(address, tcp_port, Some(udp_port));
+        let Some(info) = memory.get(&id).filter(|_| !self.peers.contains_key(&id)) else {
+            return self.add_peer(id, addr, None)
+        };
+
+        self.add_peer_kind(id, Some(info.kind), addr.with_quic_port(info.quic_port), info.fork_id);
+        if let Some(peer) = self.peers.get_mut(&id) {
+            trace!(target: "net::peers", peer_id=?id, reputation=info.reputation, "seeded discovered peer from memory");
+            peer.reputation = info.reputation;
+            peer.record_peak_reputation();
+            peer.last_ban_reason = info.last_ban_reason;
+            peer.protocol_version = info.protocol_version;
+        }
+    }
+
+    /// Merges persisted peer records into the live peer set, e.g. after a crash when the persisted
+    /// peers file and the recovered in-memory peer set diverged.
+    ///
+    /// Unknown peers are added with their persisted metadata, unless they are banned or were
+    /// forgotten. Known peers take the persisted kind and fork id and keep the higher of both
+    /// reputations. Trusted peers are never changed to a lower kind, such records are reported as
+    /// conflicts instead.
+    pub fn reconcile(&mut self, persisted: &[PersistedPeerInfo]) -> ReconcileReport {
+        let mut report = ReconcileReport::default();
+        for info in persisted {
+            let NodeRecord { address, tcp_port, udp_port, id: peer_id } = info.record;
+            match self.peers.entry(peer_id) {
+                Entry::Occupied(mut entry) => {
+                    let peer = entry.get_mut();
+                    let mut updated = false;
+                    if peer.kind != info.kind {
+                        if peer.is_trusted() {
+                            report.conflicts.push(peer_id);
+                        } else {
+                            self.connection_info.set_kind(peer, info.kind);
+                            updated = true;
+                        }
+                    }
+                    if info.fork_id.is_some() && peer.fork_id.as_deref() != info.fork_id.as_ref() {
+                        peer.fork_id = info.fork_id.map(Box::new);
+                        updated = true;
+                    }
+                    if info.reputation > peer.reputation {
+                        peer.reputation = info.reputation;
+                        peer.record_peak_reputation();
+                        updated = true;
+                    }
+                    if updated {
+                        report.updated.push(peer_id);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    if self.ban_list.is_banned(&peer_id, &address) ||
+                        self.forgotten_peers.contains_key(&peer_id)
+                    {
+                        continue
+                    }
+                    let mut peer = Peer::with_kind(
+                        PeerAddr::new_with_ports(address, tcp_port, Some(udp_port))
+                            .with_quic_port(info.quic_port),
+                        info.kind,
+                    );
+                    peer.fork_id = info.fork_id.map(Box::new);
+                    peer.reputation = info.reputation;
+                    peer.record_peak_reputation();
+                    peer.last_ban_reason = info.last_ban_reason;
+                    peer.protocol_version = info.protocol_version;
+                    entry.insert(peer);
+                    self.queued_actions.push_back(PeerAction::PeerAdded(peer_id));
+                    report.added.push(peer_id);
+                }
+            }
+            if info.kind.is_trusted() {
+                self.trusted_peer_ids.insert(peer_id);
+            }
+        }
+
+        for peers in [&mut report.added, &mut report.updated, &mut report.conflicts] {
+            peers.sort_unstable();
+            peers.dedup();
+        }
+        report
+    }
+
     /// Marks the given peer as trusted.
     pub(crate) fn add_trusted_peer_id(&mut self, peer_id: PeerId) {
         self.trusted_peer_ids.insert(peer_id);
         if let Some(peer) = self.peers.get_mut(&peer_id) {
-            peer.kind = PeerKind::Trusted;
+            self.connection_info.set_kind(peer, PeerKind::Trusted);
         }
     }
 
@@ -842,14 +2352,25 @@ impl PeersManager {
             return
         }
 
+        if self.is_forgotten(&peer_id) {
+            trace!(target: "net::peers", ?peer_id, "Skipping forgotten peer");
+            return
+        }
+
+        let bonus = self.trusted_subnet_bonus_for(&addr);
         match self.peers.entry(peer_id) {
             Entry::Occupied(mut entry) => {
                 let peer = entry.get_mut();
+                if peer.addr.tcp().ip().is_unspecified() {
+                    // a placeholder, e.g. from an imported blocklist, that can now be dialed
+                    peer.unreachable = false;
+                }
                 peer.fork_id = fork_id.map(Box::new);
                 peer.addr = addr;
+                peer.last_seen = std::time::Instant::now();
 
                 if let Some(kind) = kind {
-                    peer.kind = kind;
+                    self.connection_info.set_kind(peer, kind);
                 }
 
                 if peer.state.is_incoming() {
@@ -861,10 +2382,21 @@ impl PeersManager {
             }
             Entry::Vacant(entry) => {
                 trace!(target: "net::peers", ?peer_id, addr=?addr.tcp(), "discovered new node");
-                let mut peer = Peer::with_kind(addr, kind.unwrap_or(PeerKind::Basic));
+                let mut peer = match kind {
+                    Some(kind) => Peer::with_kind(addr, kind),
+                    None => Peer {
+                        reputation: self.discovered_peer_reputation,
+                        peak_reputation: self.discovered_peer_reputation,
+                        ..Peer::new_discovered(addr)
+                    },
+                };
                 peer.fork_id = fork_id.map(Box::new);
+                if !peer.is_trusted() {
+                    Self::apply_initial_bonus(&mut peer, bonus);
+                }
                 entry.insert(peer);
                 self.queued_actions.push_back(PeerAction::PeerAdded(peer_id));
+                self.evict_discovered_peers();
             }
         }
 
@@ -874,6 +2406,107 @@ impl PeersManager {
         }
     }
 
+    /// Returns the [`PeersConfig::trusted_subnet_bonus`] if the address is in the same subnet as
+    /// a trusted peer, and 0 otherwise.
+    ///
+    /// Subnets are compared by their first [`TRUSTED_SUBNET_V4_BITS`] or
+    /// [`TRUSTED_SUBNET_V6_BITS`].
+    fn trusted_subnet_bonus_for(&self, addr: &PeerAddr) -> i32 {
+        let Some(bonus) = self.trusted_subnet_bonus else { return 0 };
+        let subnet = addr.subnet_group(TRUSTED_SUBNET_V4_BITS, TRUSTED_SUBNET_V6_BITS);
+        let in_trusted_subnet =
+            self.trusted_peer_ids.iter().filter_map(|id| self.peers.get(id)).any(|peer| {
+                peer.addr.subnet_group(TRUSTED_SUBNET_V4_BITS, TRUSTED_SUBNET_V6_BITS) == subnet
+            });
+        if in_trusted_subnet {
+            bonus
+        } else {
+            0
+        }
+    }
+
+    /// Adds the initial reputation bonus to a newly created peer.
+    const fn apply_initial_bonus(peer: &mut Peer, bonus: i32) {
+        if bonus != 0 {
+            peer.reputation = peer.reputation.saturating_add(bonus);
+            peer.record_peak_reputation();
+        }
+    }
+
+    /// Evicts the least recently seen basic peers we never connected to once there are more than
+    /// `max_discovered_peers` of them, down to 90% of the limit.
+    ///
+    /// Every added peer adds at most one evictable peer, so the peer set is only scanned once
+    /// enough peers were added since the last scan to possibly exceed the limit.
+    fn evict_discovered_peers(&mut self) {
+        if self.discovered_until_eviction_check > 0 {
+            self.discovered_until_eviction_check -= 1;
+            return
+        }
+
+        // the number of peers is an upper bound for the evictable peers
+        let mut remaining = self.peers.len();
+        if remaining > self.max_discovered_peers {
+            let is_evictable = |peer_id: &PeerId, peer: &Peer| {
+                peer.kind == PeerKind::Basic &&
+                    !peer.was_ever_connected() &&
+                    peer.state.is_unconnected() &&
+                    !self.trusted_peer_ids.contains(peer_id)
+            };
+            let mut evictable = self
+                .peers
+                .iter()
+                .filter(|(peer_id, peer)| is_evictable(peer_id, peer))
+                .map(|(peer_id, peer)| (peer.last_seen, *peer_id))
+                .collect::<Vec<_>>();
+            remaining = evictable.len();
+
+            if evictable.len() > self.max_discovered_peers {
+                let low_water_mark = self.max_discovered_peers - self.max_discovered_peers / 10;
+                let excess = evictable.len() - low_water_mark;
+                evictable.select_nth_unstable(excess - 1);
+                for (_, peer_id) in evictable.into_iter().take(excess) {
+                    trace!(target: "net::peers", ?peer_id, "evicting least recently seen peer");
+                    self.peers.remove(&peer_id);
+                    self.queued_actions.push_back(PeerAction::PeerRemoved(peer_id));
+                }
+                remaining = low_water_mark;
+            }
+        }
+
+        self.discovered_until_eviction_check = self.max_discovered_peers.saturating_sub(remaining);
+    }
+
+    /// Adds the given node records as basic peers, as if they were discovered, and returns how
+    /// many peers were added.
+    ///
+    /// Records of already known peers are skipped and don't update the known peer. Malformed
+    /// records without a usable address, i.e. with an unspecified ip or a zero tcp port, are
+    /// skipped and counted separately. Records can also be rejected by the peer set, e.g. if the
+    /// ip is not allowed by the ip filter.
+    pub fn extend_from_records(&mut self, records: impl IntoIterator<Item = NodeRecord>) -> usize {
+        let mut added = 0;
+        let mut malformed = 0;
+        for NodeRecord { address, tcp_port, udp_port, id } in records {
+            if address.is_unspecified() || tcp_port == 0 {
+                malformed += 1;
+                continue
+            }
+            if self.peers.contains_key(&id) {
+                continue
+            }
+            self.add_peer(id, PeerAddr::new_with_ports(address, tcp_port, Some(udp_port)), None);
+            if self.peers.contains_key(&id) {
+                added += 1;
+            }
+        }
+        if malformed > 0 {
+            warn!(target: "net::peers", malformed, "skipped node records without usable address");
+        }
+        trace!(target: "net::peers", added, "imported node records");
+        added
+    }
+
     /// Removes the tracked node from the set.
     pub(crate) fn remove_peer(&mut self, peer_id: PeerId) {
         let Entry::Occupied(entry) = self.peers.entry(peer_id) else { return };
@@ -901,6 +2534,37 @@ impl PeersManager {
         }
     }
 
+    /// Removes the peer from the peer set and prevents it from being re-added for `block_for`.
+    ///
+    /// Unlike [`Self::remove_peer`], this keeps discovery from immediately re-adding a peer that
+    /// was removed manually. If the peer is connected, its session is terminated and the entry is
+    /// removed once disconnected.
+    ///
+    /// Trusted peers can't be forgotten.
+    pub fn forget_peer(&mut self, peer_id: &PeerId, block_for: Duration) {
+        if self.trusted_peer_ids.contains(peer_id) ||
+            self.peers.get(peer_id).is_some_and(|peer| peer.is_trusted())
+        {
+            return
+        }
+
+        trace!(target: "net::peers", ?peer_id, ?block_for, "forgetting peer");
+        self.remove_peer(*peer_id);
+        self.forgotten_peers.insert(*peer_id, std::time::Instant::now() + block_for);
+    }
+
+    /// Returns `true` if the peer was forgotten and the block window hasn't expired yet.
+    ///
+    /// Expired entries are removed.
+    fn is_forgotten(&mut self, peer_id: &PeerId) -> bool {
+        let Entry::Occupied(entry) = self.forgotten_peers.entry(*peer_id) else { return false };
+        if std::time::Instant::now() > *entry.get() {
+            entry.remove();
+            return false
+        }
+        true
+    }
+
     /// Connect to the given peer. NOTE: if the maximum number of outbound sessions is reached,
     /// this won't do anything. See `reth_network::SessionManager::dial_outbound`.
     #[cfg_attr(not(test), expect(dead_code))]
@@ -935,17 +2599,23 @@ impl PeersManager {
             return
         }
 
+        if self.is_forgotten(&peer_id) {
+            trace!(target: "net::peers", ?peer_id, "Skipping forgotten peer");
+            return
+        }
+
+        let bonus = self.trusted_subnet_bonus_for(&addr);
         match self.peers.entry(peer_id) {
             Entry::Occupied(mut entry) => {
                 let peer = entry.get_mut();
-                peer.kind = kind;
+                self.connection_info.set_kind(peer, kind);
                 peer.fork_id = fork_id.map(Box::new);
                 peer.addr = addr;
 
                 if peer.state == PeerConnectionState::Idle {
                     // Try connecting again.
                     peer.state = PeerConnectionState::PendingOut;
-                    self.connection_info.inc_pending_out();
+                    self.connection_info.inc_peer(peer);
                     self.queued_actions
                         .push_back(PeerAction::Connect { peer_id, remote_addr: addr.tcp() });
                 }
@@ -955,8 +2625,10 @@ impl PeersManager {
                 let mut peer = Peer::with_kind(addr, kind);
                 peer.state = PeerConnectionState::PendingOut;
                 peer.fork_id = fork_id.map(Box::new);
-                entry.insert(peer);
-                self.connection_info.inc_pending_out();
+                if !kind.is_trusted() {
+                    Self::apply_initial_bonus(&mut peer, bonus);
+                }
+                self.connection_info.inc_peer(entry.insert(peer));
                 self.queued_actions
                     .push_back(PeerAction::Connect { peer_id, remote_addr: addr.tcp() });
             }
@@ -967,6 +2639,54 @@ impl PeersManager {
         }
     }
 
+    /// Loads trusted peers from the given reader.
+    ///
+    /// The input is expected to contain one enode URL per line, empty lines and lines starting
+    /// with `#` are ignored. Known peers are promoted to [`PeerKind::Trusted`], unknown peers are
+    /// added as trusted peers.
+    ///
+    /// The input is validated before any peer is updated, so an invalid line leaves the peer set
+    /// untouched.
+    ///
+    /// Returns the number of peers that were not trusted before.
+    pub fn load_trusted_from_reader<R: Read>(
+        &mut self,
+        reader: R,
+    ) -> Result<usize, LoadTrustedPeersError> {
+        let mut records = Vec::new();
+        for (idx, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let record = line
+                .parse::<NodeRecord>()
+                .map_err(|err| LoadTrustedPeersError::InvalidRecord { line: idx + 1, err })?;
+            records.push(record);
+        }
+
+        let mut added = 0;
+        for NodeRecord { address, tcp_port, udp_port, id } in records {
+            if self.trusted_peer_ids.insert(id) {
+                added += 1;
+            }
+            if let Some(peer) = self.peers.get_mut(&id) {
+                self.connection_info.set_kind(peer, PeerKind::Trusted);
+            } else {
+                self.add_peer_kind(
+                    id,
+                    Some(PeerKind::Trusted),
+                    PeerAddr::new_with_ports(address, tcp_port, Some(udp_port)),
+                    None,
+                );
+            }
+        }
+
+        trace!(target: "net::peers", added, "loaded trusted peers");
+        Ok(added)
+    }
+
     /// Removes the tracked node from the trusted set.
     pub(crate) fn remove_peer_from_trusted_set(&mut self, peer_id: PeerId) {
         let Entry::Occupied(mut entry) = self.peers.entry(peer_id) else { return };
@@ -975,11 +2695,58 @@ impl PeersManager {
         }
 
         let peer = entry.get_mut();
-        peer.kind = PeerKind::Basic;
+        self.connection_info.set_kind(peer, PeerKind::Basic);
 
         self.trusted_peer_ids.remove(&peer_id);
     }
 
+    /// Demotes a static peer to a basic peer, so that it's no longer preferred or protected and is
+    /// subject to the regular churn, without forgetting it.
+    ///
+    /// The reputation and connection state of the peer are preserved. Returns `true` if the peer
+    /// was found and was static.
+    pub fn retire_static(&mut self, id: &PeerId) -> bool {
+        let Some(peer) = self.peers.get_mut(id) else { return false };
+        if !peer.is_static() {
+            return false
+        }
+        trace!(target: "net::peers", peer_id=?id, "retiring static peer");
+        self.connection_info.set_kind(peer, PeerKind::Basic);
+        true
+    }
+
+    /// Returns up to `limit` idle peers to dial, in the order they should be dialed.
+    ///
+    /// All eligible trusted peers are always returned before any other peer, regardless of their
+    /// reputation, followed by static peers and then basic peers. Within each group, peers are
+    /// ordered by reputation, highest first, preferring peers with a discovered `fork_id` on ties.
+    ///
+    /// Like [`Self::best_unconnected`], banned, backed off and unreachable peers are skipped and
+    /// only trusted peers are considered if `trusted_nodes_only` is enabled.
+    pub fn select_dial_candidates(&self, limit: usize) -> Vec<PeerId> {
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| {
+                !peer.is_observer() &&
+                    Self::is_dialable(peer, self.ban_threshold) &&
+                    (!self.trusted_nodes_only || peer.is_trusted())
+            })
+            .map(|(peer_id, peer)| {
+                let group = if peer.is_trusted() {
+                    0
+                } else if peer.is_static() {
+                    1
+                } else {
+                    2
+                };
+                (group, std::cmp::Reverse((peer.reputation, peer.fork_id.is_some())), *peer_id)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        candidates.into_iter().take(limit).map(|(_, _, peer_id)| peer_id).collect()
+    }
+
     /// Returns the best idle peer to connect to.
     ///
     /// Peers that are `trusted` or `static`, see [`PeerKind`], are prioritized as long as they're
@@ -992,12 +2759,15 @@ impl PeersManager {
     /// `trusted` peers.
     ///
     /// Returns `None` if no peer is available.
-    fn best_unconnected(&mut self) -> Option<(PeerId, &mut Peer)> {
+    ///
+    /// If `trusted_only` is set, only trusted peers are considered.
+    fn best_unconnected(&mut self, trusted_only: bool) -> Option<(PeerId, &mut Peer)> {
+        let trusted_only = trusted_only || self.trusted_nodes_only;
+        let ban_threshold = self.ban_threshold;
         let mut unconnected = self.peers.iter_mut().filter(|(_, peer)| {
-            !peer.is_backed_off() &&
-                !peer.is_banned() &&
-                peer.state.is_unconnected() &&
-                (!self.trusted_nodes_only || peer.is_trusted())
+            !peer.is_observer() &&
+                Self::is_dialable(peer, ban_threshold) &&
+                (!trusted_only || peer.is_trusted())
         });
 
         // keep track of the best peer, if there's one
@@ -1027,11 +2797,116 @@ impl PeersManager {
         Some((*best_peer.0, best_peer.1))
     }
 
+    /// Returns `true` if the peer is currently eligible for an outbound connection.
+    const fn is_dialable(peer: &Peer, ban_threshold: i32) -> bool {
+        peer.addr.tcp_addr().is_some() &&
+            !peer.is_backed_off() &&
+            !peer.is_unreachable() &&
+            !peer.is_banned_with_threshold(ban_threshold) &&
+            peer.state.is_unconnected()
+    }
+
+    /// Returns the number of outbound slots reserved for trusted peers that are currently not
+    /// occupied by a trusted peer, see [`ConnectionsConfig::reserved_trusted_slots`].
+    fn unused_trusted_reservation(&self) -> usize {
+        let reserved = self.connection_info.config.reserved_trusted_slots;
+        if reserved == 0 {
+            return 0
+        }
+        let trusted_outbound = self
+            .peers
+            .values()
+            .filter(|peer| {
+                peer.is_trusted() &&
+                    matches!(
+                        peer.state,
+                        PeerConnectionState::Out | PeerConnectionState::PendingOut
+                    )
+            })
+            .count();
+        reserved.saturating_sub(trusted_outbound)
+    }
+
+    /// Disconnects outbound peers that occupy slots reserved for trusted peers, if there are
+    /// trusted peers waiting to be dialed.
+    ///
+    /// Trusted peers that failed to connect since their last session don't count as waiting, they
+    /// only take reserved slots that are free. Evictions happen at most once per low backoff
+    /// duration, so peers aren't churned on every refill.
+    ///
+    /// The lowest reputation peers are evicted first, static peers are never evicted.
+    fn evict_for_reserved_trusted_slots(&mut self) {
+        let unused_reservation = self.unused_trusted_reservation();
+        if unused_reservation == 0 {
+            return
+        }
+        let now = std::time::Instant::now();
+        if self
+            .last_reserved_slot_eviction
+            .is_some_and(|last| now.saturating_duration_since(last) < self.backoff_durations.low)
+        {
+            return
+        }
+        let waiting = self
+            .peers
+            .iter()
+            .filter(|(peer_id, peer)| {
+                peer.is_trusted() &&
+                    Self::is_dialable(peer, self.ban_threshold) &&
+                    !self.trusted_failing_since.contains_key(peer_id)
+            })
+            .count();
+        let needed = unused_reservation.min(waiting);
+
+        // slots that are free or about to be freed by ongoing disconnects
+        let disconnecting = self.peers.values().filter(|peer| {
+            peer.state == PeerConnectionState::DisconnectingOut && !peer.is_observer()
+        });
+        let free = self
+            .connection_info
+            .config
+            .max_outbound
+            .saturating_sub(self.connection_info.num_outbound) +
+            disconnecting.count();
+        let to_evict = needed.saturating_sub(free);
+        if to_evict == 0 {
+            return
+        }
+
+        let mut candidates = self
+            .peers
+            .iter()
+            .filter(|(_, peer)| {
+                peer.state == PeerConnectionState::Out &&
+                    !peer.is_trusted() &&
+                    !peer.is_static() &&
+                    !peer.is_observer()
+            })
+            .map(|(peer_id, peer)| (peer.reputation, *peer_id))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+
+        if candidates.is_empty() {
+            return
+        }
+        self.last_reserved_slot_eviction = Some(now);
+        for (_, peer_id) in candidates.into_iter().take(to_evict) {
+            trace!(target: "net::peers", ?peer_id, "evicting outbound peer for reserved trusted slot");
+            if let Some(peer) = self.peers.get_mut(&peer_id) {
+                peer.state.disconnect();
+            }
+            self.queued_actions.push_back(PeerAction::Disconnect {
+                peer_id,
+                reason: Some(DisconnectReason::TooManyPeers),
+            });
+        }
+    }
+
     /// If there's capacity for new outbound connections, this will queue new
     /// [`PeerAction::Connect`] actions.
     ///
     /// New connections are only initiated, if slots are available and appropriate peers are
-    /// available.
+    /// available. Outbound slots reserved for trusted peers are only filled with trusted peers.
     fn fill_outbound_slots(&mut self) {
         self.tick();
 
@@ -1040,10 +2915,48 @@ impl PeersManager {
             return
         }
 
+        self.evict_pending_sessions();
+        self.evict_for_reserved_trusted_slots();
+
+        // observers don't count toward the outbound limits, so they're dialed regardless of
+        // capacity, but only up to their own limit of concurrent dials
+        let mut pending_observers = self
+            .peers
+            .values()
+            .filter(|peer| peer.is_observer() && peer.state.is_pending_out())
+            .count();
+        let ban_threshold = self.ban_threshold;
+        for (peer_id, peer) in &mut self.peers {
+            if pending_observers >= self.connection_info.config.max_concurrent_observer_dials {
+                break
+            }
+            if !peer.is_observer() ||
+                !Self::is_dialable(peer, ban_threshold) ||
+                (self.trusted_nodes_only && !peer.is_trusted())
+            {
+                continue
+            }
+            let Some(remote_addr) = peer.addr.tcp_addr() else { continue };
+            if self.ban_list.is_banned(peer_id, &remote_addr.ip()) {
+                continue
+            }
+
+            trace!(target: "net::peers", ?peer_id, addr=?peer.addr, "schedule outbound observer connection");
+            peer.state = PeerConnectionState::PendingOut;
+            pending_observers += 1;
+            self.queued_actions.push_back(PeerAction::Connect { peer_id: *peer_id, remote_addr });
+        }
+
         // as long as there are slots available fill them with the best peers
         while self.connection_info.has_out_capacity() {
+            // with reserved slots, other peers may only take the outbound slots that are left
+            let trusted_only = self.connection_info.config.reserved_trusted_slots > 0 &&
+                self.connection_info.num_outbound +
+                    self.connection_info.num_pending_out +
+                    self.unused_trusted_reservation() >=
+                    self.connection_info.config.max_outbound;
             let action = {
-                let (peer_id, peer) = match self.best_unconnected() {
+                let (peer_id, peer) = match self.best_unconnected(trusted_only) {
                     Some(peer) => peer,
                     _ => break,
                 };
@@ -1136,11 +3049,17 @@ impl PeersManager {
                     if now > *until {
                         if let Some(peer) = self.peers.get_mut(peer_id) {
                             peer.backed_off = false;
+                            // give unreachable peers another attempt, repeated failures
+                            // eventually remove them via `max_backoff_count`
+                            peer.unreachable = false;
                         }
                         return false
                     }
                     true
-                })
+                });
+
+                // clear expired tombstones of forgotten peers
+                self.forgotten_peers.retain(|_, until| now <= *until);
             }
 
             while self.refill_slots_interval.poll_tick(cx).is_ready() {
@@ -1193,6 +3112,15 @@ impl ConnectionInfo {
             self.num_outbound < self.config.max_outbound
     }
 
+    /// Returns `true` if the reputation is below the configured minimum reputation for inbound
+    /// sessions.
+    const fn is_below_min_inbound_reputation(&self, reputation: i32) -> bool {
+        match self.config.min_inbound_reputation {
+            Some(min_reputation) => reputation < min_reputation,
+            None => false,
+        }
+    }
+
     ///  Returns `true` if there's still capacity to accept a new incoming connection.
     const fn has_in_capacity(&self) -> bool {
         self.num_inbound < self.config.max_inbound
@@ -1212,6 +3140,38 @@ impl ConnectionInfo {
         }
     }
 
+    /// Increments the counter matching the given state.
+    const fn inc_state(&mut self, state: PeerConnectionState) {
+        match state {
+            PeerConnectionState::Idle => {}
+            PeerConnectionState::DisconnectingIn | PeerConnectionState::In => self.inc_in(),
+            PeerConnectionState::DisconnectingOut | PeerConnectionState::Out => self.inc_out(),
+            PeerConnectionState::PendingOut => self.inc_pending_out(),
+        }
+    }
+
+    /// Accounts for the peer's current state, unless it's an observer.
+    const fn inc_peer(&mut self, peer: &Peer) {
+        if !peer.is_observer() {
+            self.inc_state(peer.state);
+        }
+    }
+
+    /// Releases the peer's current state, unless it's an observer.
+    const fn decr_peer(&mut self, peer: &Peer) {
+        if !peer.is_observer() {
+            self.decr_state(peer.state);
+        }
+    }
+
+    /// Changes the kind of the peer, moving its connection in or out of the counters if it
+    /// becomes or stops being an observer.
+    const fn set_kind(&mut self, peer: &mut Peer, kind: PeerKind) {
+        self.decr_peer(peer);
+        peer.kind = kind;
+        self.inc_peer(peer);
+    }
+
     const fn decr_out(&mut self) {
         self.num_outbound -= 1;
     }
@@ -1245,12 +3205,147 @@ impl ConnectionInfo {
     }
 }
 
-/// Actions the peer manager can trigger.
-#[derive(Debug)]
-pub enum PeerAction {
-    /// Start a new connection to a peer.
-    Connect {
-        /// The peer to connect to.
+/// Counters describing the peer set at a point in time, see
+/// [`PeersManager::snapshot_metrics`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PeersMetricsSnapshot {
+    /// Number of known peers.
+    pub total: usize,
+    /// Number of peers with an active session.
+    pub connected: usize,
+    /// Number of peers that are banned, either by reputation or by the ban list.
+    pub banned: usize,
+    /// Number of peers that are currently backed off.
+    pub backed_off: usize,
+    /// Number of trusted peers.
+    pub trusted: usize,
+    /// Number of static peers.
+    pub static_peers: usize,
+    /// Number of basic peers that are only known from discovery and currently not connected.
+    pub discovered_only: usize,
+}
+
+/// The effect of applying a new [`ConnectionsConfig`] on the active sessions, see
+/// [`PeersManager::slots_delta`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SlotDelta {
+    /// Number of active outbound sessions above the new outbound cap.
+    pub excess_outbound: usize,
+    /// Number of active inbound sessions above the new inbound cap.
+    pub excess_inbound: usize,
+    /// Number of outbound slots the new cap adds to the current one.
+    pub opened_outbound: usize,
+    /// Number of inbound slots the new cap adds to the current one.
+    pub opened_inbound: usize,
+}
+
+impl SlotDelta {
+    /// Returns the total number of sessions that would have to be drained.
+    pub const fn excess(&self) -> usize {
+        self.excess_outbound + self.excess_inbound
+    }
+
+    /// Returns the total number of slots that would open up.
+    pub const fn opened(&self) -> usize {
+        self.opened_outbound + self.opened_inbound
+    }
+}
+
+/// Fork compatibility of the known peers after a fork transition, see
+/// [`PeersManager::apply_fork_transition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ForkTransitionReport {
+    /// Number of peers whose announced fork id is compatible with the new filter.
+    pub compatible: usize,
+    /// Number of peers whose announced fork id is incompatible with the new filter.
+    pub incompatible: usize,
+    /// Number of peers that didn't announce a fork id.
+    pub unknown: usize,
+}
+
+/// Aggregated outcomes of [`PeersManager::apply_reputation_batch`].
+///
+/// All peer ids are sorted and deduplicated.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReputationBatchOutcome {
+    /// Number of changes that were applied.
+    pub applied: usize,
+    /// Number of changes that were ignored, e.g. for unknown peers or while frozen.
+    pub ignored: usize,
+    /// Peers that were banned by the batch.
+    pub banned: Vec<PeerId>,
+    /// Peers that were unbanned by the batch.
+    pub unbanned: Vec<PeerId>,
+}
+
+/// Changes to the peer set made by [`PeersManager::reconcile`].
+///
+/// All peer ids are sorted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReconcileReport {
+    /// Peers that were added from the persisted records.
+    pub added: Vec<PeerId>,
+    /// Known peers whose kind, fork id or reputation was updated.
+    pub updated: Vec<PeerId>,
+    /// Trusted peers whose persisted record has a lower kind, which was ignored.
+    pub conflicts: Vec<PeerId>,
+}
+
+/// A peer ordered by its eviction priority.
+///
+/// The ordering is chosen so that a [`BinaryHeap`] of candidates yields the peer that should be
+/// evicted first: evictable peers are greater than protected ones, and among them lower
+/// reputation is greater. Ties are broken by the lower peer id. Trusted, static and observer
+/// peers are protected and compare below all evictable peers, see [`Self::is_evictable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvictionCandidate {
+    peer_id: PeerId,
+    reputation: i32,
+    evictable: bool,
+}
+
+impl EvictionCandidate {
+    /// Creates the candidate for the given peer.
+    pub const fn new(peer_id: PeerId, peer: &Peer) -> Self {
+        Self {
+            peer_id,
+            reputation: peer.reputation,
+            evictable: !peer.is_trusted() && !peer.is_static() && !peer.is_observer(),
+        }
+    }
+
+    /// Returns the id of the peer.
+    pub const fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    /// Returns `true` if the peer may be evicted.
+    pub const fn is_evictable(&self) -> bool {
+        self.evictable
+    }
+}
+
+impl Ord for EvictionCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.evictable
+            .cmp(&other.evictable)
+            .then_with(|| other.reputation.cmp(&self.reputation))
+            .then_with(|| other.peer_id.cmp(&self.peer_id))
+    }
+}
+
+impl PartialOrd for EvictionCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Actions the peer manager can trigger.
+#[derive(Debug)]
+pub enum PeerAction {
+    /// Start a new connection to a peer.
+    Connect {
+        /// The peer to connect to.
         peer_id: PeerId,
         /// Where to reach the node
         remote_addr: SocketAddr,
@@ -1273,6 +3368,12 @@ pub enum PeerAction {
         /// The peer ID.
         peer_id: PeerId,
     },
+    /// Disconnect an incoming connection of a known peer whose reputation is below
+    /// [`ConnectionsConfig::min_inbound_reputation`].
+    DisconnectLowReputationIncoming {
+        /// The peer ID.
+        peer_id: PeerId,
+    },
     /// Ban the peer in discovery.
     DiscoveryBanPeerId {
         /// The peer ID.
@@ -1316,6 +3417,23 @@ impl Display for InboundConnectionError {
     }
 }
 
+/// Error returned by [`PeersManager::load_trusted_from_reader`].
+#[derive(Debug, Error)]
+pub enum LoadTrustedPeersError {
+    /// Failed to read the input.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A line could not be parsed as an enode URL.
+    #[error("invalid enode on line {line}: {err}")]
+    InvalidRecord {
+        /// The 1-based line number.
+        line: usize,
+        /// The parse error.
+        #[source]
+        err: NodeRecordParseError,
+    },
+}
+
 /// The reason a peer was backed off.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BackoffReason {
@@ -1339,36 +3457,50 @@ impl BackoffReason {
 
 #[cfg(test)]
 mod tests {
-    use alloy_primitives::B512;
+    use alloy_primitives::{B256, B512};
+    use rand::{rngs::StdRng, SeedableRng};
     use reth_eth_wire::{
         errors::{EthHandshakeError, EthStreamError, P2PHandshakeError, P2PStreamError},
         DisconnectReason,
     };
-    use reth_ethereum_forks::{ForkHash, ForkId};
+    use reth_ethereum_forks::{ForkFilter, ForkFilterKey, ForkHash, ForkId, Head};
     use reth_net_banlist::BanList;
     use reth_network_api::Direction;
-    use reth_network_peers::{PeerId, TrustedPeer};
+    use reth_network_peers::{NodeRecord, PeerId, TrustedPeer};
     use reth_network_types::{
-        peers::reputation::DEFAULT_REPUTATION, BackoffKind, Peer, ReputationChangeKind,
+        peers::{
+            addr::find_id_conflicts,
+            reputation::{
+                MergePolicy, BANNED_REPUTATION, DEFAULT_DISCOVERED_REPUTATION, DEFAULT_REPUTATION,
+                MAX_REPUTATION, QUARANTINE_REPUTATION,
+            },
+        },
+        BackoffKind, DialOutcome, IpSubnet, Peer, PeerKind, PersistedPeerInfo,
+        ReputationChangeKind, ReputationChangeOutcome, ReputationChangeWeights, ReputationTier,
     };
     use std::{
+        collections::{BTreeMap, HashMap, HashSet},
         future::{poll_fn, Future},
         io,
         net::{IpAddr, Ipv4Addr, SocketAddr},
         pin::Pin,
+        sync::{Arc, Mutex},
         task::{Context, Poll},
         time::Duration,
     };
     use url::Host;
 
-    use super::PeersManager;
+    use super::{LoadTrustedPeersError, PeersManager};
     use crate::{
         error::SessionError,
         peers::{
-            ConnectionInfo, InboundConnectionError, PeerAction, PeerAddr, PeerBackoffDurations,
-            PeerConnectionState,
+            ConnectionInfo, ConnectionsConfig, EvictionCandidate, ForkTransitionReport,
+            InboundConnectionError, PeerAction, PeerAddr, PeerBackoffDurations,
+            PeerConnectionState, PeersMetricsSnapshot, ReconcileReport, ReputationAccumulator,
+            ReputationBatchOutcome, SlotDelta,
         },
         session::PendingSessionHandshakeError,
+        test_utils::MockEventSource,
         PeersConfig,
     };
 
@@ -2142,7 +4274,7 @@ mod tests {
         // Simulate a rejection due to an already established connection, expecting the
         // `num_pending_in` to decrease by 1. The peer should remain connected and the `num_inbound`
         // should not be changed.
-        peers.on_already_connected(Direction::Incoming);
+        peers.on_already_connected(peer, Direction::Incoming);
 
         let p = peers.peers.get_mut(&peer).expect("peer not found");
         assert_eq!(p.addr.tcp(), socket_addr);
@@ -2208,10 +4340,10 @@ mod tests {
         let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 1, 2)), 8008);
         let mut peers = PeersManager::default();
         peers.add_peer(peer, PeerAddr::from_tcp(socket_addr), None);
-        assert_eq!(peers.get_reputation(&peer), Some(0));
+        assert_eq!(peers.get_reputation(&peer), Some(DEFAULT_DISCOVERED_REPUTATION));
 
         peers.apply_reputation_change(&peer, ReputationChangeKind::Other(1024));
-        assert_eq!(peers.get_reputation(&peer), Some(1024));
+        assert_eq!(peers.get_reputation(&peer), Some(DEFAULT_DISCOVERED_REPUTATION + 1024));
 
         peers.apply_reputation_change(&peer, ReputationChangeKind::Reset);
         assert_eq!(peers.get_reputation(&peer), Some(0));
@@ -2680,7 +4812,7 @@ mod tests {
     async fn test_tick() {
         let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 1, 2));
         let socket_addr = SocketAddr::new(ip, 8008);
-        let config = PeersConfig::test();
+        let config = PeersConfig::test().with_discovered_peer_reputation(DEFAULT_REPUTATION);
         let mut peer_manager = PeersManager::new(config);
         let peer_id = PeerId::random();
         peer_manager.add_peer(peer_id, PeerAddr::from_tcp(socket_addr), None);
@@ -2907,7 +5039,7 @@ mod tests {
         }
 
         peers.on_incoming_session_established(peer_id, addr);
-        peers.on_already_connected(Direction::Outgoing(peer_id));
+        peers.on_already_connected(peer_id, Direction::Outgoing(peer_id));
         assert_eq!(peers.peers.get(&peer_id).unwrap().state, PeerConnectionState::In);
         assert_eq!(peers.connection_info.num_inbound, 1);
         assert_eq!(peers.connection_info.num_pending_out, 0);
@@ -3305,7 +5437,2403 @@ mod tests {
         peers.add_peer(with_fork, PeerAddr::from_tcp(addr), None);
         peers.peers.get_mut(&with_fork).unwrap().fork_id = Some(Box::new(fork_id));
 
-        let (best_id, _) = peers.best_unconnected().unwrap();
+        let (best_id, _) = peers.best_unconnected(false).unwrap();
         assert_eq!(best_id, with_fork, "fork_id should break tie when reputation is equal");
     }
+
+    #[tokio::test]
+    async fn test_contains_peer() {
+        let mut peers = PeersManager::default();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8008);
+
+        let peer = PeerId::random();
+        assert!(!peers.contains_peer(&peer));
+
+        peers.add_peer(peer, PeerAddr::from_tcp(addr), None);
+        assert!(peers.contains_peer(&peer));
+
+        let other = PeerId::random();
+        assert!(!peers.contains_peer(&other));
+
+        peers.remove_peer(peer);
+        assert!(!peers.contains_peer(&peer));
+    }
+
+    #[tokio::test]
+    async fn test_set_config_raised_ban_threshold_bans_peers() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8008);
+
+        let idle = PeerId::random();
+        peers.add_peer(idle, PeerAddr::from_tcp(addr), None);
+        let connected = PeerId::random();
+        peers.add_peer(connected, PeerAddr::from_tcp(addr), None);
+        let healthy = PeerId::random();
+        peers.add_peer(healthy, PeerAddr::from_tcp(addr), None);
+        peers.queued_actions.clear();
+
+        let reputation = peers.ban_threshold / 2;
+        peers.peers.get_mut(&idle).unwrap().reputation = reputation;
+        let peer = peers.peers.get_mut(&connected).unwrap();
+        peer.reputation = reputation;
+        peer.state = PeerConnectionState::Out;
+
+        // a stricter threshold bans peers that were fine before
+        peers.set_config(PeersConfig::test().with_ban_threshold(reputation + 1));
+
+        assert!(peers.ban_list.is_banned_peer(&idle));
+        assert!(peers.ban_list.is_banned_peer(&connected));
+        assert!(!peers.ban_list.is_banned_peer(&healthy));
+        assert_eq!(peers.peers[&connected].state, PeerConnectionState::DisconnectingOut);
+
+        let actions = peers.queued_actions.drain(..).collect::<Vec<_>>();
+        assert!(actions.iter().any(|action| matches!(action, PeerAction::Disconnect { peer_id, .. } if *peer_id == connected)));
+        assert!(actions
+            .iter()
+            .any(|action| matches!(action, PeerAction::BanPeer { peer_id } if *peer_id == idle)));
+        assert!(actions.iter().any(
+            |action| matches!(action, PeerAction::BanPeer { peer_id } if *peer_id == connected)
+        ));
+        assert!(!actions.iter().any(
+            |action| matches!(action, PeerAction::BanPeer { peer_id } if *peer_id == healthy)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_config_applies_limits() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        peers.set_config(PeersConfig::test().with_max_outbound(1).with_max_inbound(2));
+        assert_eq!(peers.connection_info.config.max_outbound, 1);
+        assert_eq!(peers.connection_info.config.max_inbound, 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_config_marks_excess_sessions() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        let outbound = [PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random()];
+        for (reputation, peer_id) in outbound.iter().enumerate() {
+            peers.add_peer(*peer_id, addr, None);
+            let peer = peers.peers.get_mut(peer_id).unwrap();
+            peer.state = PeerConnectionState::Out;
+            peer.reputation = reputation as i32;
+            peers.connection_info.inc_out();
+        }
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr);
+        let peer = peers.peers.get_mut(&trusted).unwrap();
+        peer.state = PeerConnectionState::Out;
+        peer.reputation = -100;
+        peers.connection_info.inc_out();
+        peers.queued_actions.clear();
+
+        // the lowest reputation sessions are marked, but not dropped mid-session
+        peers.set_config(PeersConfig::test().with_max_outbound(2));
+        let marked = |peers: &PeersManager| {
+            outbound.iter().map(|peer_id| peers.peers[peer_id].pending_eviction).collect::<Vec<_>>()
+        };
+        assert_eq!(marked(&peers), [true, true, true, false]);
+        assert!(!peers.peers[&trusted].pending_eviction);
+        assert!(peers.queued_actions.is_empty());
+
+        // a marked session closed in the meantime, the remaining excess is evicted at the next
+        // refill
+        peers.on_active_session_gracefully_closed(outbound[1]);
+        let disconnected = peers
+            .queued_actions
+            .iter()
+            .filter_map(|action| match action {
+                PeerAction::Disconnect {
+                    peer_id,
+                    reason: Some(DisconnectReason::TooManyPeers),
+                } => Some(*peer_id),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(disconnected, HashSet::from([outbound[0], outbound[2]]));
+        assert_eq!(peers.peers[&outbound[0]].state, PeerConnectionState::DisconnectingOut);
+        assert_eq!(peers.peers[&outbound[2]].state, PeerConnectionState::DisconnectingOut);
+        assert_eq!(peers.peers[&outbound[3]].state, PeerConnectionState::Out);
+        assert_eq!(marked(&peers), [false; 4]);
+    }
+
+    #[tokio::test]
+    async fn test_set_config_rechecks_ip_filter() {
+        use reth_net_banlist::IpFilter;
+
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = |ip: [u8; 4]| {
+            PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), 30303))
+        };
+
+        let (idle, connected, allowed, trusted) =
+            (PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random());
+        peers.add_peer(idle, addr([10, 0, 0, 1]), None);
+        peers.add_peer(connected, addr([10, 0, 0, 2]), None);
+        peers.peers.get_mut(&connected).unwrap().state = PeerConnectionState::In;
+        peers.connection_info.inc_in();
+        peers.add_peer(allowed, addr([192, 168, 0, 1]), None);
+        peers.add_trusted_peer(trusted, addr([10, 0, 0, 3]));
+        peers.queued_actions.clear();
+
+        let ip_filter = IpFilter::from_cidr_string("192.168.0.0/16").unwrap();
+        peers.set_config(PeersConfig::test().with_ip_filter(ip_filter));
+
+        assert!(!peers.contains_peer(&idle));
+        assert!(peers.contains_peer(&allowed));
+        assert!(peers.contains_peer(&trusted));
+        let peer = &peers.peers[&connected];
+        assert!(peer.pending_eviction && peer.remove_after_disconnect);
+        assert_eq!(peer.state, PeerConnectionState::In);
+
+        // the session is closed at the next refill even though the limits are not exceeded
+        peers.queued_actions.clear();
+        peers.fill_outbound_slots();
+        assert!(peers.queued_actions.iter().any(
+            |action| matches!(action, PeerAction::Disconnect { peer_id, .. } if *peer_id == connected)
+        ));
+        assert_eq!(peers.peers[&connected].state, PeerConnectionState::DisconnectingIn);
+    }
+
+    #[tokio::test]
+    async fn test_load_trusted_from_reader() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 30303);
+
+        let existing = PeerId::random();
+        peers.add_peer(existing, PeerAddr::from_tcp(addr), None);
+        let new = PeerId::random();
+
+        let input = format!(
+            "# trusted peers\n{}\n\n{}\n",
+            NodeRecord::new(addr, existing),
+            NodeRecord::new(addr, new)
+        );
+        assert_eq!(peers.load_trusted_from_reader(input.as_bytes()).unwrap(), 2);
+        assert_eq!(peers.num_known_peers(), 2);
+        assert!(peers.peers[&existing].is_trusted());
+        assert!(peers.peers[&new].is_trusted());
+        assert!(peers.trusted_peer_ids.contains(&existing));
+        assert!(peers.trusted_peer_ids.contains(&new));
+
+        // loading the same file again doesn't add anything
+        assert_eq!(peers.load_trusted_from_reader(input.as_bytes()).unwrap(), 0);
+        assert_eq!(peers.num_known_peers(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_load_trusted_from_reader_invalid() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 30303);
+
+        let input = format!("{}\nnot an enode\n", NodeRecord::new(addr, PeerId::random()));
+        let err = peers.load_trusted_from_reader(input.as_bytes()).unwrap_err();
+        assert!(matches!(err, LoadTrustedPeersError::InvalidRecord { line: 2, .. }));
+        assert_eq!(peers.num_known_peers(), 0);
+        assert!(peers.trusted_peer_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sample_weighted() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        let good = PeerId::random();
+        let neutral = PeerId::random();
+        let poor = PeerId::random();
+        let banned = PeerId::random();
+        let backed_off = PeerId::random();
+        for peer_id in [good, neutral, poor, banned, backed_off] {
+            peers.add_peer(peer_id, addr, None);
+        }
+        peers.peers.get_mut(&good).unwrap().reputation = MAX_REPUTATION;
+        peers.peers.get_mut(&poor).unwrap().reputation = BANNED_REPUTATION / 2;
+        peers.peers.get_mut(&banned).unwrap().reputation = BANNED_REPUTATION - 1;
+        peers.peers.get_mut(&backed_off).unwrap().backed_off = true;
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut counts = HashMap::<PeerId, usize>::new();
+        for _ in 0..10_000 {
+            let sample = peers.sample_weighted(1, &mut rng);
+            assert_eq!(sample.len(), 1);
+            *counts.entry(sample[0]).or_default() += 1;
+        }
+
+        assert!(!counts.contains_key(&banned));
+        assert!(!counts.contains_key(&backed_off));
+        assert!(counts[&good] > counts[&neutral]);
+        assert!(counts[&neutral] > counts[&poor]);
+        assert!(counts[&poor] > 0);
+
+        // the sample never contains duplicates and is bounded by the eligible peers
+        let mut sample = peers.sample_weighted(10, &mut rng);
+        sample.sort_unstable();
+        let mut expected = vec![good, neutral, poor];
+        expected.sort_unstable();
+        assert_eq!(sample, expected);
+
+        // same seed, same sample
+        let a = peers.sample_weighted(2, &mut StdRng::seed_from_u64(7));
+        let b = peers.sample_weighted(2, &mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+    }
+
+    #[tokio::test]
+    async fn test_is_quarantined() {
+        let config = PeersConfig::test().with_ban_threshold(-1000).with_quarantine_threshold(-100);
+        let mut peers = PeersManager::new(config);
+        let peer = PeerId::random();
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        assert!(!peers.is_quarantined(&peer));
+
+        peers.add_peer(peer, addr, None);
+        assert!(!peers.is_quarantined(&peer));
+
+        peers.peers.get_mut(&peer).unwrap().reputation = -101;
+        assert!(peers.is_quarantined(&peer));
+
+        peers.peers.get_mut(&peer).unwrap().reputation = -1001;
+        assert!(!peers.is_quarantined(&peer));
+    }
+
+    #[tokio::test]
+    async fn test_forget_peer() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let peer = PeerId::random();
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        peers.add_peer(peer, addr, None);
+        peers.forget_peer(&peer, Duration::from_millis(50));
+        assert!(!peers.contains_peer(&peer));
+
+        // can't be re-added during the window
+        peers.add_peer(peer, addr, None);
+        assert!(!peers.contains_peer(&peer));
+        peers.add_and_connect(peer, addr, None);
+        assert!(!peers.contains_peer(&peer));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        peers.add_peer(peer, addr, None);
+        assert!(peers.contains_peer(&peer));
+        assert!(peers.forgotten_peers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_forget_trusted_peer() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let peer = PeerId::random();
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        peers.add_trusted_peer(peer, addr);
+        peers.forget_peer(&peer, Duration::from_secs(60));
+
+        assert!(peers.contains_peer(&peer));
+        assert!(peers.forgotten_peers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_discovered_peer_reputation() {
+        let config = PeersConfig::test().with_discovered_peer_reputation(-42);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        let discovered = PeerId::random();
+        peers.add_peer(discovered, addr, None);
+        assert_eq!(peers.peers[&discovered].reputation, -42);
+
+        let static_peer = PeerId::random();
+        peers.add_peer_kind(static_peer, Some(PeerKind::Static), addr, None);
+        assert_eq!(peers.peers[&static_peer].reputation, DEFAULT_REPUTATION);
+    }
+
+    #[tokio::test]
+    async fn test_plan_drain() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        let mut add = |kind, reputation, state| {
+            let peer_id = PeerId::random();
+            peers.add_peer_kind(peer_id, Some(kind), addr, None);
+            let peer = peers.peers.get_mut(&peer_id).unwrap();
+            peer.reputation = reputation;
+            peer.state = state;
+            peer_id
+        };
+        let trusted = add(PeerKind::Trusted, -3000, PeerConnectionState::Out);
+        let static_peer = add(PeerKind::Static, -3000, PeerConnectionState::In);
+        let worst = add(PeerKind::Basic, -2000, PeerConnectionState::In);
+        let bad = add(PeerKind::Basic, -1000, PeerConnectionState::Out);
+        let good = add(PeerKind::Basic, 1000, PeerConnectionState::Out);
+        let _idle = add(PeerKind::Basic, -5000, PeerConnectionState::Idle);
+
+        assert!(peers.plan_drain(5).is_empty());
+        assert!(peers.plan_drain(10).is_empty());
+        assert_eq!(peers.plan_drain(3), vec![worst, bad]);
+        // protected peers are never drained, even if the target can't be reached
+        assert_eq!(peers.plan_drain(0), vec![worst, bad, good]);
+
+        assert_eq!(peers.drain(4), vec![worst]);
+        assert!(peers.peers[&worst].remove_after_disconnect);
+        assert_eq!(peers.peers[&worst].state, PeerConnectionState::DisconnectingIn);
+        assert!(!peers.peers[&bad].remove_after_disconnect);
+        assert!(peers.peers.contains_key(&trusted));
+        assert!(peers.peers.contains_key(&static_peer));
+    }
+
+    #[tokio::test]
+    async fn test_trusted_peer_warnings() {
+        let config = PeersConfig::test().with_quarantine_threshold(-100);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        let healthy = PeerId::random();
+        let flaky = PeerId::random();
+        let very_flaky = PeerId::random();
+        let basic = PeerId::random();
+        for peer_id in [healthy, flaky, very_flaky] {
+            peers.add_trusted_peer(peer_id, addr);
+        }
+        peers.add_peer(basic, addr, None);
+        peers.peers.get_mut(&flaky).unwrap().reputation = -101;
+        peers.peers.get_mut(&very_flaky).unwrap().reputation = BANNED_REPUTATION * 2;
+        peers.peers.get_mut(&basic).unwrap().reputation = -101;
+
+        assert_eq!(
+            peers.trusted_peer_warnings(),
+            vec![(very_flaky, BANNED_REPUTATION * 2), (flaky, -101)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_after_max_dial_attempts() {
+        let config = PeersConfig::test().with_max_dial_attempts(2).with_max_backoff_count(10);
+        let mut peers = PeersManager::new(config);
+        let peer = PeerId::random();
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        peers.add_peer(peer, PeerAddr::from_tcp(socket_addr), None);
+
+        let err = PendingSessionHandshakeError::Timeout;
+        for attempt in 1..=3 {
+            assert!(!peers.peers[&peer].is_unreachable(), "attempt {attempt}");
+            peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::PendingOut;
+            peers.connection_info.inc_pending_out();
+            peers.on_outgoing_pending_session_dropped(&socket_addr, &peer, &err);
+            // release the backoff so the peer would be dialed again
+            peers.peers.get_mut(&peer).unwrap().backed_off = false;
+        }
+
+        // kept in the table but no longer dialed
+        assert_eq!(peers.peers[&peer].severe_backoff_counter, 3);
+        assert!(peers.peers[&peer].is_unreachable());
+        assert!(peers.best_unconnected(false).is_none());
+
+        // a successful connection clears the flag
+        peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::PendingOut;
+        peers.connection_info.inc_pending_out();
+        peers.on_active_outgoing_established(peer);
+        assert!(!peers.peers[&peer].is_unreachable());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_peer_retried_and_removed() {
+        // default `max_dial_attempts` and `max_backoff_count`
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let peer = PeerId::random();
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        peers.add_peer(peer, PeerAddr::from_tcp(socket_addr), None);
+
+        let err = PendingSessionHandshakeError::Timeout;
+        let mut failures = 0;
+        while peers.contains_peer(&peer) {
+            let entry = peers.peers.get_mut(&peer).unwrap();
+            if !entry.state.is_pending_out() {
+                entry.state = PeerConnectionState::PendingOut;
+                peers.connection_info.inc_pending_out();
+            }
+            peers.on_outgoing_pending_session_dropped(&socket_addr, &peer, &err);
+            failures += 1;
+            let Some(entry) = peers.peers.get(&peer) else { break };
+            assert_eq!(entry.is_unreachable(), failures > peers.max_dial_attempts);
+
+            // expire the backoff, which gives the peer another attempt
+            peers
+                .backed_off_peers
+                .insert(peer, std::time::Instant::now() - Duration::from_millis(1));
+            peers.release_interval.reset_immediately();
+            poll_fn(|cx| loop {
+                if peers.poll(cx).is_pending() {
+                    return Poll::Ready(());
+                }
+            })
+            .await;
+            assert!(!peers.peers[&peer].is_unreachable());
+        }
+
+        assert_eq!(failures, peers.max_backoff_count + 1);
+    }
+
+    #[tokio::test]
+    async fn test_evict_least_recently_seen_discovered_peer() {
+        let config = PeersConfig::test().with_max_discovered_peers(2);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let now = std::time::Instant::now();
+
+        let oldest = PeerId::random();
+        let newer = PeerId::random();
+        let connected = PeerId::random();
+        let trusted = PeerId::random();
+        peers.add_peer(connected, addr, None);
+        peers.peers.get_mut(&connected).unwrap().state = PeerConnectionState::Out;
+        peers.add_trusted_peer(trusted, addr);
+        peers.add_peer(oldest, addr, None);
+        peers.add_peer(newer, addr, None);
+        for (peer_id, age) in [(oldest, 40), (newer, 30), (connected, 50), (trusted, 60)] {
+            peers.peers.get_mut(&peer_id).unwrap().last_seen = now - Duration::from_secs(age);
+        }
+
+        let added = PeerId::random();
+        peers.add_peer(added, addr, None);
+
+        assert!(!peers.contains_peer(&oldest));
+        for peer_id in [newer, connected, trusted, added] {
+            assert!(peers.contains_peer(&peer_id));
+        }
+
+        // rediscovering a peer refreshes it
+        peers.add_peer(newer, addr, None);
+        let added_later = PeerId::random();
+        peers.add_peer(added_later, addr, None);
+        assert!(!peers.contains_peer(&added));
+        assert!(peers.contains_peer(&newer));
+        assert!(peers.contains_peer(&added_later));
+    }
+
+    #[tokio::test]
+    async fn test_evict_discovered_peers_in_batches() {
+        let config = PeersConfig::test().with_max_discovered_peers(10);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        // a previously connected peer that is idle again is kept
+        let reconnected = PeerId::random();
+        peers.add_peer(reconnected, addr, None);
+        peers.peers.get_mut(&reconnected).unwrap().state = PeerConnectionState::PendingOut;
+        peers.connection_info.inc_pending_out();
+        peers.on_active_outgoing_established(reconnected);
+        peers.on_active_session_gracefully_closed(reconnected);
+        assert!(peers.peers[&reconnected].state.is_unconnected());
+        peers.peers.get_mut(&reconnected).unwrap().last_seen =
+            std::time::Instant::now() - Duration::from_secs(60);
+
+        for _ in 0..10 {
+            peers.add_peer(PeerId::random(), addr, None);
+        }
+        assert_eq!(peers.peers.len(), 11);
+
+        // exceeding the limit evicts down to the low water mark
+        peers.add_peer(PeerId::random(), addr, None);
+        assert_eq!(peers.peers.len(), 10);
+        assert!(peers.contains_peer(&reconnected));
+
+        // the freed up room is used before the peer set is scanned again
+        assert_eq!(peers.discovered_until_eviction_check, 1);
+    }
+
+    #[tokio::test]
+    async fn test_decay_all_unbans_peers() {
+        let config = PeersConfig::test()
+            .with_reputation_decay_per_second(1024)
+            .with_reputation_decay_min_age(Duration::ZERO);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        let banned = [PeerId::random(), PeerId::random(), PeerId::random()];
+        let deeply_banned = PeerId::random();
+        let healthy = PeerId::random();
+        for peer_id in banned.into_iter().chain([deeply_banned, healthy]) {
+            peers.add_peer(peer_id, addr, None);
+        }
+        for peer_id in banned {
+            peers.apply_reputation_change(
+                &peer_id,
+                ReputationChangeKind::Other(BANNED_REPUTATION - 1024),
+            );
+        }
+        peers.apply_reputation_change(&deeply_banned, ReputationChangeKind::BadProtocol);
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr);
+        peers.peers.get_mut(&trusted).unwrap().reputation = BANNED_REPUTATION - 1024;
+        for peer_id in banned.into_iter().chain([deeply_banned]) {
+            assert!(peers.ban_list.is_banned_peer(&peer_id));
+        }
+
+        let now = std::time::Instant::now() + Duration::from_secs(10);
+        let mut unbanned = peers.decay_all(now);
+        unbanned.sort_unstable();
+        let mut expected = banned.to_vec();
+        expected.sort_unstable();
+        assert_eq!(unbanned, expected);
+        for peer_id in banned {
+            assert!(!peers.ban_list.is_banned_peer(&peer_id));
+        }
+        assert!(peers.ban_list.is_banned_peer(&deeply_banned));
+        // trusted peers don't decay
+        assert_eq!(peers.peers[&trusted].reputation, BANNED_REPUTATION - 1024);
+
+        // nothing left to unban
+        assert!(peers.decay_all(now + Duration::from_secs(10)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_protect_static() {
+        let err = PendingSessionHandshakeError::Eth(EthStreamError::EthHandshakeError(
+            EthHandshakeError::NonStatusMessageInHandshake,
+        ));
+        assert!(err.is_fatal_protocol_error());
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+
+        for protect_static in [false, true] {
+            let mut peers =
+                PeersManager::new(PeersConfig::test().with_protect_static(protect_static));
+            let peer = PeerId::random();
+            peers.add_peer_kind(
+                peer,
+                Some(PeerKind::Static),
+                PeerAddr::from_tcp(socket_addr),
+                None,
+            );
+            peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::PendingOut;
+            peers.connection_info.inc_pending_out();
+
+            peers.on_outgoing_pending_session_dropped(&socket_addr, &peer, &err);
+
+            assert!(peers.ban_list.is_banned_peer(&peer));
+            assert_eq!(peers.peers.contains_key(&peer), protect_static);
+            let removed = peers
+                .queued_actions
+                .iter()
+                .any(|action| matches!(action, PeerAction::PeerRemoved(id) if *id == peer));
+            assert_eq!(removed, !protect_static);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extend_from_records() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let record = |id: PeerId, address: IpAddr, tcp_port: u16| NodeRecord {
+            address,
+            tcp_port,
+            udp_port: tcp_port,
+            id,
+        };
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        let known = PeerId::random();
+        let known_addr = PeerAddr::from_tcp(SocketAddr::new(localhost, 30303));
+        peers.add_peer(known, known_addr, None);
+
+        let new = [PeerId::random(), PeerId::random()];
+        let added = peers.extend_from_records([
+            record(new[0], localhost, 30304),
+            record(new[1], IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 30303),
+            // duplicates of a known and a new peer
+            record(known, localhost, 40404),
+            record(new[0], localhost, 40404),
+            // malformed
+            record(PeerId::random(), IpAddr::V4(Ipv4Addr::UNSPECIFIED), 30303),
+            record(PeerId::random(), localhost, 0),
+        ]);
+
+        assert_eq!(added, 2);
+        assert_eq!(peers.peers.len(), 3);
+        for peer_id in new {
+            assert_eq!(peers.peers[&peer_id].kind, PeerKind::Basic);
+        }
+        // known peers are not updated
+        assert_eq!(peers.peers[&known].addr, known_addr);
+        assert_eq!(peers.peers[&new[0]].addr.tcp().port(), 30304);
+
+        assert_eq!(peers.extend_from_records([record(known, localhost, 30303)]), 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_metrics() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let add = |peers: &mut PeersManager, kind| {
+            let peer_id = PeerId::random();
+            peers.add_peer_kind(peer_id, Some(kind), addr, None);
+            peer_id
+        };
+
+        add(&mut peers, PeerKind::Basic);
+        add(&mut peers, PeerKind::Basic);
+        let connected_basic = add(&mut peers, PeerKind::Basic);
+        peers.peers.get_mut(&connected_basic).unwrap().state = PeerConnectionState::In;
+        let banned = add(&mut peers, PeerKind::Basic);
+        peers.apply_reputation_change(&banned, ReputationChangeKind::BadProtocol);
+        let backed_off = add(&mut peers, PeerKind::Basic);
+        peers.peers.get_mut(&backed_off).unwrap().backed_off = true;
+        let trusted = add(&mut peers, PeerKind::Trusted);
+        peers.peers.get_mut(&trusted).unwrap().state = PeerConnectionState::Out;
+        add(&mut peers, PeerKind::Static);
+        let pending_static = add(&mut peers, PeerKind::Static);
+        peers.peers.get_mut(&pending_static).unwrap().state = PeerConnectionState::PendingOut;
+
+        assert_eq!(
+            peers.snapshot_metrics(),
+            PeersMetricsSnapshot {
+                total: 8,
+                connected: 2,
+                banned: 1,
+                backed_off: 1,
+                trusted: 1,
+                static_peers: 2,
+                discovered_only: 4,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_already_connected_penalty() {
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+
+        for weight in [0, -1024] {
+            let reputation_weights =
+                ReputationChangeWeights { already_connected: weight, ..Default::default() };
+            let mut peers =
+                PeersManager::new(PeersConfig { reputation_weights, ..PeersConfig::test() });
+            let peer = PeerId::random();
+            assert!(peers.on_incoming_pending_session(socket_addr.ip()).is_ok());
+            peers.on_incoming_session_established(peer, socket_addr);
+            let before = peers.peers[&peer].reputation;
+
+            assert!(peers.on_incoming_pending_session(socket_addr.ip()).is_ok());
+            peers.on_already_connected(peer, Direction::Incoming);
+            assert_eq!(peers.peers[&peer].reputation, before + weight);
+            assert_eq!(peers.connection_info.num_pending_in, 0);
+
+            // simultaneous dials are not the remote's fault
+            peers.on_already_connected(peer, Direction::Outgoing(peer));
+            assert_eq!(peers.peers[&peer].reputation, before + weight);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reevaluate_ban_state() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let mut ids = [PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random()];
+        ids.sort_unstable();
+        let [idle, connected, healthy, far_below] = ids;
+        for peer_id in ids {
+            peers.add_peer(peer_id, addr, None);
+        }
+        for peer_id in [idle, connected] {
+            peers.peers.get_mut(&peer_id).unwrap().reputation = -2000;
+        }
+        peers.peers.get_mut(&connected).unwrap().state = PeerConnectionState::Out;
+        peers.connection_info.inc_out();
+        peers.apply_reputation_change(&far_below, ReputationChangeKind::BadProtocol);
+        assert!(peers.ban_list.is_banned_peer(&far_below));
+
+        // a stricter threshold bans the affected peers
+        let strict = PeersConfig::test().with_ban_threshold(-1500).with_unban_margin(500);
+        assert_eq!(
+            peers.reevaluate_ban_state(&strict),
+            vec![
+                (idle, ReputationChangeOutcome::Ban),
+                (connected, ReputationChangeOutcome::DisconnectAndBan),
+            ]
+        );
+        assert!(peers.ban_list.is_banned_peer(&idle));
+        assert!(peers.ban_list.is_banned_peer(&connected));
+        assert!(!peers.ban_list.is_banned_peer(&healthy));
+        assert_eq!(peers.peers[&connected].state, PeerConnectionState::DisconnectingOut);
+
+        // nothing changes if the threshold stays the same
+        assert!(peers.reevaluate_ban_state(&strict).is_empty());
+
+        // relaxing the threshold only unbans peers that clear the margin
+        let relaxed = PeersConfig::test().with_ban_threshold(-2300).with_unban_margin(500);
+        assert!(peers.reevaluate_ban_state(&relaxed).is_empty());
+        let relaxed = relaxed.with_ban_threshold(-2500);
+        assert_eq!(
+            peers.reevaluate_ban_state(&relaxed),
+            vec![
+                (idle, ReputationChangeOutcome::Unban),
+                (connected, ReputationChangeOutcome::Unban)
+            ]
+        );
+        assert!(!peers.ban_list.is_banned_peer(&idle));
+        assert!(peers.ban_list.is_banned_peer(&far_below));
+    }
+
+    #[tokio::test]
+    async fn test_peer_iteration_callbacks() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let ids = (0..10).map(|_| PeerId::random()).collect::<HashSet<_>>();
+        for (i, peer_id) in ids.iter().enumerate() {
+            peers.add_peer(*peer_id, addr, None);
+            peers.peers.get_mut(peer_id).unwrap().reputation = i as i32;
+        }
+
+        let mut visits = HashMap::<PeerId, usize>::new();
+        peers.for_each_peer(|peer_id, _| *visits.entry(*peer_id).or_default() += 1);
+        assert_eq!(visits.keys().copied().collect::<HashSet<_>>(), ids);
+        assert!(visits.values().all(|count| *count == 1));
+
+        let sum = peers.try_fold_peers(0, |sum, _, peer| Ok::<_, ()>(sum + peer.reputation));
+        assert_eq!(sum, Ok((0..10).sum()));
+
+        // stops at the first error
+        let mut visited = 0;
+        let res = peers.try_fold_peers((), |(), _, _| {
+            visited += 1;
+            if visited == 3 {
+                return Err(visited)
+            }
+            Ok(())
+        });
+        assert_eq!(res, Err(3));
+        assert_eq!(visited, 3);
+    }
+
+    #[tokio::test]
+    async fn test_apply_external_scores() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let mut ids = [PeerId::random(), PeerId::random(), PeerId::random()];
+        ids.sort_unstable();
+        let [idle, connected, good] = ids;
+        for peer_id in ids {
+            peers.add_peer(peer_id, addr, None);
+        }
+        peers.peers.get_mut(&connected).unwrap().state = PeerConnectionState::Out;
+        peers.connection_info.inc_out();
+        let unknown = PeerId::random();
+
+        let transitions = peers.apply_external_scores([
+            (good, i32::MAX),
+            (idle, BANNED_REPUTATION - 1),
+            (unknown, BANNED_REPUTATION - 1),
+            (connected, BANNED_REPUTATION - 1),
+        ]);
+        assert_eq!(
+            transitions,
+            vec![
+                (idle, ReputationChangeOutcome::Ban),
+                (connected, ReputationChangeOutcome::DisconnectAndBan),
+            ]
+        );
+        assert_eq!(peers.peers[&good].reputation, MAX_REPUTATION);
+        assert!(peers.ban_list.is_banned_peer(&idle));
+        assert!(peers.ban_list.is_banned_peer(&connected));
+        assert_eq!(peers.peers[&connected].state, PeerConnectionState::DisconnectingOut);
+        assert!(!peers.peers.contains_key(&unknown));
+
+        // a score within the unban margin keeps the peer banned
+        assert!(peers.apply_external_scores([(idle, BANNED_REPUTATION + 1)]).is_empty());
+        assert!(peers.ban_list.is_banned_peer(&idle));
+
+        assert_eq!(
+            peers.apply_external_scores([(idle, 0)]),
+            vec![(idle, ReputationChangeOutcome::Unban)]
+        );
+        assert!(!peers.ban_list.is_banned_peer(&idle));
+    }
+
+    #[tokio::test]
+    async fn test_reserved_trusted_slots() {
+        let config = PeersConfig::test().with_max_outbound(3).with_reserved_trusted_slots(1);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        for _ in 0..5 {
+            peers.add_peer(PeerId::random(), addr, None);
+        }
+
+        let connects = |peers: &PeersManager| {
+            peers
+                .queued_actions
+                .iter()
+                .filter_map(|action| match action {
+                    PeerAction::Connect { peer_id, .. } => Some(*peer_id),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // basic peers leave the reserved slot free
+        peers.fill_outbound_slots();
+        assert_eq!(connects(&peers).len(), 2);
+        assert_eq!(peers.connection_info.num_pending_out, 2);
+
+        peers.queued_actions.clear();
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr);
+        peers.fill_outbound_slots();
+        assert_eq!(connects(&peers), vec![trusted]);
+    }
+
+    #[tokio::test]
+    async fn test_reserved_trusted_slots_eviction() {
+        let config = PeersConfig::test().with_max_outbound(2).with_reserved_trusted_slots(1);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+
+        // basic peers occupy all outbound slots, including the reserved one
+        let (worse, better) = (PeerId::random(), PeerId::random());
+        for (peer_id, reputation) in [(worse, -100), (better, 100)] {
+            peers.add_peer(peer_id, addr, None);
+            let peer = peers.peers.get_mut(&peer_id).unwrap();
+            peer.state = PeerConnectionState::Out;
+            peer.reputation = reputation;
+            peers.connection_info.inc_out();
+        }
+        peers.queued_actions.clear();
+
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr);
+        peers.queued_actions.clear();
+        peers.fill_outbound_slots();
+        assert!(matches!(
+            peers.queued_actions.as_slices().0,
+            [PeerAction::Disconnect { peer_id, reason: Some(DisconnectReason::TooManyPeers) }]
+                if *peer_id == worse
+        ));
+        assert_eq!(peers.peers[&worse].state, PeerConnectionState::DisconnectingOut);
+        assert_eq!(peers.peers[&better].state, PeerConnectionState::Out);
+
+        // the pending disconnect is accounted for, no further peers are evicted
+        peers.queued_actions.clear();
+        peers.fill_outbound_slots();
+        assert!(peers.queued_actions.is_empty());
+
+        // once the session is closed the trusted peer takes the freed slot
+        peers.on_active_session_gracefully_closed(worse);
+        assert!(peers.queued_actions.iter().any(
+            |action| matches!(action, PeerAction::Connect { peer_id, .. } if *peer_id == trusted)
+        ));
+        assert_eq!(peers.peers[&trusted].state, PeerConnectionState::PendingOut);
+    }
+
+    #[tokio::test]
+    async fn test_reserved_trusted_slots_no_churn_for_failing_trusted_peer() {
+        let config = PeersConfig::test().with_max_outbound(2).with_reserved_trusted_slots(1);
+        let mut peers = PeersManager::new(config);
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let addr = PeerAddr::from_tcp(socket_addr);
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr);
+
+        let has_disconnect = |peers: &PeersManager| {
+            peers
+                .queued_actions
+                .iter()
+                .any(|action| matches!(action, PeerAction::Disconnect { .. }))
+        };
+        let err = PendingSessionHandshakeError::Timeout;
+        for _ in 0..3 {
+            // basic peers occupy all outbound slots, including the reserved one
+            for _ in peers.connection_info.num_outbound..2 {
+                let peer_id = PeerId::random();
+                peers.add_peer(peer_id, addr, None);
+                peers.peers.get_mut(&peer_id).unwrap().state = PeerConnectionState::Out;
+                peers.connection_info.inc_out();
+            }
+
+            // the trusted peer keeps failing to connect
+            peers.peers.get_mut(&trusted).unwrap().state = PeerConnectionState::PendingOut;
+            peers.connection_info.inc_pending_out();
+            peers.queued_actions.clear();
+            peers.on_outgoing_pending_session_dropped(&socket_addr, &trusted, &err);
+            assert!(!has_disconnect(&peers));
+
+            // and is dialable again once its backoff expired
+            peers.peers.get_mut(&trusted).unwrap().backed_off = false;
+            peers.queued_actions.clear();
+            peers.fill_outbound_slots();
+            assert!(!has_disconnect(&peers));
+        }
+        assert_eq!(peers.connection_info.num_outbound, 2);
+    }
+
+    #[tokio::test]
+    async fn test_probation_after_unban() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (fresh, unbanned) = (PeerId::random(), PeerId::random());
+        for peer_id in [fresh, unbanned] {
+            peers.add_peer(peer_id, addr, None);
+            peers.peers.get_mut(&peer_id).unwrap().reputation = DEFAULT_REPUTATION;
+        }
+
+        // ban and unban the peer, which puts it on probation
+        assert_eq!(
+            peers.apply_external_scores([(unbanned, BANNED_REPUTATION - 1)]),
+            vec![(unbanned, ReputationChangeOutcome::Ban)]
+        );
+        assert_eq!(
+            peers.apply_external_scores([(unbanned, DEFAULT_REPUTATION)]),
+            vec![(unbanned, ReputationChangeOutcome::Unban)]
+        );
+        assert!(peers.peers[&unbanned].is_on_probation(std::time::Instant::now()));
+        assert!(!peers.peers[&fresh].is_on_probation(std::time::Instant::now()));
+
+        // the same misbehavior bans the peer on probation sooner
+        let mut offenses = HashMap::new();
+        for peer_id in [fresh, unbanned] {
+            let mut count = 0;
+            while !peers.peers[&peer_id].is_banned() {
+                peers.apply_reputation_change(&peer_id, ReputationChangeKind::BadMessage);
+                count += 1;
+            }
+            offenses.insert(peer_id, count);
+        }
+        assert!(offenses[&unbanned] < offenses[&fresh]);
+
+        // penalties are no longer amplified after the probation window
+        peers.peers.get_mut(&unbanned).unwrap().probation_until =
+            Some(std::time::Instant::now() - Duration::from_secs(1));
+        peers.peers.get_mut(&unbanned).unwrap().reputation = DEFAULT_REPUTATION;
+        peers.apply_reputation_change(&unbanned, ReputationChangeKind::BadMessage);
+        peers.peers.get_mut(&fresh).unwrap().reputation = DEFAULT_REPUTATION;
+        peers.apply_reputation_change(&fresh, ReputationChangeKind::BadMessage);
+        assert_eq!(peers.peers[&unbanned].reputation, peers.peers[&fresh].reputation);
+    }
+
+    #[tokio::test]
+    async fn test_subnet_distribution() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let ips = [
+            "10.0.1.1",
+            "10.0.1.2",
+            "10.0.1.3",
+            "10.0.2.1",
+            "192.168.0.1",
+            "2001:db8::1",
+            "2001:db8::2",
+            "2001:db9::1",
+        ];
+        for ip in ips {
+            let addr = PeerAddr::from_tcp(SocketAddr::new(ip.parse().unwrap(), 30303));
+            peers.add_peer(PeerId::random(), addr, None);
+        }
+        let subnet = |ip: &str, bits| IpSubnet::new(ip.parse().unwrap(), bits, bits);
+
+        let distribution = peers.subnet_distribution(24, 32);
+        assert_eq!(distribution.len(), 5);
+        assert_eq!(distribution[&subnet("10.0.1.0", 24)], 3);
+        assert_eq!(distribution[&subnet("10.0.2.0", 24)], 1);
+        assert_eq!(distribution[&subnet("192.168.0.0", 24)], 1);
+        assert_eq!(distribution[&subnet("2001:db8::", 32)], 2);
+        assert_eq!(distribution[&subnet("2001:db9::", 32)], 1);
+
+        // coarser groups concentrate the peers
+        let distribution = peers.subnet_distribution(8, 16);
+        assert_eq!(distribution.len(), 3);
+        assert_eq!(distribution[&subnet("10.0.0.0", 8)], 4);
+        assert_eq!(distribution[&subnet("192.0.0.0", 8)], 1);
+        assert_eq!(distribution[&subnet("2001::", 16)], 3);
+        assert_eq!(subnet("10.0.1.7", 8).to_string(), "10.0.0.0/8");
+    }
+
+    #[tokio::test]
+    async fn test_reputation_frozen() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (peer, banned) = (PeerId::random(), PeerId::random());
+        peers.add_peer(peer, addr, None);
+        peers.add_peer(banned, addr, None);
+        peers.apply_reputation_change(&banned, ReputationChangeKind::BadProtocol);
+        assert!(peers.ban_list.is_banned_peer(&banned));
+        let reputation = peers.peers[&peer].reputation;
+
+        peers.set_reputation_frozen(true);
+        assert!(peers.is_reputation_frozen());
+        peers.apply_reputation_change(&peer, ReputationChangeKind::BadProtocol);
+        peers.apply_reputation_change(&peer, ReputationChangeKind::BadMessage);
+        assert_eq!(peers.peers[&peer].reputation, reputation);
+        assert!(!peers.ban_list.is_banned_peer(&peer));
+        // existing bans are untouched
+        assert!(peers.ban_list.is_banned_peer(&banned));
+
+        peers.set_reputation_frozen(false);
+        peers.apply_reputation_change(&peer, ReputationChangeKind::BadProtocol);
+        assert!(peers.peers[&peer].is_banned());
+        assert!(peers.ban_list.is_banned_peer(&peer));
+    }
+
+    #[tokio::test]
+    async fn test_reputation_frozen_on_failed_handshake() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let peer = PeerId::random();
+        peers.add_peer(peer, PeerAddr::from_tcp(socket_addr), None);
+        let reputation = peers.peers[&peer].reputation;
+
+        // not a backoff error, so it's penalized with `FailedToConnect`
+        let err = PendingSessionHandshakeError::Eth(EthStreamError::InvalidMessage(
+            reth_eth_wire::message::MessageError::Invalid(
+                reth_eth_wire::EthVersion::Eth68,
+                reth_eth_wire::EthMessageID::Status,
+            ),
+        ));
+        assert!(err.should_backoff().is_none() && !err.is_fatal_protocol_error());
+
+        peers.set_reputation_frozen(true);
+        for _ in 0..3 {
+            peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::PendingOut;
+            peers.connection_info.inc_pending_out();
+            peers.on_outgoing_pending_session_dropped(&socket_addr, &peer, &err);
+        }
+        assert_eq!(peers.peers[&peer].reputation, reputation);
+        assert!(!peers.ban_list.is_banned_peer(&peer));
+
+        peers.set_reputation_frozen(false);
+        peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::PendingOut;
+        peers.connection_info.inc_pending_out();
+        peers.on_outgoing_pending_session_dropped(&socket_addr, &peer, &err);
+        assert!(peers.peers[&peer].reputation < reputation);
+    }
+
+    #[tokio::test]
+    async fn test_find_id_conflicts() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = |port| PeerAddr::from_tcp(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port));
+        let (peer, other) = (PeerId::random(), PeerId::random());
+        peers.add_peer(peer, addr(30303), None);
+        peers.add_peer(other, addr(30303), None);
+
+        // an address change replaces the previous address
+        peers.add_peer(peer, addr(30304), None);
+        assert_eq!(peers.peers[&peer].addr, addr(30304));
+        assert!(peers.find_id_conflicts().is_empty());
+
+        // a conflicting entry for the same id is reported
+        let entries = peers
+            .peers
+            .iter()
+            .map(|(peer_id, peer)| (*peer_id, peer.addr))
+            .chain([(peer, addr(30303)), (other, addr(30303))]);
+        assert_eq!(find_id_conflicts(entries), vec![(peer, vec![addr(30304), addr(30303)])]);
+    }
+
+    #[tokio::test]
+    async fn test_select_dial_candidates() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (trusted, static_peer, good, poor, banned) = (
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+            PeerId::random(),
+        );
+        peers.add_trusted_peer(trusted, addr);
+        peers.add_peer_kind(static_peer, Some(PeerKind::Static), addr, None);
+        for peer_id in [good, poor, banned] {
+            peers.add_peer(peer_id, addr, None);
+        }
+        for (peer_id, reputation) in [
+            (trusted, BANNED_REPUTATION / 2),
+            (static_peer, -100),
+            (good, MAX_REPUTATION),
+            (poor, -200),
+            (banned, BANNED_REPUTATION - 1),
+        ] {
+            peers.peers.get_mut(&peer_id).unwrap().reputation = reputation;
+        }
+
+        // the low reputation trusted peer comes before the high reputation basic peer
+        assert_eq!(peers.select_dial_candidates(10), vec![trusted, static_peer, good, poor]);
+        assert_eq!(peers.select_dial_candidates(2), vec![trusted, static_peer]);
+
+        peers.peers.get_mut(&trusted).unwrap().state = PeerConnectionState::PendingOut;
+        peers.connection_info.inc_pending_out();
+        assert_eq!(peers.select_dial_candidates(10), vec![static_peer, good, poor]);
+    }
+
+    #[tokio::test]
+    async fn test_peers_by_fork() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let old_fork = ForkId { hash: ForkHash([0xaa, 0xbb, 0xcc, 0xdd]), next: 100 };
+        let new_fork = ForkId { hash: ForkHash([0x11, 0x22, 0x33, 0x44]), next: 0 };
+
+        let mut add = |fork_id: Option<ForkId>, state| {
+            let peer_id = PeerId::random();
+            peers.add_peer(peer_id, addr, fork_id);
+            peers.peers.get_mut(&peer_id).unwrap().state = state;
+            match state {
+                PeerConnectionState::In => peers.connection_info.inc_in(),
+                PeerConnectionState::Out => peers.connection_info.inc_out(),
+                _ => {}
+            }
+            peer_id
+        };
+        let mut old = vec![
+            add(Some(old_fork), PeerConnectionState::Out),
+            add(Some(old_fork), PeerConnectionState::In),
+        ];
+        let new = vec![add(Some(new_fork), PeerConnectionState::Out)];
+        let mut unknown =
+            vec![add(None, PeerConnectionState::In), add(None, PeerConnectionState::Out)];
+        // peers without an active session are not counted
+        add(Some(new_fork), PeerConnectionState::Idle);
+        old.sort_unstable();
+        unknown.sort_unstable();
+
+        let buckets = peers.peers_by_fork();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[&Some(old_fork)], old);
+        assert_eq!(buckets[&Some(new_fork)], new);
+        assert_eq!(buckets[&None], unknown);
+    }
+
+    #[tokio::test]
+    async fn test_penalty_escalation() {
+        let reputation_weights =
+            ReputationChangeWeights { escalation_percent: 50, ..Default::default() };
+        let mut peers =
+            PeersManager::new(PeersConfig::test().with_reputation_weights(reputation_weights));
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let peer_id = PeerId::random();
+        peers.add_peer(peer_id, addr, None);
+
+        let penalize = |peers: &mut PeersManager, kind| {
+            let before = peers.peers[&peer_id].reputation;
+            peers.apply_reputation_change(&peer_id, kind);
+            peers.peers[&peer_id].reputation - before
+        };
+        let weights = ReputationChangeWeights::default();
+
+        // interleaved kinds don't escalate each other
+        assert_eq!(penalize(&mut peers, ReputationChangeKind::Timeout), weights.timeout);
+        assert_eq!(penalize(&mut peers, ReputationChangeKind::Dropped), weights.dropped);
+        assert_eq!(
+            penalize(&mut peers, ReputationChangeKind::BadAnnouncement),
+            weights.bad_announcement
+        );
+
+        // repeated penalties of the same kind accelerate the drop
+        assert_eq!(penalize(&mut peers, ReputationChangeKind::Timeout), weights.timeout * 3 / 2);
+        assert_eq!(penalize(&mut peers, ReputationChangeKind::Timeout), weights.timeout * 2);
+
+        // outside the window the escalation starts over
+        let (_, last) = peers
+            .peers
+            .get_mut(&peer_id)
+            .unwrap()
+            .recent_penalties
+            .get_mut(&ReputationChangeKind::Timeout)
+            .unwrap();
+        *last -= weights.escalation_window + Duration::from_secs(1);
+        assert_eq!(penalize(&mut peers, ReputationChangeKind::Timeout), weights.timeout);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_persisted() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (basic, trusted, banned) = (PeerId::random(), PeerId::random(), PeerId::random());
+        peers.add_peer(basic, addr, None);
+        peers.add_trusted_peer(trusted, addr);
+        peers.add_peer(banned, addr, None);
+        peers.apply_reputation_change(&banned, ReputationChangeKind::BadProtocol);
+
+        let snapshot = peers.snapshot_persisted();
+        assert_eq!(snapshot.len(), peers.peers.len());
+        for info in &snapshot {
+            let peer = &peers.peers[&info.record.id];
+            assert_eq!(info.record.address, peer.addr.tcp().ip());
+            assert_eq!(info.record.tcp_port, peer.addr.tcp().port());
+            assert_eq!(info.kind, peer.kind);
+            assert_eq!(info.reputation, peer.reputation);
+            assert_eq!(info.last_ban_reason, peer.last_ban_reason);
+        }
+        // banned peers are included, unlike in the persisted peers
+        assert!(snapshot.iter().any(|info| info.record.id == banned));
+        assert_eq!(peers.persistable_peers().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_observer_excluded_from_cap() {
+        let config = PeersConfig::test().with_max_outbound(1).with_max_inbound(1);
+        let mut peers = PeersManager::new(config);
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let addr = PeerAddr::from_tcp(socket_addr);
+        let (observer, basic) = (PeerId::random(), PeerId::random());
+        peers.add_peer_kind(observer, Some(PeerKind::Observer), addr, None);
+        peers.add_peer(basic, addr, None);
+
+        // the observer is dialed in addition to the single outbound slot
+        peers.fill_outbound_slots();
+        let connects = peers
+            .queued_actions
+            .iter()
+            .filter(|action| matches!(action, PeerAction::Connect { .. }))
+            .count();
+        assert_eq!(connects, 2);
+        assert_eq!(peers.connection_info.num_pending_out, 1);
+
+        peers.on_active_outgoing_established(observer);
+        peers.on_active_outgoing_established(basic);
+        assert_eq!(peers.peers[&observer].state, PeerConnectionState::Out);
+        assert_eq!(peers.connection_info.num_outbound, 1);
+        assert_eq!(peers.connection_info.num_pending_out, 0);
+
+        // an inbound observer doesn't take the inbound slot
+        let inbound_observer = PeerId::random();
+        peers.add_peer_kind(inbound_observer, Some(PeerKind::Observer), addr, None);
+        peers.connection_info.inc_in();
+        peers.queued_actions.clear();
+        peers.connection_info.inc_pending_in();
+        peers.on_incoming_session_established(inbound_observer, socket_addr);
+        assert_eq!(peers.peers[&inbound_observer].state, PeerConnectionState::In);
+        assert_eq!(peers.connection_info.num_inbound, 1);
+        assert!(peers.queued_actions.is_empty());
+
+        // the observer is counted once it's promoted to a regular peer
+        peers.add_peer_kind(observer, Some(PeerKind::Basic), addr, None);
+        assert_eq!(peers.connection_info.num_outbound, 2);
+        peers.on_active_session_gracefully_closed(observer);
+        assert_eq!(peers.connection_info.num_outbound, 1);
+    }
+
+    #[tokio::test]
+    async fn test_observer_not_routable() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (observer, basic) = (PeerId::random(), PeerId::random());
+        peers.add_peer_kind(observer, Some(PeerKind::Observer), addr, None);
+        peers.add_peer(basic, addr, None);
+        peers.peers.get_mut(&observer).unwrap().state = PeerConnectionState::Out;
+
+        assert!(!peers.is_routable(&observer));
+        assert!(peers.is_routable(&basic));
+        assert!(!peers.is_routable(&PeerId::random()));
+        assert_eq!(peers.select_dial_candidates(10), vec![basic]);
+
+        // the reputation is tracked, but the observer is never disconnected or banned
+        peers.queued_actions.clear();
+        peers.apply_reputation_change(&observer, ReputationChangeKind::BadProtocol);
+        let peer = &peers.peers[&observer];
+        assert!(peer.is_banned_with_threshold(peers.ban_threshold));
+        assert_eq!(peer.state, PeerConnectionState::Out);
+        assert!(!peers.ban_list.is_banned_peer(&observer));
+        assert!(peers.queued_actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_observer_dials_checked_and_limited() {
+        let config = PeersConfig::test().with_max_concurrent_observer_dials(2);
+        let mut peers = PeersManager::new(config);
+        let observer_at = |ip: [u8; 4], port: u16| {
+            PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::from(ip)), port))
+        };
+
+        let no_tcp = PeerId::random();
+        peers.add_peer_kind(no_tcp, Some(PeerKind::Observer), observer_at([127, 0, 0, 1], 0), None);
+        let banned_ip = PeerId::random();
+        peers.add_peer_kind(
+            banned_ip,
+            Some(PeerKind::Observer),
+            observer_at([127, 0, 0, 2], 30303),
+            None,
+        );
+        peers.ban_list.ban_ip(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)));
+        let dialable = (0..3).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for (idx, peer_id) in dialable.iter().enumerate() {
+            let addr = observer_at([127, 0, 1, idx as u8], 30303);
+            peers.add_peer_kind(*peer_id, Some(PeerKind::Observer), addr, None);
+        }
+
+        let dialed = |peers: &PeersManager| {
+            peers
+                .queued_actions
+                .iter()
+                .filter_map(|action| match action {
+                    PeerAction::Connect { peer_id, .. } => Some(*peer_id),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // only two dials to valid observers, the rest waits for a free dial slot
+        peers.fill_outbound_slots();
+        let first = dialed(&peers);
+        assert_eq!(first.len(), 2);
+        assert!(first.iter().all(|peer_id| dialable.contains(peer_id)));
+        assert_eq!(peers.connection_info.num_pending_out, 0);
+
+        peers.queued_actions.clear();
+        peers.fill_outbound_slots();
+        assert!(dialed(&peers).is_empty());
+
+        peers.on_active_outgoing_established(first[0]);
+        peers.fill_outbound_slots();
+        let second = dialed(&peers);
+        assert_eq!(second.len(), 1);
+        assert!(dialable.contains(&second[0]) && !first.contains(&second[0]));
+
+        // observers are never trusted, so they're not dialed in trusted only mode
+        let mut peers = PeersManager::new(PeersConfig::test().with_trusted_nodes_only(true));
+        peers.add_peer_kind(
+            dialable[0],
+            Some(PeerKind::Observer),
+            observer_at([127, 0, 1, 0], 30303),
+            None,
+        );
+        peers.fill_outbound_slots();
+        assert!(dialed(&peers).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_decay_all_min_age() {
+        let min_age = Duration::from_secs(60);
+        let config = PeersConfig::test()
+            .with_reputation_decay_per_second(1024)
+            .with_reputation_decay_min_age(min_age);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (young, old) = (PeerId::random(), PeerId::random());
+        for peer_id in [young, old] {
+            peers.add_peer(peer_id, addr, None);
+            peers.apply_reputation_change(
+                &peer_id,
+                ReputationChangeKind::Other(BANNED_REPUTATION - 1024),
+            );
+        }
+        let now = std::time::Instant::now() + Duration::from_secs(10);
+        peers.peers.get_mut(&young).unwrap().first_seen = now;
+        peers.peers.get_mut(&old).unwrap().first_seen = now - 2 * min_age;
+
+        // only the peer that was observed for long enough is forgiven
+        assert_eq!(peers.decay_all(now), vec![old]);
+        assert!(peers.ban_list.is_banned_peer(&young));
+        assert!(peers.peers[&young].is_banned_with_threshold(peers.ban_threshold));
+
+        assert_eq!(peers.decay_all(now + min_age + Duration::from_secs(10)), vec![young]);
+    }
+
+    #[tokio::test]
+    async fn test_reset_reputation_for_kind() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (timed_out, banned, other, recovered) =
+            (PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random());
+        for peer_id in [timed_out, banned, other, recovered] {
+            peers.add_peer(peer_id, addr, None);
+        }
+        peers.apply_reputation_change(&timed_out, ReputationChangeKind::Timeout);
+        peers.apply_reputation_change(&banned, ReputationChangeKind::BadProtocol);
+        peers.apply_reputation_change(&other, ReputationChangeKind::BadMessage);
+        // only the most recent penalty counts
+        peers.apply_reputation_change(&recovered, ReputationChangeKind::BadProtocol);
+        peers.apply_reputation_change(&recovered, ReputationChangeKind::Timeout);
+        assert!(peers.ban_list.is_banned_peer(&banned));
+        let other_reputation = peers.peers[&other].reputation;
+
+        peers.queued_actions.clear();
+        assert_eq!(peers.reset_reputation_for_kind(ReputationChangeKind::BadProtocol), 1);
+        assert_eq!(peers.peers[&banned].reputation, DEFAULT_REPUTATION);
+        assert!(!peers.ban_list.is_banned_peer(&banned));
+        assert!(!peers.peers[&banned].is_on_probation(std::time::Instant::now()));
+        assert!(matches!(
+            peers.queued_actions.pop_front(),
+            Some(PeerAction::UnBanPeer { peer_id }) if peer_id == banned
+        ));
+        assert!(peers.peers[&recovered].reputation < DEFAULT_REPUTATION);
+        assert_eq!(peers.peers[&other].reputation, other_reputation);
+
+        assert_eq!(peers.reset_reputation_for_kind(ReputationChangeKind::Timeout), 2);
+        assert_eq!(peers.peers[&timed_out].reputation, DEFAULT_REPUTATION);
+        assert_eq!(peers.peers[&recovered].reputation, DEFAULT_REPUTATION);
+        assert_eq!(peers.peers[&other].reputation, other_reputation);
+        assert_eq!(peers.reset_reputation_for_kind(ReputationChangeKind::Timeout), 0);
+    }
+
+    #[tokio::test]
+    async fn test_add_discovered_with_memory() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let record =
+            |id| NodeRecord::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303), id);
+        let (remembered, unremembered) = (PeerId::random(), PeerId::random());
+        let fork_id = ForkId { hash: ForkHash([1, 2, 3, 4]), next: 0 };
+        let memory = HashMap::from([(
+            remembered,
+            PersistedPeerInfo {
+                record: record(remembered),
+                kind: PeerKind::Static,
+                fork_id: Some(fork_id),
+                reputation: MAX_REPUTATION,
+                last_ban_reason: None,
+                protocol_version: None,
+                quic_port: Some(30305),
+            },
+        )]);
+
+        peers.add_discovered_with_memory(record(remembered), &memory);
+        peers.add_discovered_with_memory(record(unremembered), &memory);
+
+        let peer = &peers.peers[&remembered];
+        assert_eq!(peer.reputation, MAX_REPUTATION);
+        assert_eq!(peer.kind, PeerKind::Static);
+        assert_eq!(peer.fork_id.as_deref(), Some(&fork_id));
+        assert_eq!(peer.addr.quic_port(), Some(30305));
+        let peer = &peers.peers[&unremembered];
+        assert_eq!(peer.reputation, DEFAULT_DISCOVERED_REPUTATION);
+        assert_eq!(peer.kind, PeerKind::Basic);
+        assert_eq!(peer.fork_id, None);
+
+        // the memory is only consulted for peers that aren't tracked yet
+        peers.apply_reputation_change(&remembered, ReputationChangeKind::BadMessage);
+        let reputation = peers.peers[&remembered].reputation;
+        peers.add_discovered_with_memory(record(remembered), &memory);
+        assert_eq!(peers.peers[&remembered].reputation, reputation);
+    }
+
+    #[tokio::test]
+    async fn test_export_import_banned() {
+        let mut source = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (banned, known, trusted, healthy) =
+            (PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random());
+        for peer_id in [banned, known, trusted, healthy] {
+            source.add_peer(peer_id, addr, None);
+        }
+        for peer_id in [banned, known, trusted] {
+            source.apply_reputation_change(&peer_id, ReputationChangeKind::BadProtocol);
+        }
+
+        let list = source.export_banned();
+        let mut expected = vec![
+            (banned, source.peers[&banned].reputation),
+            (known, source.peers[&known].reputation),
+            (trusted, source.peers[&trusted].reputation),
+        ];
+        expected.sort_unstable_by_key(|(peer_id, _)| *peer_id);
+        assert_eq!(list, expected);
+
+        let mut target = PeersManager::new(PeersConfig::test());
+        target.add_peer(known, addr, None);
+        target.add_trusted_peer(trusted, addr);
+        target.add_peer(healthy, addr, None);
+        assert_eq!(target.import_banned(list.clone()), 2);
+
+        // the unknown peer is added as a placeholder that isn't dialed
+        let placeholder = &target.peers[&banned];
+        assert!(placeholder.is_unreachable());
+        assert!(placeholder.addr.tcp().ip().is_unspecified());
+        for peer_id in [banned, known] {
+            assert!(target.ban_list.is_banned_peer(&peer_id));
+        }
+        assert!(!target.peers[&trusted].is_banned_with_threshold(target.ban_threshold));
+        assert!(!target.ban_list.is_banned_peer(&healthy));
+
+        let mut expected = list;
+        expected.retain(|(peer_id, _)| *peer_id != trusted);
+        assert_eq!(target.export_banned(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_imported_placeholder_not_persisted() {
+        let config = PeersConfig::test()
+            .with_reputation_decay_per_second(1024)
+            .with_reputation_decay_min_age(Duration::ZERO);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let (known, unknown) = (PeerId::random(), PeerId::random());
+        peers.add_peer(known, addr, None);
+        assert_eq!(peers.import_banned([(unknown, BANNED_REPUTATION - 1024)]), 1);
+        assert!(peers.ban_list.is_banned_peer(&unknown));
+
+        // the placeholder decays back above the ban threshold and is unbanned
+        let unbanned = peers.decay_all(std::time::Instant::now() + Duration::from_secs(3600));
+        assert_eq!(unbanned, vec![unknown]);
+        assert!(!peers.peers[&unknown].is_banned_with_threshold(peers.ban_threshold));
+
+        // but it has no address, so it's neither persisted nor listed
+        let persisted = peers.persistable_peers().map(|info| info.record.id).collect::<Vec<_>>();
+        assert_eq!(persisted, vec![known]);
+        let listed = peers.iter_peers().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!(listed, vec![known]);
+    }
+
+    #[tokio::test]
+    async fn test_dial_history() {
+        let mut peers = PeersManager::new(PeersConfig::test().with_dial_history_capacity(2));
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let peer_id = PeerId::random();
+        peers.add_peer(peer_id, PeerAddr::from_tcp(socket_addr), None);
+
+        let dial = |peers: &mut PeersManager| {
+            let peer = peers.peers.get_mut(&peer_id).unwrap();
+            peer.state = PeerConnectionState::PendingOut;
+            peer.backed_off = false;
+            peers.connection_info.inc_pending_out();
+        };
+        dial(&mut peers);
+        peers.on_outgoing_connection_failure(
+            &socket_addr,
+            &peer_id,
+            &io::Error::new(io::ErrorKind::ConnectionRefused, "refused"),
+        );
+        dial(&mut peers);
+        peers.on_active_outgoing_established(peer_id);
+        peers.on_active_session_gracefully_closed(peer_id);
+        dial(&mut peers);
+        peers.on_outgoing_connection_failure(
+            &socket_addr,
+            &peer_id,
+            &io::Error::new(io::ErrorKind::ConnectionRefused, "refused"),
+        );
+
+        let history = peers.peers[&peer_id]
+            .dial_history()
+            .iter()
+            .map(|(_, outcome)| *outcome)
+            .collect::<Vec<_>>();
+        assert_eq!(history, vec![DialOutcome::Connected, DialOutcome::ConnectionFailed]);
+    }
+
+    #[tokio::test]
+    async fn test_on_tier_change() {
+        let peer = PeerId::random();
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let mut peers = PeersManager::new(PeersConfig::test());
+        peers.add_peer(peer, PeerAddr::from_tcp(socket_addr), None);
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+        peers.on_tier_change(move |peer_id, old, new| {
+            recorded.lock().unwrap().push((peer_id, old, new));
+        });
+
+        // jitter within the normal tier
+        peers.apply_reputation_change(&peer, ReputationChangeKind::Other(-100));
+        peers.apply_reputation_change(&peer, ReputationChangeKind::Other(100));
+        peers.apply_reputation_change(&peer, ReputationChangeKind::Other(-100));
+        assert!(changes.lock().unwrap().is_empty());
+
+        // crossing the quarantine threshold
+        peers.apply_reputation_change(&peer, ReputationChangeKind::Other(QUARANTINE_REPUTATION));
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![(peer, ReputationTier::Normal, ReputationTier::Quarantined)]
+        );
+
+        // crossing the ban threshold
+        peers.apply_reputation_change(&peer, ReputationChangeKind::Other(BANNED_REPUTATION));
+        assert_eq!(
+            changes.lock().unwrap().last(),
+            Some(&(peer, ReputationTier::Quarantined, ReputationTier::Banned))
+        );
+        assert_eq!(changes.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_on_tier_change_on_decay() {
+        let config = PeersConfig::test()
+            .with_reputation_decay_per_second(1024)
+            .with_reputation_decay_min_age(Duration::ZERO);
+        let mut peers = PeersManager::new(config);
+        let peer = PeerId::random();
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        peers.add_peer(peer, addr, None);
+        peers.apply_reputation_change(&peer, ReputationChangeKind::Other(BANNED_REPUTATION - 1024));
+        assert!(peers.ban_list.is_banned_peer(&peer));
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+        peers.on_tier_change(move |peer_id, old, new| {
+            recorded.lock().unwrap().push((peer_id, old, new));
+        });
+
+        let unbanned = peers.decay_all(std::time::Instant::now() + Duration::from_secs(3600));
+        assert_eq!(unbanned, vec![peer]);
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        let (peer_id, old, new) = changes[0];
+        assert_eq!((peer_id, old), (peer, ReputationTier::Banned));
+        assert_ne!(new, ReputationTier::Banned);
+    }
+
+    #[tokio::test]
+    async fn test_apply_subnet_reputation() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = |ip: [u8; 4]| PeerAddr::from_tcp(SocketAddr::from((ip, 30303)));
+
+        let in_subnet = [PeerId::random(), PeerId::random()];
+        peers.add_peer(in_subnet[0], addr([10, 0, 1, 1]), None);
+        peers.add_peer(in_subnet[1], addr([10, 0, 200, 7]), None);
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr([10, 0, 3, 3]));
+        let other = PeerId::random();
+        peers.add_peer(other, addr([10, 1, 0, 1]), None);
+
+        let subnet = IpSubnet::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 16, 64);
+
+        // a penalty that doesn't ban
+        let banned = peers.apply_subnet_reputation(subnet, -1000, ReputationChangeKind::BadMessage);
+        assert!(banned.is_empty());
+        for peer_id in &in_subnet {
+            assert_eq!(peers.get_reputation(peer_id), Some(DEFAULT_DISCOVERED_REPUTATION - 1000));
+        }
+        assert_eq!(peers.get_reputation(&trusted), Some(DEFAULT_REPUTATION));
+        assert_eq!(peers.get_reputation(&other), Some(DEFAULT_DISCOVERED_REPUTATION));
+
+        // a penalty that bans the whole subnet
+        let banned = peers.apply_subnet_reputation(
+            subnet,
+            BANNED_REPUTATION,
+            ReputationChangeKind::BadMessage,
+        );
+        let mut expected = in_subnet.to_vec();
+        expected.sort_unstable();
+        assert_eq!(banned, expected);
+        for peer_id in &in_subnet {
+            assert!(peers.peers.get(peer_id).unwrap().is_banned());
+        }
+        assert!(!peers.peers.get(&trusted).unwrap().is_banned());
+        assert_eq!(peers.get_reputation(&other), Some(DEFAULT_DISCOVERED_REPUTATION));
+        assert!(!peers.peers.get(&other).unwrap().is_banned());
+    }
+
+    #[tokio::test]
+    async fn test_slots_delta() {
+        let config = PeersConfig::test().with_max_outbound(4).with_max_inbound(4);
+        let mut peers = PeersManager::new(config);
+        for i in 0..3u8 {
+            let addr = SocketAddr::from(([127, 0, 0, i + 1], 30303));
+            let peer = PeerId::random();
+            peers.add_peer(peer, PeerAddr::from_tcp(addr), None);
+            peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::Out;
+            peers.connection_info.inc_out();
+        }
+        for i in 0..2u8 {
+            let addr = SocketAddr::from(([127, 0, 1, i + 1], 30303));
+            let peer = PeerId::random();
+            peers.add_peer(peer, PeerAddr::from_tcp(addr), None);
+            peers.peers.get_mut(&peer).unwrap().state = PeerConnectionState::In;
+            peers.connection_info.inc_in();
+        }
+
+        // increasing the caps opens up slots
+        let increased =
+            ConnectionsConfig { max_outbound: 10, max_inbound: 6, ..Default::default() };
+        assert_eq!(
+            peers.slots_delta(&increased),
+            SlotDelta { opened_outbound: 6, opened_inbound: 2, ..Default::default() }
+        );
+
+        // decreasing the caps below the active sessions requires draining
+        let decreased = ConnectionsConfig { max_outbound: 1, max_inbound: 2, ..Default::default() };
+        let delta = peers.slots_delta(&decreased);
+        assert_eq!(delta, SlotDelta { excess_outbound: 2, ..Default::default() });
+        assert_eq!(delta.excess(), 2);
+        assert_eq!(delta.opened(), 0);
+
+        // nothing changed
+        assert_eq!(peers.connection_info.num_outbound, 3);
+        assert_eq!(peers.connection_info.num_inbound, 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_fork_transition() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let genesis = B256::random();
+        let forks = [ForkFilterKey::Block(10)];
+        let pre_fork = ForkFilter::new(Head::default(), genesis, 0, forks).current();
+        let filter = ForkFilter::new(Head { number: 20, ..Default::default() }, genesis, 0, forks);
+
+        let mut add = |fork_id: Option<ForkId>, kind: PeerKind| {
+            let peer_id = PeerId::random();
+            let addr = PeerAddr::from_tcp(SocketAddr::from(([127, 0, 0, 1], 30303)));
+            peers.add_peer_kind(peer_id, Some(kind), addr, fork_id);
+            peer_id
+        };
+        let current = add(Some(filter.current()), PeerKind::Basic);
+        // announced the fork before it activated
+        let upgraded = add(Some(pre_fork), PeerKind::Basic);
+        // unaware of the fork
+        let stale = add(Some(ForkId { hash: pre_fork.hash, next: 0 }), PeerKind::Basic);
+        let other_chain =
+            add(Some(ForkId { hash: ForkHash([1, 2, 3, 4]), next: 0 }), PeerKind::Basic);
+        let stale_static = add(Some(ForkId { hash: pre_fork.hash, next: 0 }), PeerKind::Static);
+        let unknown = add(None, PeerKind::Basic);
+
+        let report = peers.apply_fork_transition(&filter);
+        assert_eq!(report, ForkTransitionReport { compatible: 2, incompatible: 3, unknown: 1 });
+
+        for peer_id in [stale, other_chain] {
+            assert!(peers.peers[&peer_id].remove_after_disconnect);
+        }
+        for peer_id in [current, upgraded, stale_static, unknown] {
+            assert!(!peers.peers[&peer_id].remove_after_disconnect);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_age_histogram() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let now = std::time::Instant::now();
+        let minute = Duration::from_secs(60);
+        for (i, age) in [0, 30, 60, 61, 600, 3600, 7200].into_iter().enumerate() {
+            let peer_id = PeerId::random();
+            let addr = SocketAddr::from(([127, 0, 0, i as u8 + 1], 30303));
+            peers.add_peer(peer_id, PeerAddr::from_tcp(addr), None);
+            peers.peers.get_mut(&peer_id).unwrap().first_seen = now - Duration::from_secs(age);
+        }
+
+        let buckets = [minute, 10 * minute, 60 * minute];
+        assert_eq!(peers.age_histogram(now, &buckets), vec![2, 2, 1, 2]);
+        assert_eq!(peers.age_histogram(now, &[]), vec![7]);
+
+        // peers seen after `now` count as new
+        let earlier = now - Duration::from_secs(30);
+        assert_eq!(peers.age_histogram(earlier, &buckets), vec![4, 1, 1, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_retire_static() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let static_peer = PeerId::random();
+        peers.add_peer_kind(static_peer, Some(PeerKind::Static), addr, None);
+        let basic = PeerId::random();
+        peers.add_peer(basic, addr, None);
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr);
+
+        peers.apply_reputation_change(&static_peer, ReputationChangeKind::BadMessage);
+        let reputation = peers.get_reputation(&static_peer);
+
+        assert!(peers.retire_static(&static_peer));
+        let peer = &peers.peers[&static_peer];
+        assert_eq!(peer.kind, PeerKind::Basic);
+        assert_eq!(peers.get_reputation(&static_peer), reputation);
+
+        // already retired
+        assert!(!peers.retire_static(&static_peer));
+
+        assert!(!peers.retire_static(&basic));
+        assert_eq!(peers.peers[&basic].kind, PeerKind::Basic);
+        assert!(!peers.retire_static(&trusted));
+        assert_eq!(peers.peers[&trusted].kind, PeerKind::Trusted);
+        assert!(!peers.retire_static(&PeerId::random()));
+    }
+
+    #[tokio::test]
+    async fn test_min_inbound_reputation() {
+        let config = PeersConfig::test().with_min_inbound_reputation(-2000);
+        let mut peers = PeersManager::new(config);
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+
+        let bad = PeerId::random();
+        peers.add_peer(bad, PeerAddr::from_tcp(socket_addr), None);
+        peers.peers.get_mut(&bad).unwrap().reputation = -3000;
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, PeerAddr::from_tcp(socket_addr));
+        peers.peers.get_mut(&trusted).unwrap().reputation = -3000;
+        peers.queued_actions.clear();
+
+        // known peer below the gate is refused
+        assert!(peers.on_incoming_pending_session(socket_addr.ip()).is_ok());
+        peers.on_incoming_session_established(bad, socket_addr);
+        assert!(matches!(
+            peers.queued_actions.pop_front(),
+            Some(PeerAction::DisconnectLowReputationIncoming { peer_id }) if peer_id == bad
+        ));
+        assert_eq!(peers.peers[&bad].state, PeerConnectionState::Idle);
+        assert_eq!(peers.connection_info.num_inbound, 0);
+
+        // unknown peer has no history and is accepted
+        let unknown = PeerId::random();
+        assert!(peers.on_incoming_pending_session(socket_addr.ip()).is_ok());
+        peers.on_incoming_session_established(unknown, socket_addr);
+        assert_eq!(peers.peers[&unknown].state, PeerConnectionState::In);
+
+        // trusted peers are exempt
+        assert!(peers.on_incoming_pending_session(socket_addr.ip()).is_ok());
+        peers.on_incoming_session_established(trusted, socket_addr);
+        assert_eq!(peers.peers[&trusted].state, PeerConnectionState::In);
+
+        assert!(!peers.queued_actions.iter().any(|action| matches!(
+            action,
+            PeerAction::DisconnectLowReputationIncoming { .. } | PeerAction::Disconnect { .. }
+        )));
+        assert_eq!(peers.connection_info.num_inbound, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reputation_floors() {
+        let config = PeersConfig::test().with_reputation_floors(-10_000, -30_000);
+        let mut peers = PeersManager::new(config);
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+
+        let mut add = |kind| {
+            let peer_id = PeerId::random();
+            peers.add_peer_kind(peer_id, Some(kind), PeerAddr::from_tcp(socket_addr), None);
+            peer_id
+        };
+        let trusted = add(PeerKind::Trusted);
+        let static_peer = add(PeerKind::Static);
+        let basic = add(PeerKind::Basic);
+
+        for _ in 0..100 {
+            for peer_id in [trusted, static_peer, basic] {
+                peers.apply_reputation_change(&peer_id, ReputationChangeKind::BadProtocol);
+            }
+        }
+
+        assert_eq!(peers.get_reputation(&trusted), Some(-10_000));
+        assert_eq!(peers.get_reputation(&static_peer), Some(-30_000));
+        assert!(peers.get_reputation(&basic).unwrap() < BANNED_REPUTATION);
+        assert!(!peers.peers[&trusted].is_banned());
+        assert!(!peers.peers[&static_peer].is_banned());
+
+        // rewards still apply above the floor
+        peers.apply_reputation_change(&trusted, ReputationChangeKind::Other(1000));
+        assert_eq!(peers.get_reputation(&trusted), Some(-9_000));
+    }
+
+    #[tokio::test]
+    async fn test_reputation_floors_on_failed_handshake() {
+        let config = PeersConfig::test().with_reputation_floors(-10_000, -30_000);
+        let mut peers = PeersManager::new(config);
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let static_peer = PeerId::random();
+        peers.add_peer_kind(
+            static_peer,
+            Some(PeerKind::Static),
+            PeerAddr::from_tcp(socket_addr),
+            None,
+        );
+
+        // not a backoff error, so it's penalized with `FailedToConnect`
+        let err = PendingSessionHandshakeError::Eth(EthStreamError::InvalidMessage(
+            reth_eth_wire::message::MessageError::Invalid(
+                reth_eth_wire::EthVersion::Eth68,
+                reth_eth_wire::EthMessageID::Status,
+            ),
+        ));
+        for _ in 0..100 {
+            peers.peers.get_mut(&static_peer).unwrap().state = PeerConnectionState::PendingOut;
+            peers.connection_info.inc_pending_out();
+            peers.on_outgoing_pending_session_dropped(&socket_addr, &static_peer, &err);
+        }
+
+        assert_eq!(peers.get_reputation(&static_peer), Some(-30_000));
+        assert!(!peers.peers[&static_peer].is_banned());
+        assert!(!peers.ban_list.is_banned_peer(&static_peer));
+    }
+
+    #[tokio::test]
+    async fn test_ban_rate() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let window = Duration::from_secs(10);
+        let start = std::time::Instant::now();
+        assert_eq!(peers.ban_rate(window, start), 0.0);
+
+        // a single ban
+        let peer_id = PeerId::random();
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        peers.add_peer(peer_id, addr, None);
+        peers.apply_reputation_change(&peer_id, ReputationChangeKind::BadProtocol);
+        let threshold = 1.0;
+        assert!(peers.ban_rate(window, std::time::Instant::now()) < threshold);
+
+        // a spike of bans
+        for i in 0..20u8 {
+            let peer_id = PeerId::random();
+            let addr = PeerAddr::from_tcp(SocketAddr::from(([127, 0, 1, i], 30303)));
+            peers.add_peer(peer_id, addr, None);
+            peers.apply_reputation_change(&peer_id, ReputationChangeKind::BadProtocol);
+        }
+        let now = std::time::Instant::now();
+        assert_eq!(peers.ban_rate(window, now), 2.1);
+        assert!(peers.ban_rate(window, now) > threshold);
+
+        // the spike is outside of later windows
+        assert_eq!(peers.ban_rate(window, now + 2 * window), 0.0);
+        assert_eq!(peers.ban_rate(Duration::ZERO, now), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_no_tcp_port_not_dialed() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let udp_only = PeerId::random();
+        let addr = PeerAddr::new_with_ports(IpAddr::V4(Ipv4Addr::LOCALHOST), 0, Some(30303));
+        assert_eq!(addr.tcp_addr(), None);
+        peers.add_peer(udp_only, addr, None);
+
+        // kept for discovery, but never dialed
+        assert_eq!(peers.peers[&udp_only].addr.udp(), Some(addr.udp().unwrap()));
+        assert!(peers.select_dial_candidates(10).is_empty());
+        peers.fill_outbound_slots();
+        assert!(!peers
+            .queued_actions
+            .iter()
+            .any(|action| matches!(action, PeerAction::Connect { .. })));
+        assert_eq!(peers.peers[&udp_only].state, PeerConnectionState::Idle);
+
+        let dialable = PeerId::random();
+        let addr = PeerAddr::new_with_ports(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303, Some(30303));
+        assert_eq!(addr.tcp_addr(), Some(addr.tcp()));
+        peers.add_peer(dialable, addr, None);
+        assert_eq!(peers.select_dial_candidates(10), vec![dialable]);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_reputation_merge_policy() {
+        let peer_id = PeerId::random();
+        let trusted = TrustedPeer {
+            host: url::Host::Ipv4(Ipv4Addr::LOCALHOST),
+            tcp_port: 30303,
+            udp_port: 30303,
+            id: peer_id,
+        };
+        let persisted = PersistedPeerInfo {
+            record: NodeRecord::new(
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303),
+                peer_id,
+            ),
+            kind: PeerKind::Trusted,
+            fork_id: None,
+            reputation: -2048,
+            last_ban_reason: None,
+            protocol_version: None,
+            quic_port: None,
+        };
+        let config = |policy| PeersConfig {
+            persisted_peers: vec![persisted.clone()],
+            ..PeersConfig::test()
+                .with_trusted_nodes(vec![trusted.clone()])
+                .with_reputation_merge_policy(policy)
+        };
+
+        // the configured trusted peer keeps its live reputation by default
+        let peers = PeersManager::new(config(MergePolicy::default()));
+        assert_eq!(peers.peers[&peer_id].reputation, DEFAULT_REPUTATION);
+
+        let peers = PeersManager::new(config(MergePolicy::TakePersisted));
+        assert_eq!(peers.peers[&peer_id].reputation, -2048);
+
+        let peers = PeersManager::new(config(MergePolicy::TakeMin));
+        assert_eq!(peers.peers[&peer_id].reputation, -2048);
+
+        let peers = PeersManager::new(config(MergePolicy::WeightedAverage { live_percent: 75 }));
+        assert_eq!(peers.peers[&peer_id].reputation, -512);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_subnet_bonus() {
+        let trusted = PeerId::random();
+        let addr = |ip: [u8; 4]| PeerAddr::from_tcp(SocketAddr::new(IpAddr::from(ip), 30303));
+
+        let mut peers = PeersManager::new(PeersConfig::test().with_trusted_subnet_bonus(512));
+        peers.add_trusted_peer(trusted, addr([10, 0, 0, 1]));
+        assert_eq!(peers.peers[&trusted].reputation, DEFAULT_REPUTATION);
+
+        let (neighbour, stranger) = (PeerId::random(), PeerId::random());
+        peers.add_peer(neighbour, addr([10, 0, 0, 2]), None);
+        peers.add_peer(stranger, addr([10, 0, 1, 2]), None);
+        assert_eq!(peers.peers[&neighbour].reputation, DEFAULT_DISCOVERED_REPUTATION + 512);
+        assert_eq!(peers.peers[&stranger].reputation, DEFAULT_DISCOVERED_REPUTATION);
+
+        // the bonus is only applied on creation
+        peers.add_peer(stranger, addr([10, 0, 0, 3]), None);
+        assert_eq!(peers.peers[&stranger].reputation, DEFAULT_DISCOVERED_REPUTATION);
+
+        // disabled by default
+        let mut peers = PeersManager::new(PeersConfig::test());
+        peers.add_trusted_peer(trusted, addr([10, 0, 0, 1]));
+        peers.add_peer(neighbour, addr([10, 0, 0, 2]), None);
+        assert_eq!(peers.peers[&neighbour].reputation, DEFAULT_DISCOVERED_REPUTATION);
+    }
+
+    #[tokio::test]
+    async fn test_trim_history_to_budget() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let now = std::time::Instant::now();
+        let ids = [PeerId::random(), PeerId::random(), PeerId::random()];
+        for (i, peer_id) in ids.iter().enumerate() {
+            let ip = Ipv4Addr::new(127, 0, 0, i as u8 + 1);
+            peers.add_peer(
+                *peer_id,
+                PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(ip), 30303)),
+                None,
+            );
+            let peer = peers.peers.get_mut(peer_id).unwrap();
+            for _ in 0..16 {
+                peer.record_dial_with_capacity(now, DialOutcome::ConnectionFailed, 16);
+            }
+        }
+        let [low, normal, good] = ids;
+        peers.peers.get_mut(&low).unwrap().reputation = BANNED_REPUTATION;
+        peers.peers.get_mut(&good).unwrap().reputation = MAX_REPUTATION;
+
+        let estimate = peers.history_memory_estimate();
+        assert!(estimate > 0);
+
+        // within budget, nothing is trimmed
+        peers.trim_history_to_budget(estimate);
+        assert_eq!(peers.history_memory_estimate(), estimate);
+
+        // the history of the lowest reputation peer is dropped first
+        let per_peer = peers.peers[&normal].history_memory_estimate();
+        peers.trim_history_to_budget(2 * per_peer);
+        assert!(peers.history_memory_estimate() <= 2 * per_peer);
+        assert!(peers.peers[&low].dial_history().is_empty());
+        assert!(!peers.peers[&normal].dial_history().is_empty());
+        assert!(!peers.peers[&good].dial_history().is_empty());
+
+        peers.trim_history_to_budget(0);
+        assert_eq!(peers.history_memory_estimate(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_reputation_events() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr =
+            |i| PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 30303));
+        let (bad, flaky, good) = (PeerId::random(), PeerId::random(), PeerId::random());
+        peers.add_peer(bad, addr(1), None);
+        peers.add_peer(flaky, addr(2), None);
+        peers.add_peer(good, addr(3), None);
+
+        // the scripted changes are applied instead of the weights of the kinds
+        let mut source = MockEventSource::new([
+            (good, 100, ReputationChangeKind::GoodResponse),
+            (bad, -1000, ReputationChangeKind::BadMessage),
+            (flaky, -1000, ReputationChangeKind::Timeout),
+            (good, 100, ReputationChangeKind::GoodResponse),
+            (bad, BANNED_REPUTATION, ReputationChangeKind::BadMessage),
+            (flaky, 500, ReputationChangeKind::GoodResponse),
+        ]);
+        assert_eq!(peers.replay_reputation_events(&mut source), 6);
+        assert_eq!(source.remaining(), 0);
+
+        assert!(peers.ban_list.is_banned_peer(&bad));
+        assert_eq!(peers.peers[&bad].last_ban_reason, Some(ReputationChangeKind::BadMessage));
+        assert_eq!(peers.peers[&flaky].reputation, DEFAULT_DISCOVERED_REPUTATION - 500);
+        assert_eq!(peers.peers[&good].reputation, DEFAULT_DISCOVERED_REPUTATION + 200);
+        assert!(!peers.ban_list.is_banned_peer(&good));
+
+        // kind specific handling still applies, e.g. trusted peers are exempt from timeouts
+        let trusted = PeerId::random();
+        peers.add_trusted_peer(trusted, addr(4));
+        let mut source = MockEventSource::new([(trusted, -1000, ReputationChangeKind::Timeout)]);
+        peers.replay_reputation_events(&mut source);
+        assert_eq!(peers.peers[&trusted].reputation, DEFAULT_REPUTATION);
+
+        // replaying an exhausted source does nothing
+        assert_eq!(peers.replay_reputation_events(&mut source), 0);
+    }
+
+    #[tokio::test]
+    async fn test_peers_by_protocol_version() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let ids = (0..4).map(|_| PeerId::random()).collect::<Vec<_>>();
+        for (i, peer_id) in ids.iter().enumerate() {
+            let ip = Ipv4Addr::new(127, 0, 0, i as u8 + 1);
+            peers.add_peer(
+                *peer_id,
+                PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(ip), 30303)),
+                None,
+            );
+        }
+        assert!(peers.peers_by_protocol_version().is_empty());
+
+        peers.on_protocol_version(&ids[0], 68);
+        peers.on_protocol_version(&ids[1], 67);
+        peers.on_protocol_version(&ids[2], 68);
+        // unknown peers are ignored
+        peers.on_protocol_version(&PeerId::random(), 66);
+        assert_eq!(peers.peers[&ids[0]].protocol_version(), Some(68));
+        assert_eq!(peers.peers[&ids[3]].protocol_version(), None);
+
+        let mut v68 = vec![ids[0], ids[2]];
+        v68.sort_unstable();
+        assert_eq!(
+            peers.peers_by_protocol_version(),
+            BTreeMap::from([(67, vec![ids[1]]), (68, v68)])
+        );
+
+        // the version is persisted and restored
+        let persisted = peers.snapshot_persisted();
+        let restored =
+            PeersManager::new(PeersConfig { persisted_peers: persisted, ..PeersConfig::test() });
+        assert_eq!(restored.peers[&ids[0]].protocol_version(), Some(68));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let record = |id, i| {
+            NodeRecord::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, i)), 30303), id)
+        };
+        let fork_id = ForkId { hash: ForkHash([1, 2, 3, 4]), next: 0 };
+
+        let (trusted, known, unknown, banned) =
+            (PeerId::random(), PeerId::random(), PeerId::random(), PeerId::random());
+        peers.add_trusted_peer(trusted, PeerAddr::from_tcp(record(trusted, 1).tcp_addr()));
+        peers.add_peer(known, PeerAddr::from_tcp(record(known, 2).tcp_addr()), None);
+        peers.ban_list.ban_peer_until(banned, std::time::Instant::now() + Duration::from_secs(60));
+        peers.queued_actions.clear();
+
+        let persisted = [
+            // conflicts with the configured kind, but the higher reputation is kept
+            PersistedPeerInfo {
+                reputation: MAX_REPUTATION,
+                ..PersistedPeerInfo::from_node_record(record(trusted, 1))
+            },
+            // updates the kind and fork id, but keeps the higher live reputation
+            PersistedPeerInfo {
+                kind: PeerKind::Static,
+                fork_id: Some(fork_id),
+                reputation: BANNED_REPUTATION,
+                ..PersistedPeerInfo::from_node_record(record(known, 2))
+            },
+            PersistedPeerInfo {
+                reputation: -100,
+                protocol_version: Some(68),
+                ..PersistedPeerInfo::from_node_record(record(unknown, 3))
+            },
+            PersistedPeerInfo::from_node_record(record(banned, 4)),
+        ];
+
+        let report = peers.reconcile(&persisted);
+        let mut updated = vec![trusted, known];
+        updated.sort_unstable();
+        assert_eq!(
+            report,
+            ReconcileReport { added: vec![unknown], updated, conflicts: vec![trusted] }
+        );
+
+        let peer = &peers.peers[&trusted];
+        assert_eq!(peer.kind, PeerKind::Trusted);
+        assert_eq!(peer.reputation, MAX_REPUTATION);
+
+        let peer = &peers.peers[&known];
+        assert_eq!(peer.kind, PeerKind::Static);
+        assert_eq!(peer.fork_id.as_deref(), Some(&fork_id));
+        assert_eq!(peer.reputation, DEFAULT_DISCOVERED_REPUTATION);
+
+        let peer = &peers.peers[&unknown];
+        assert_eq!(peer.reputation, -100);
+        assert_eq!(peer.protocol_version(), Some(68));
+        assert!(
+            matches!(peers.queued_actions.pop_front(), Some(PeerAction::PeerAdded(id)) if id == unknown)
+        );
+        assert!(!peers.peers.contains_key(&banned));
+
+        // reconciling the same records again changes nothing
+        assert_eq!(peers.reconcile(&persisted[1..3]), ReconcileReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_unreachable_trusted() {
+        let window = Duration::from_secs(60);
+        let mut peers =
+            PeersManager::new(PeersConfig::test().with_trusted_unreachable_warn_after(window));
+        let socket_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303);
+        let (trusted, basic) = (PeerId::random(), PeerId::random());
+        peers.add_trusted_peer(trusted, PeerAddr::from_tcp(socket_addr));
+        peers.add_peer(basic, PeerAddr::from_tcp(socket_addr), None);
+
+        let start = std::time::Instant::now();
+        let err = io::Error::new(io::ErrorKind::ConnectionRefused, "refused");
+        peers.on_outgoing_connection_failure(&socket_addr, &trusted, &err);
+        peers.on_outgoing_connection_failure(&socket_addr, &basic, &err);
+        // repeated failures don't reset the start of the failure window
+        peers.on_outgoing_connection_failure(&socket_addr, &trusted, &err);
+
+        assert!(peers.sweep_unreachable_trusted(start).is_empty());
+        let later = std::time::Instant::now() + window + Duration::from_secs(1);
+        assert_eq!(peers.sweep_unreachable_trusted(later), vec![trusted]);
+        // the peer is still retried
+        assert!(peers.peers.contains_key(&trusted));
+
+        // a session resets the window
+        peers.on_active_outgoing_established(trusted);
+        assert!(peers.sweep_unreachable_trusted(later).is_empty());
+
+        // disabled by default
+        let mut peers = PeersManager::new(PeersConfig::test());
+        peers.add_trusted_peer(trusted, PeerAddr::from_tcp(socket_addr));
+        peers.on_outgoing_connection_failure(&socket_addr, &trusted, &err);
+        assert!(peers.sweep_unreachable_trusted(later).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_eviction_queue() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let worst = PeerId::random();
+        let bad = PeerId::random();
+        let good = PeerId::random();
+        let trusted = PeerId::random();
+        let pending = PeerId::random();
+        for (peer_id, reputation) in
+            [(worst, -3000), (bad, -2000), (good, 500), (trusted, -5000), (pending, -9000)]
+        {
+            peers.add_peer(peer_id, addr, None);
+            let peer = peers.peers.get_mut(&peer_id).unwrap();
+            peer.reputation = reputation;
+            peer.state = PeerConnectionState::Out;
+        }
+        peers.peers.get_mut(&trusted).unwrap().kind = PeerKind::Trusted;
+        peers.peers.get_mut(&pending).unwrap().state = PeerConnectionState::PendingOut;
+
+        let mut queue = peers.eviction_queue();
+        assert_eq!(queue.len(), 4);
+        let mut order = Vec::new();
+        while let Some(candidate) = queue.pop() {
+            order.push((candidate.peer_id(), candidate.is_evictable()));
+        }
+        assert_eq!(order, vec![(worst, true), (bad, true), (good, true), (trusted, false)]);
+    }
+
+    #[test]
+    fn test_eviction_candidate_ordering() {
+        let mut peer =
+            Peer::new(PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303)));
+        peer.reputation = BANNED_REPUTATION;
+        let banned = EvictionCandidate::new(PeerId::random(), &peer);
+        peer.reputation = MAX_REPUTATION;
+        let good = EvictionCandidate::new(PeerId::random(), &peer);
+        peer.kind = PeerKind::Trusted;
+        peer.reputation = BANNED_REPUTATION;
+        let trusted = EvictionCandidate::new(PeerId::random(), &peer);
+
+        assert!(banned > good);
+        assert!(good > trusted);
+        assert!(!trusted.is_evictable());
+        assert_eq!(trusted.cmp(&trusted), std::cmp::Ordering::Equal);
+    }
+
+    #[tokio::test]
+    async fn test_apply_reputation_batch() {
+        let mut peers = PeersManager::new(PeersConfig::test());
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let peer = PeerId::random();
+        peers.add_peer(peer, addr, None);
+
+        let outcome = peers.apply_reputation_batch([
+            (peer, ReputationChangeKind::BadProtocol),
+            (peer, ReputationChangeKind::BadProtocol),
+            (PeerId::random(), ReputationChangeKind::BadMessage),
+        ]);
+        assert_eq!(
+            outcome,
+            ReputationBatchOutcome { applied: 2, ignored: 1, banned: vec![peer], unbanned: vec![] }
+        );
+        assert!(peers.peers.get(&peer).unwrap().is_banned());
+    }
+
+    #[tokio::test]
+    async fn test_reputation_accumulator() {
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let first = PeerId::random();
+        let second = PeerId::random();
+        let changes = [
+            (first, ReputationChangeKind::Other(-100)),
+            (second, ReputationChangeKind::GoodResponse),
+            (first, ReputationChangeKind::Timeout),
+            (first, ReputationChangeKind::Other(50)),
+            (second, ReputationChangeKind::BadAnnouncement),
+            (second, ReputationChangeKind::Other(-7)),
+        ];
+
+        let mut individual = PeersManager::new(PeersConfig::test());
+        let mut buffered = PeersManager::new(PeersConfig::test());
+        for peers in [&mut individual, &mut buffered] {
+            peers.add_peer(first, addr, None);
+            peers.add_peer(second, addr, None);
+        }
+
+        for (peer_id, kind) in changes {
+            individual.apply_reputation_change(&peer_id, kind);
+        }
+
+        let mut accumulator = ReputationAccumulator::new();
+        for (peer_id, kind) in changes {
+            accumulator.record(peer_id, kind);
+        }
+        assert_eq!(accumulator.len(), changes.len());
+        assert_eq!(buffered.get_reputation(&first), Some(DEFAULT_DISCOVERED_REPUTATION));
+
+        let outcome = accumulator.flush(&mut buffered);
+        assert!(accumulator.is_empty());
+        assert_eq!(outcome.applied, changes.len());
+        for peer_id in [first, second] {
+            assert_eq!(buffered.get_reputation(&peer_id), individual.get_reputation(&peer_id));
+        }
+        assert_ne!(buffered.get_reputation(&first), Some(DEFAULT_DISCOVERED_REPUTATION));
+    }
+
+    #[tokio::test]
+    async fn test_gossip_eligible_peers() {
+        let config = PeersConfig::test().with_gossip_reputation_threshold(-2000);
+        let mut peers = PeersManager::new(config);
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let good = PeerId::random();
+        let low = PeerId::random();
+        let trusted = PeerId::random();
+        let disconnected = PeerId::random();
+        for (peer_id, reputation) in [(good, 0), (low, -3000), (trusted, -5000), (disconnected, 0)]
+        {
+            peers.add_peer(peer_id, addr, None);
+            let peer = peers.peers.get_mut(&peer_id).unwrap();
+            peer.reputation = reputation;
+            peer.state = PeerConnectionState::In;
+        }
+        peers.peers.get_mut(&trusted).unwrap().kind = PeerKind::Trusted;
+        peers.peers.get_mut(&disconnected).unwrap().state = PeerConnectionState::Idle;
+
+        let mut expected = vec![good, trusted];
+        expected.sort_unstable();
+        assert_eq!(peers.gossip_eligible_peers(), expected);
+
+        // the threshold is inclusive
+        peers.peers.get_mut(&low).unwrap().reputation = -2000;
+        assert!(peers.gossip_eligible_peers().contains(&low));
+    }
+
+    #[tokio::test]
+    async fn test_decay_backoff_counters() {
+        let period = Duration::from_secs(60);
+        let mut peers = PeersManager::new(PeersConfig::test().with_backoff_decay_period(period));
+        let addr = PeerAddr::from_tcp(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 30303));
+        let now = std::time::Instant::now();
+        let (basic, trusted, recent) = (PeerId::random(), PeerId::random(), PeerId::random());
+        for (peer_id, last_backoff) in [(basic, now), (trusted, now), (recent, now + period / 2)] {
+            peers.add_peer(peer_id, addr, None);
+            let peer = peers.peers.get_mut(&peer_id).unwrap();
+            peer.record_backoff_at(&BackoffKind::High, last_backoff);
+            peer.record_backoff_at(&BackoffKind::High, last_backoff);
+        }
+        peers.peers.get_mut(&trusted).unwrap().kind = PeerKind::Trusted;
+
+        // nothing decays within the quiet period
+        assert_eq!(peers.decay_backoff_counters(now + period / 2), 0);
+
+        assert_eq!(peers.decay_backoff_counters(now + period), 2);
+        assert_eq!(peers.peers[&basic].severe_backoff_counter, 1);
+        assert_eq!(peers.peers[&trusted].severe_backoff_counter, 1);
+        assert_eq!(peers.peers[&recent].severe_backoff_counter, 2);
+
+        // a new severe backoff restarts the quiet period
+        peers.peers.get_mut(&basic).unwrap().record_backoff_at(&BackoffKind::High, now + period);
+        assert_eq!(peers.decay_backoff_counters(now + period * 3 / 2), 1);
+        assert_eq!(peers.peers[&basic].severe_backoff_counter, 2);
+        assert_eq!(peers.peers[&recent].severe_backoff_counter, 1);
+    }
 }
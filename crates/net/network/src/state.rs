@@ -318,7 +318,9 @@ impl<N: NetworkPrimitives> NetworkState<N> {
     /// Removes a peer and its address with the given kind from the peerset.
     pub(crate) fn remove_peer_kind(&mut self, peer_id: PeerId, kind: PeerKind) {
         match kind {
-            PeerKind::Basic | PeerKind::Static => self.peers_manager.remove_peer(peer_id),
+            PeerKind::Basic | PeerKind::Static | PeerKind::Observer => {
+                self.peers_manager.remove_peer(peer_id)
+            }
             PeerKind::Trusted => self.peers_manager.remove_peer_from_trusted_set(peer_id),
         }
     }
@@ -361,7 +363,8 @@ impl<N: NetworkPrimitives> NetworkState<N> {
                 self.queued_messages.push_back(StateAction::Disconnect { peer_id, reason });
             }
             PeerAction::DisconnectBannedIncoming { peer_id } |
-            PeerAction::DisconnectUntrustedIncoming { peer_id } => {
+            PeerAction::DisconnectUntrustedIncoming { peer_id } |
+            PeerAction::DisconnectLowReputationIncoming { peer_id } => {
                 self.state_fetcher.on_pending_disconnect(&peer_id);
                 self.queued_messages.push_back(StateAction::Disconnect { peer_id, reason: None });
             }
@@ -155,7 +155,7 @@ impl<N: NetworkPrimitives> Swarm<N> {
             }
             SessionEvent::AlreadyConnected { peer_id, remote_addr, direction } => {
                 trace!(target: "net", ?peer_id, ?remote_addr, ?direction, "already connected");
-                self.state.peers_mut().on_already_connected(direction);
+                self.state.peers_mut().on_already_connected(peer_id, direction);
                 None
             }
             SessionEvent::ValidMessage { peer_id, message } => {
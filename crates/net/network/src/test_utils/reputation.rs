@@ -0,0 +1,39 @@
+//! Scripted reputation events for testing
+
+use crate::peers::ReputationEventSource;
+use reth_network_peers::PeerId;
+use reth_network_types::ReputationChangeKind;
+use std::collections::VecDeque;
+
+/// A [`ReputationEventSource`] that yields a scripted sequence of `(peer, change, kind)` events in
+/// order.
+///
+/// This allows replaying realistic sequences of reputation changes deterministically, see
+/// [`PeersManager::replay_reputation_events`](crate::peers::PeersManager::replay_reputation_events).
+#[derive(Debug, Clone, Default)]
+pub struct MockEventSource {
+    events: VecDeque<(PeerId, i32, ReputationChangeKind)>,
+}
+
+impl MockEventSource {
+    /// Creates a new source that yields the given events in order.
+    pub fn new(events: impl IntoIterator<Item = (PeerId, i32, ReputationChangeKind)>) -> Self {
+        Self { events: events.into_iter().collect() }
+    }
+
+    /// Appends an event to the script.
+    pub fn push(&mut self, peer_id: PeerId, change: i32, kind: ReputationChangeKind) {
+        self.events.push_back((peer_id, change, kind));
+    }
+
+    /// Returns the number of events that haven't been yielded yet.
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+}
+
+impl ReputationEventSource for MockEventSource {
+    fn next_event(&mut self) -> Option<(PeerId, i32, ReputationChangeKind)> {
+        self.events.pop_front()
+    }
+}
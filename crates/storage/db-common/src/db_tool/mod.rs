@@ -1,5 +1,6 @@
 //! Common db operations
 
+use alloy_primitives::BlockNumber;
 use boyer_moore_magiclen::BMByte;
 use eyre::Result;
 use reth_db_api::{
@@ -11,8 +12,12 @@ use reth_db_api::{
 };
 use reth_fs_util as fs;
 use reth_node_types::NodeTypesWithDB;
-use reth_provider::{providers::ProviderNodeTypes, ChainSpecProvider, DBProvider, ProviderFactory};
-use std::{path::Path, rc::Rc, sync::Arc};
+use reth_provider::{
+    providers::ProviderNodeTypes, ChainSpecProvider, DBProvider, ProviderFactory,
+    StageCheckpointReader,
+};
+use reth_stages_types::StageId;
+use std::{fmt, path::Path, rc::Rc, sync::Arc};
 use tracing::info;
 
 /// Wrapper over DB that implements many useful DB queries.
@@ -162,6 +167,96 @@ impl<N: ProviderNodeTypes> DbTool<N> {
     }
 }
 
+/// Returns all stages, in pipeline order, that have no checkpoint stored in the database.
+///
+/// A stage without a checkpoint has never run, e.g. right after `init` or a partial import.
+pub fn missing_checkpoints<P: StageCheckpointReader>(provider: &P) -> Result<Vec<StageId>> {
+    let mut missing = Vec::new();
+    for stage_id in StageId::ALL {
+        if provider.get_stage_checkpoint(stage_id)?.is_none() {
+            missing.push(stage_id);
+        }
+    }
+    Ok(missing)
+}
+
+/// Stages paired with the stages whose output they consume.
+///
+/// A stage's checkpoint must never be ahead of the checkpoint of any of its prerequisites.
+pub const STAGE_PREREQUISITES: &[(StageId, StageId)] = &[
+    (StageId::Bodies, StageId::Headers),
+    (StageId::SenderRecovery, StageId::Bodies),
+    (StageId::Execution, StageId::SenderRecovery),
+    (StageId::AccountHashing, StageId::Execution),
+    (StageId::StorageHashing, StageId::Execution),
+    (StageId::MerkleExecute, StageId::AccountHashing),
+    (StageId::MerkleExecute, StageId::StorageHashing),
+    (StageId::TransactionLookup, StageId::Bodies),
+    (StageId::IndexStorageHistory, StageId::Execution),
+    (StageId::IndexAccountHistory, StageId::Execution),
+    (StageId::Finish, StageId::MerkleExecute),
+    (StageId::Finish, StageId::TransactionLookup),
+    (StageId::Finish, StageId::IndexStorageHistory),
+    (StageId::Finish, StageId::IndexAccountHistory),
+];
+
+/// A stage whose checkpoint is ahead of the checkpoint of one of its prerequisites, see
+/// [`STAGE_PREREQUISITES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointInconsistency {
+    /// The stage that is ahead.
+    pub stage: StageId,
+    /// The checkpoint of the stage.
+    pub checkpoint: BlockNumber,
+    /// The prerequisite the stage is ahead of.
+    pub prerequisite: StageId,
+    /// The checkpoint of the prerequisite.
+    pub prerequisite_checkpoint: BlockNumber,
+}
+
+impl fmt::Display for CheckpointInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} checkpoint {} is ahead of its prerequisite {} at {}",
+            self.stage, self.checkpoint, self.prerequisite, self.prerequisite_checkpoint
+        )
+    }
+}
+
+/// Checks that no stage checkpoint is ahead of the checkpoint of its prerequisites, see
+/// [`STAGE_PREREQUISITES`].
+///
+/// Stages without a checkpoint are treated as being at block 0. All violations are returned, not
+/// just the first one. The outer result only fails if the checkpoints can't be read.
+pub fn check_checkpoint_consistency<P: StageCheckpointReader>(
+    provider: &P,
+) -> Result<std::result::Result<(), Vec<CheckpointInconsistency>>> {
+    let checkpoint = |stage_id| -> Result<BlockNumber> {
+        Ok(provider.get_stage_checkpoint(stage_id)?.map_or(0, |checkpoint| checkpoint.block_number))
+    };
+
+    let mut inconsistencies = Vec::new();
+    for &(stage, prerequisite) in STAGE_PREREQUISITES {
+        let stage_checkpoint = checkpoint(stage)?;
+        let prerequisite_checkpoint = checkpoint(prerequisite)?;
+        if stage_checkpoint > prerequisite_checkpoint {
+            inconsistencies.push(CheckpointInconsistency {
+                stage,
+                checkpoint: stage_checkpoint,
+                prerequisite,
+                prerequisite_checkpoint,
+            });
+        }
+    }
+
+    if inconsistencies.is_empty() {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(inconsistencies))
+    }
+}
+
 /// Filters the results coming from the database.
 #[derive(Debug)]
 pub struct ListFilter {
@@ -195,3 +290,90 @@ impl ListFilter {
         self.len = len;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_provider::{
+        test_utils::create_test_provider_factory, DatabaseProviderFactory, StageCheckpointWriter,
+    };
+    use reth_stages_types::StageCheckpoint;
+
+    #[test]
+    fn missing_checkpoints_lists_unset_stages() {
+        let provider_factory = create_test_provider_factory();
+
+        {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for stage_id in [StageId::Headers, StageId::Bodies, StageId::Finish] {
+                provider_rw
+                    .save_stage_checkpoint(stage_id, StageCheckpoint::new(10))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit initial checkpoints");
+        }
+
+        let provider = provider_factory.provider().expect("provider");
+        let missing = missing_checkpoints(&provider).expect("missing checkpoints");
+
+        let expected = StageId::ALL
+            .into_iter()
+            .filter(|id| ![StageId::Headers, StageId::Bodies, StageId::Finish].contains(id))
+            .collect::<Vec<_>>();
+        assert_eq!(missing, expected);
+    }
+
+    #[test]
+    fn checkpoint_consistency() {
+        let provider_factory = create_test_provider_factory();
+
+        // an empty database is consistent
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(check_checkpoint_consistency(&provider).expect("check consistency"), Ok(()));
+        drop(provider);
+
+        let save = |checkpoints: &[(StageId, u64)]| {
+            let provider_rw = provider_factory.database_provider_rw().expect("rw provider");
+            for (stage_id, block_number) in checkpoints {
+                provider_rw
+                    .save_stage_checkpoint(*stage_id, StageCheckpoint::new(*block_number))
+                    .expect("save checkpoint");
+            }
+            provider_rw.commit().expect("commit checkpoints");
+        };
+
+        save(&StageId::ALL.map(|stage_id| (stage_id, 100)));
+        let provider = provider_factory.provider().expect("provider");
+        assert_eq!(check_checkpoint_consistency(&provider).expect("check consistency"), Ok(()));
+        drop(provider);
+
+        save(&[(StageId::Bodies, 50), (StageId::AccountHashing, 120)]);
+        let provider = provider_factory.provider().expect("provider");
+        let inconsistencies = check_checkpoint_consistency(&provider)
+            .expect("check consistency")
+            .expect_err("inconsistent checkpoints");
+        assert_eq!(
+            inconsistencies,
+            vec![
+                CheckpointInconsistency {
+                    stage: StageId::SenderRecovery,
+                    checkpoint: 100,
+                    prerequisite: StageId::Bodies,
+                    prerequisite_checkpoint: 50,
+                },
+                CheckpointInconsistency {
+                    stage: StageId::AccountHashing,
+                    checkpoint: 120,
+                    prerequisite: StageId::Execution,
+                    prerequisite_checkpoint: 100,
+                },
+                CheckpointInconsistency {
+                    stage: StageId::TransactionLookup,
+                    checkpoint: 100,
+                    prerequisite: StageId::Bodies,
+                    prerequisite_checkpoint: 50,
+                },
+            ]
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 pub use thread_priority::{self, *};
 
+use std::time::Duration;
+
 /// Runs the given closure exactly once per call site.
 ///
 /// Each invocation expands to its own `static Once`, so two `once!` calls in the same function
@@ -21,19 +23,144 @@ macro_rules! once {
 /// falls back to a moderate bump via [`ThreadPriority::Crossplatform`] (~5 nice points
 /// on unix). Failures are logged at `debug` level.
 pub fn increase_thread_priority() {
-    let thread_name = std::thread::current().name().unwrap_or("unnamed").to_string();
-    if let Err(err) = ThreadPriority::Max.set_for_current() {
-        tracing::debug!(%thread_name, ?err, "failed to set max thread priority, trying moderate bump; grant CAP_SYS_NICE to the process to enable this");
+    if !increase_thread_priority_no_fallback() {
         // Crossplatform value 62/99 ≈ nice -5 on unix.
         let fallback = ThreadPriority::Crossplatform(
             ThreadPriorityValue::try_from(62u8).expect("62 is within the valid 0..100 range"),
         );
         if let Err(err) = fallback.set_for_current() {
+            let thread_name = std::thread::current().name().unwrap_or("unnamed").to_string();
             tracing::debug!(%thread_name, ?err, "failed to set moderate thread priority");
         }
     }
 }
 
+/// Sets the current thread's priority to [`ThreadPriority::Max`], without the moderate fallback
+/// of [`increase_thread_priority`].
+///
+/// Useful on hosts where even a moderate bump is undesirable, e.g. if priorities are managed via
+/// cgroups. Returns `true` if the priority was set, failures are logged at `debug` level and
+/// leave the priority untouched.
+pub fn increase_thread_priority_no_fallback() -> bool {
+    match ThreadPriority::Max.set_for_current() {
+        Ok(()) => true,
+        Err(err) => {
+            let thread_name = std::thread::current().name().unwrap_or("unnamed").to_string();
+            tracing::debug!(%thread_name, ?err, "failed to set max thread priority; grant CAP_SYS_NICE to the process to enable this");
+            false
+        }
+    }
+}
+
+/// Priority class of a task, used to pick the priority of the thread running it, see
+/// [`apply_priority_class`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskPriorityClass {
+    /// Block execution, runs at an elevated priority.
+    Execution,
+    /// State root and trie computations, runs at an elevated priority.
+    Trie,
+    /// Networking tasks, run at the default priority.
+    Networking,
+    /// Maintenance and other background work, runs at the lowest priority.
+    Background,
+}
+
+impl TaskPriorityClass {
+    /// Returns the [`ThreadPriority`] threads of this class should run with.
+    ///
+    /// - [`Self::Execution`] and [`Self::Trie`]: [`ThreadPriority::Max`]
+    /// - [`Self::Networking`]: `Crossplatform(50)`, the default niceness of 0 on unix
+    /// - [`Self::Background`]: [`ThreadPriority::Min`], niceness 19 on unix
+    pub fn thread_priority(self) -> ThreadPriority {
+        match self {
+            Self::Execution | Self::Trie => ThreadPriority::Max,
+            Self::Networking => ThreadPriority::Crossplatform(
+                ThreadPriorityValue::try_from(50u8).expect("50 is within the valid 0..100 range"),
+            ),
+            Self::Background => ThreadPriority::Min,
+        }
+    }
+}
+
+/// Sets the priority of the current thread according to the given [`TaskPriorityClass`].
+///
+/// Elevated classes go through [`increase_thread_priority`], so they fall back to a moderate bump
+/// if the maximum priority can't be set. Failures are logged at `debug` level.
+pub fn apply_priority_class(class: TaskPriorityClass) {
+    match class {
+        TaskPriorityClass::Execution | TaskPriorityClass::Trie => increase_thread_priority(),
+        TaskPriorityClass::Networking | TaskPriorityClass::Background => {
+            if let Err(err) = class.thread_priority().set_for_current() {
+                let thread_name = std::thread::current().name().unwrap_or("unnamed").to_string();
+                tracing::debug!(%thread_name, ?class, ?err, "failed to set thread priority");
+            }
+        }
+    }
+}
+
+/// Default timeout for [`increase_thread_priority_with_timeout`].
+pub const INCREASE_THREAD_PRIORITY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Same as [`increase_thread_priority`], but gives up if the attempt doesn't complete within
+/// `timeout`, so that a slow or hanging syscall on locked-down hosts can't delay startup.
+///
+/// On Linux, the niceness of the calling thread is set from a helper thread. If the helper does
+/// not finish in time, this returns `false` and the helper is detached, so the priority may still
+/// be applied later. [`INCREASE_THREAD_PRIORITY_TIMEOUT`] is a sensible default.
+///
+/// On other platforms this falls back to [`increase_thread_priority`] without a timeout and
+/// always returns `true`.
+pub fn increase_thread_priority_with_timeout(timeout: Duration) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: gettid has no preconditions.
+        let tid = unsafe { libc::gettid() };
+        let completed = run_with_timeout(timeout, move || set_thread_niceness(tid));
+        if !completed {
+            tracing::debug!(tid, ?timeout, "timed out increasing thread priority");
+        }
+        completed
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = timeout;
+        increase_thread_priority();
+        true
+    }
+}
+
+/// Runs `f` on a helper thread and waits at most `timeout` for it to complete.
+///
+/// Returns `true` if `f` completed in time.
+#[cfg(target_os = "linux")]
+fn run_with_timeout(timeout: Duration, f: impl FnOnce() + Send + 'static) -> bool {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let spawned = std::thread::Builder::new().name("reth-priority".to_string()).spawn(move || {
+        f();
+        let _ = tx.send(());
+    });
+    if let Err(err) = spawned {
+        tracing::debug!(%err, "failed to spawn thread priority helper");
+        return false;
+    }
+    rx.recv_timeout(timeout).is_ok()
+}
+
+/// Sets the niceness of the thread with the given TID, mirroring [`increase_thread_priority`]:
+/// the maximum priority is tried first, then a moderate bump.
+#[cfg(target_os = "linux")]
+fn set_thread_niceness(tid: libc::pid_t) {
+    // SAFETY: setpriority is safe to call with any TID, it fails for invalid ones.
+    let set = |nice| unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) == 0 };
+    if !set(-20) {
+        tracing::debug!(tid, err = %std::io::Error::last_os_error(), "failed to set max thread priority, trying moderate bump; grant CAP_SYS_NICE to the process to enable this");
+        if !set(-5) {
+            tracing::debug!(tid, err = %std::io::Error::last_os_error(), "failed to set moderate thread priority");
+        }
+    }
+}
+
 /// Deprioritizes known background threads spawned by third-party libraries (`OpenTelemetry`,
 /// `tracing-appender`, `reqwest`) by scanning `/proc/<pid>/task/` for matching thread names and
 /// setting `SCHED_IDLE` scheduling policy + maximum niceness on them.
@@ -42,13 +169,43 @@ pub fn increase_thread_priority() {
 /// thread initialization or expose the TIDs, so we have to discover them after the fact by
 /// reading `/proc`.
 ///
-/// Should be called once after tracing is initialized.
+/// Threads that already run with `SCHED_IDLE` are skipped, so the scan is cheap to repeat.
 ///
-/// No-op on non-Linux platforms.
+/// Should be called once after tracing is initialized. Logs a summary at `info` level, details
+/// about individual threads are logged at `trace` level.
+///
+/// No-op on non-Linux platforms, returning an empty report.
 #[allow(clippy::missing_const_for_fn)]
-pub fn deprioritize_background_threads() {
+pub fn deprioritize_background_threads() -> DeprioritizeReport {
     #[cfg(target_os = "linux")]
-    _deprioritize_background_threads();
+    {
+        let report = _deprioritize_background_threads();
+        tracing::info!(
+            deprioritized = report.deprioritized,
+            scanned = report.scanned,
+            already_idle = report.already_idle,
+            failed = report.failed,
+            "deprioritized {} background threads out of {} scanned",
+            report.deprioritized,
+            report.scanned
+        );
+        report
+    }
+    #[cfg(not(target_os = "linux"))]
+    DeprioritizeReport::default()
+}
+
+/// Summary of a [`deprioritize_background_threads`] scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeprioritizeReport {
+    /// Number of threads of the process that were scanned.
+    pub scanned: usize,
+    /// Number of background threads that were set to `SCHED_IDLE`.
+    pub deprioritized: usize,
+    /// Number of background threads that were skipped because they were already `SCHED_IDLE`.
+    pub already_idle: usize,
+    /// Number of background threads that could not be set to `SCHED_IDLE`.
+    pub failed: usize,
 }
 
 /// Thread name prefixes to deprioritize.
@@ -56,35 +213,68 @@ pub fn deprioritize_background_threads() {
 const DEPRIORITIZE_THREAD_PREFIXES: &[&str] =
     &["OpenTelemetry.T", "tracing-appende", "reqwest-interna"];
 
+/// Returns `true` if the thread with the given TID already runs with the `SCHED_IDLE` policy.
 #[cfg(target_os = "linux")]
-fn _deprioritize_background_threads() {
-    let pid = std::process::id();
-    let task_dir = format!("/proc/{pid}/task");
+fn is_sched_idle(tid: i32) -> bool {
+    // SAFETY: sched_getscheduler is safe to call with any TID, it returns -1 for invalid ones.
+    unsafe { libc::sched_getscheduler(tid) == libc::SCHED_IDLE }
+}
 
-    let entries = match std::fs::read_dir(&task_dir) {
-        Ok(entries) => entries,
-        Err(err) => {
-            tracing::debug!(%err, "failed to read /proc task directory");
-            return;
-        }
-    };
+/// Returns the TID and name (`comm`) of every thread of the current process, sorted by TID.
+///
+/// Thread names are truncated to 15 bytes by the kernel. Useful for debugging and for verifying
+/// that threads run with the expected priority.
+///
+/// Returns an empty list on non-Linux platforms.
+#[allow(clippy::missing_const_for_fn)]
+pub fn list_process_threads() -> Vec<(i32, String)> {
+    #[cfg(target_os = "linux")]
+    {
+        let task_dir = format!("/proc/{}/task", std::process::id());
+        let entries = match std::fs::read_dir(&task_dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                tracing::debug!(%err, "failed to read /proc task directory");
+                return Vec::new();
+            }
+        };
 
-    for entry in entries.filter_map(Result::ok) {
-        let tid_str = entry.file_name();
-        let Some(tid_str) = tid_str.to_str() else { continue };
-        let Ok(tid) = tid_str.parse::<i32>() else { continue };
+        let mut threads = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let tid_str = entry.file_name();
+                let tid_str = tid_str.to_str()?;
+                let tid = tid_str.parse::<i32>().ok()?;
+                // threads may exit while scanning
+                let comm = std::fs::read_to_string(format!("{task_dir}/{tid_str}/comm")).ok()?;
+                Some((tid, comm.trim().to_string()))
+            })
+            .collect::<Vec<_>>();
+        threads.sort_unstable();
+        threads
+    }
+    #[cfg(not(target_os = "linux"))]
+    Vec::new()
+}
 
-        let comm_path = format!("{task_dir}/{tid_str}/comm");
-        let comm = match std::fs::read_to_string(&comm_path) {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-        let comm = comm.trim();
+#[cfg(target_os = "linux")]
+fn _deprioritize_background_threads() -> DeprioritizeReport {
+    let mut report = DeprioritizeReport::default();
+
+    for (tid, comm) in list_process_threads() {
+        report.scanned += 1;
+        let comm = comm.as_str();
 
         if !DEPRIORITIZE_THREAD_PREFIXES.iter().any(|prefix| comm.starts_with(prefix)) {
             continue;
         }
 
+        if is_sched_idle(tid) {
+            report.already_idle += 1;
+            tracing::trace!(tid, comm, "background thread already SCHED_IDLE");
+            continue;
+        }
+
         // SCHED_IDLE is the lowest-priority scheduling class. The kernel will only schedule these
         // threads when no other (SCHED_OTHER/SCHED_BATCH/RT) threads need the CPU.
         // SAFETY: sched_setscheduler is safe to call with a valid TID.
@@ -97,9 +287,130 @@ fn _deprioritize_background_threads() {
                     err = std::io::Error::last_os_error().to_string(),
                     "failed to set SCHED_IDLE"
                 );
+                report.failed += 1;
+                continue;
             }
         }
 
-        tracing::debug!(tid, comm, "deprioritized background thread (SCHED_IDLE)");
+        report.deprioritized += 1;
+        tracing::trace!(tid, comm, "deprioritized background thread (SCHED_IDLE)");
+    }
+
+    report
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deprioritize_reports_changed_threads() {
+        let (tid_tx, tid_rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let handle = std::thread::Builder::new()
+            .name("reqwest-internal-test".to_string())
+            .spawn(move || {
+                // SAFETY: gettid has no preconditions.
+                tid_tx.send(unsafe { libc::gettid() }).unwrap();
+                let _ = done_rx.recv();
+            })
+            .unwrap();
+        let tid = tid_rx.recv().unwrap();
+
+        let report = deprioritize_background_threads();
+
+        assert!(is_sched_idle(tid));
+        assert_eq!(report.deprioritized, 1);
+        assert_eq!(report.already_idle, 0);
+        assert_eq!(report.failed, 0);
+        assert!(report.scanned >= 2);
+
+        // a second scan skips the thread without changing it
+        let report = deprioritize_background_threads();
+        assert!(is_sched_idle(tid));
+        assert_eq!(report.deprioritized, 0);
+        assert_eq!(report.already_idle, 1);
+        assert_eq!(report.failed, 0);
+
+        drop(done_tx);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn run_with_timeout_gives_up_on_slow_set() {
+        let start = std::time::Instant::now();
+        assert!(!run_with_timeout(Duration::from_millis(10), || {
+            std::thread::sleep(Duration::from_secs(2))
+        }));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert!(run_with_timeout(Duration::from_secs(5), || {}));
+    }
+
+    #[test]
+    fn increase_thread_priority_with_timeout_returns() {
+        let start = std::time::Instant::now();
+        std::thread::spawn(|| increase_thread_priority_with_timeout(Duration::from_secs(5)))
+            .join()
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn priority_class_levels() {
+        assert_eq!(TaskPriorityClass::Execution.thread_priority(), ThreadPriority::Max);
+        assert_eq!(TaskPriorityClass::Trie.thread_priority(), ThreadPriority::Max);
+        assert_eq!(
+            TaskPriorityClass::Networking.thread_priority(),
+            ThreadPriority::Crossplatform(50u8.try_into().unwrap())
+        );
+        assert_eq!(TaskPriorityClass::Background.thread_priority(), ThreadPriority::Min);
+
+        for class in [
+            TaskPriorityClass::Execution,
+            TaskPriorityClass::Trie,
+            TaskPriorityClass::Networking,
+            TaskPriorityClass::Background,
+        ] {
+            // applied on a separate thread to leave the test thread's priority untouched
+            std::thread::spawn(move || apply_priority_class(class)).join().unwrap();
+        }
+    }
+
+    #[test]
+    fn list_threads_contains_current() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("list-threads-test".to_string())
+            .spawn(move || {
+                // SAFETY: gettid has no preconditions.
+                let tid = unsafe { libc::gettid() };
+                tx.send((tid, list_process_threads())).unwrap();
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        let (tid, threads) = rx.recv().unwrap();
+
+        // names are truncated to 15 bytes
+        assert!(threads.contains(&(tid, "list-threads-te".to_string())));
+        assert!(threads.is_sorted());
+    }
+
+    #[test]
+    fn increase_priority_no_fallback_reports_result() {
+        std::thread::spawn(|| {
+            // SAFETY: getpriority has no preconditions.
+            let niceness = || unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+            let before = niceness();
+            if increase_thread_priority_no_fallback() {
+                assert_eq!(niceness(), -20);
+            } else {
+                // restricted, the priority must be left untouched
+                assert_eq!(niceness(), before);
+            }
+        })
+        .join()
+        .unwrap();
     }
 }
@@ -183,6 +183,26 @@ export const rethCliSidebar: SidebarItem = {
                         {
                             text: "reth db stage-checkpoints set",
                             link: "/cli/reth/db/stage-checkpoints/set"
+                        },
+                        {
+                            text: "reth db stage-checkpoints from-manifest",
+                            link: "/cli/reth/db/stage-checkpoints/from-manifest"
+                        },
+                        {
+                            text: "reth db stage-checkpoints verify",
+                            link: "/cli/reth/db/stage-checkpoints/verify"
+                        },
+                        {
+                            text: "reth db stage-checkpoints record",
+                            link: "/cli/reth/db/stage-checkpoints/record"
+                        },
+                        {
+                            text: "reth db stage-checkpoints prune-horizon",
+                            link: "/cli/reth/db/stage-checkpoints/prune-horizon"
+                        },
+                        {
+                            text: "reth db stage-checkpoints assert",
+                            link: "/cli/reth/db/stage-checkpoints/assert"
                         }
                     ]
                 },
@@ -193,6 +213,21 @@ export const rethCliSidebar: SidebarItem = {
                 {
                     text: "reth db state",
                     link: "/cli/reth/db/state"
+                },
+                {
+                    text: "reth db peers",
+                    link: "/cli/reth/db/peers",
+                    collapsed: true,
+                    items: [
+                        {
+                            text: "reth db peers validate",
+                            link: "/cli/reth/db/peers/validate"
+                        },
+                        {
+                            text: "reth db peers compact",
+                            link: "/cli/reth/db/peers/compact"
+                        }
+                    ]
                 }
             ]
         },